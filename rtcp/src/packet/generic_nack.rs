@@ -0,0 +1,273 @@
+use super::{
+    header::{self, Header, PacketType},
+    Packet, SSRC_LENGTH,
+};
+
+/// The feedback message type (FMT) of the Generic NACK packets
+const GENERIC_NACK_FORMAT: u8 = 1;
+const SENDER_SSRC_OFFSET: usize = header::HEADER_LENGTH;
+const MEDIA_SSRC_OFFSET: usize = SENDER_SSRC_OFFSET + SSRC_LENGTH;
+const NACKS_OFFSET: usize = MEDIA_SSRC_OFFSET + SSRC_LENGTH;
+
+/// The length of a NACK pair in a Generic NACK packet.
+pub const NACK_PAIR_LENGTH: usize = 4;
+
+/// This structure represents a NACK pair of a Generic NACK packet: a
+/// packet identifier (PID) which is lost, followed by a bitmask of the
+/// following lost packets (BLP).
+///
+/// ```text
+///  0               1               2               3
+///  0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            PID                |             BLP               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The bit `i` of the BLP is set if the packet `PID + i + 1` is lost too.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NackPair {
+    /// The sequence number of a lost packet
+    pub packet_id: u16,
+
+    /// The bitmask of the following lost packets
+    pub lost_packets: u16,
+}
+
+impl NackPair {
+    /// Retrieves the sequence numbers of all the lost packets described by
+    /// this pair.
+    pub fn sequence_numbers(&self) -> Vec<u16> {
+        let mut sequence_numbers = vec![self.packet_id];
+        for i in 0..16 {
+            if self.lost_packets & (1 << i) > 0 {
+                sequence_numbers.push(self.packet_id.wrapping_add(i + 1));
+            }
+        }
+
+        sequence_numbers
+    }
+}
+
+/// This structure represents the RTCP Generic NACK packet, a transport
+/// layer feedback message used by a receiver to indicate the loss of RTP
+/// packets, as defined in the [RFC 4585].
+///
+/// This structure is following this data wire:
+/// ```text
+///  0               1               2               3
+///  0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P| FMT=1   |   PT=RTPFB=205|             length            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of packet sender                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of media source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            PID                |             BLP               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// :                              ...                              :
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The `length` field of the header is the length of the packet in 32-bit
+/// words minus one.
+///
+/// [RFC 4585]: https://tools.ietf.org/html/rfc4585#section-6.2.1
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GenericNack {
+    /// The parsed RTCP header of this packet
+    pub header: Header,
+
+    /// The synchronization source of the sender of this packet
+    pub sender_ssrc: u32,
+
+    /// The synchronization source of the media stream which lost packets
+    pub media_ssrc: u32,
+
+    /// The lost packets of the media stream
+    pub nacks: Vec<NackPair>,
+}
+
+impl GenericNack {
+    /// Instanciates a Generic NACK packet from a list of lost sequence
+    /// numbers, which must be given in their sequence order.
+    pub fn new(sender_ssrc: u32, media_ssrc: u32, sequence_numbers: &[u16]) -> Self {
+        let mut nacks: Vec<NackPair> = vec![];
+        for &sequence_number in sequence_numbers {
+            if let Some(pair) = nacks.last_mut() {
+                let distance = sequence_number.wrapping_sub(pair.packet_id);
+                if distance == 0 {
+                    continue;
+                } else if distance <= 16 {
+                    pair.lost_packets |= 1 << (distance - 1);
+                    continue;
+                }
+            }
+
+            nacks.push(NackPair {
+                packet_id: sequence_number,
+                lost_packets: 0,
+            });
+        }
+
+        let length = (NACKS_OFFSET + NACK_PAIR_LENGTH * nacks.len()) / 4 - 1;
+
+        Self {
+            header: Header {
+                padding: false,
+                report_count: GENERIC_NACK_FORMAT,
+                packet_type: PacketType::TransportSpecificFeedback,
+                length: length as u16,
+            },
+            sender_ssrc,
+            media_ssrc,
+            nacks,
+        }
+    }
+
+    /// Retrieves the sequence numbers of all the lost packets.
+    pub fn sequence_numbers(&self) -> Vec<u16> {
+        self.nacks
+            .iter()
+            .flat_map(|pair| pair.sequence_numbers())
+            .collect()
+    }
+}
+
+impl Packet for GenericNack {
+    fn from_raw(raw_packet: &[u8]) -> Result<Self, ()> {
+        let header = Header::from_raw(raw_packet)?;
+
+        if header.packet_type != PacketType::TransportSpecificFeedback
+            || header.report_count != GENERIC_NACK_FORMAT
+        {
+            return Err(());
+        }
+
+        let length = (header.length as usize + 1) * 4;
+        if length < NACKS_OFFSET || length > raw_packet.len() {
+            return Err(());
+        }
+
+        let ssrc = |offset: usize| {
+            u32::from_be_bytes([
+                raw_packet[offset],
+                raw_packet[offset + 1],
+                raw_packet[offset + 2],
+                raw_packet[offset + 3],
+            ])
+        };
+        let sender_ssrc = ssrc(SENDER_SSRC_OFFSET);
+        let media_ssrc = ssrc(MEDIA_SSRC_OFFSET);
+
+        let nacks = raw_packet[NACKS_OFFSET..length]
+            .chunks_exact(NACK_PAIR_LENGTH)
+            .map(|pair| NackPair {
+                packet_id: u16::from_be_bytes([pair[0], pair[1]]),
+                lost_packets: u16::from_be_bytes([pair[2], pair[3]]),
+            })
+            .collect();
+
+        Ok(Self {
+            header,
+            sender_ssrc,
+            media_ssrc,
+            nacks,
+        })
+    }
+
+    fn to_raw(&self) -> Result<Vec<u8>, ()> {
+        let mut output = Vec::with_capacity(self.length());
+
+        output.extend_from_slice(&self.header.to_raw()?);
+        output.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        output.extend_from_slice(&self.media_ssrc.to_be_bytes());
+        for pair in &self.nacks {
+            output.extend_from_slice(&pair.packet_id.to_be_bytes());
+            output.extend_from_slice(&pair.lost_packets.to_be_bytes());
+        }
+
+        Ok(output)
+    }
+
+    fn length(&self) -> usize {
+        NACKS_OFFSET + NACK_PAIR_LENGTH * self.nacks.len()
+    }
+
+    fn synchronization_sources(&self) -> Vec<u32> {
+        vec![self.sender_ssrc, self.media_ssrc]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_unmarshalls_a_generic_nack_packet() {
+        let raw = [
+            0x81u8, 0xcdu8, 0x00u8, 0x03u8, // V=2, P=0, FMT=1, PT=RTPFB, length=3
+            0x90u8, 0x2fu8, 0x9eu8, 0x2eu8, // sender SSRC=0x902f9e2e
+            0x4bu8, 0xc4u8, 0xfcu8, 0xb4u8, // media SSRC=0x4bc4fcb4
+            0xffu8, 0xfeu8, 0x00u8, 0x05u8, // PID=65534, BLP=0b101
+        ];
+
+        let packet = GenericNack::from_raw(&raw).unwrap();
+        assert_eq!(0x902f9e2e, packet.sender_ssrc);
+        assert_eq!(0x4bc4fcb4, packet.media_ssrc);
+        assert_eq!(vec![65534, 65535, 1], packet.sequence_numbers());
+        assert_eq!(raw.to_vec(), packet.to_raw().unwrap());
+    }
+
+    #[test]
+    fn it_builds_nack_pairs_from_sequence_numbers() {
+        let packet = GenericNack::new(1, 2, &[65530, 65535, 0, 10, 11, 30]);
+
+        assert_eq!(
+            vec![
+                NackPair {
+                    packet_id: 65530,
+                    lost_packets: 0x8030,
+                },
+                NackPair {
+                    packet_id: 11,
+                    lost_packets: 0,
+                },
+                NackPair {
+                    packet_id: 30,
+                    lost_packets: 0,
+                },
+            ],
+            packet.nacks
+        );
+        assert_eq!(5, packet.header.length);
+        assert_eq!(24, packet.length());
+        assert_eq!(vec![65530, 65535, 0, 10, 11, 30], packet.sequence_numbers());
+
+        let raw = packet.to_raw().unwrap();
+        assert_eq!(packet, GenericNack::from_raw(&raw).unwrap());
+    }
+
+    #[test]
+    fn it_returns_an_error_when_feedback_format_is_wrong() {
+        let raw = [
+            0x8fu8, 0xcdu8, 0x00u8, 0x02u8, // V=2, P=0, FMT=15, PT=RTPFB, length=2
+            0x90u8, 0x2fu8, 0x9eu8, 0x2eu8, // sender SSRC=0x902f9e2e
+            0x4bu8, 0xc4u8, 0xfcu8, 0xb4u8, // media SSRC=0x4bc4fcb4
+        ];
+
+        assert!(GenericNack::from_raw(&raw).is_err());
+    }
+
+    #[test]
+    fn it_returns_an_error_when_packet_is_truncated() {
+        let raw = [
+            0x81u8, 0xcdu8, 0x00u8, 0x03u8, // V=2, P=0, FMT=1, PT=RTPFB, length=3
+            0x90u8, 0x2fu8, 0x9eu8, 0x2eu8, // sender SSRC=0x902f9e2e
+            0x4bu8, 0xc4u8, 0xfcu8, 0xb4u8, // media SSRC=0x4bc4fcb4
+        ];
+
+        assert!(GenericNack::from_raw(&raw).is_err());
+    }
+}
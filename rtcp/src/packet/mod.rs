@@ -1,7 +1,9 @@
+mod generic_nack;
 mod goodbye;
 pub mod header;
 mod sender_report;
 
+pub use generic_nack::{GenericNack, NackPair, NACK_PAIR_LENGTH};
 pub use goodbye::Goodbye;
 pub use header::Header;
 pub use sender_report::SenderReport;
@@ -0,0 +1,81 @@
+//! Helpers for the Annex B byte stream format of the H.264 and H.265
+//! specifications, where the NAL units are prefixed by start codes.
+
+/// The 4 bytes start code prefixing a NAL unit
+pub const START_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+
+/// Determines the boundaries of a NAL unit of an Annex B byte stream. The
+/// boundaries are returned into a tuple defined this way: `(start, length)`.
+///
+/// If no boundaries can be found, `None` is returned.
+fn get_nal_unit_boundaries(units: &[u8], start: usize) -> Option<(usize, usize)> {
+    let mut zero_count = 0;
+
+    for (index, byte) in units[start..].iter().enumerate() {
+        match *byte {
+            0u8 => zero_count += 1,
+            1u8 => {
+                // If we've counted 2 or more zero byte, we've got our boundaries.
+                if zero_count >= 2 {
+                    return Some((start + index - zero_count, zero_count + 1));
+                }
+            }
+            _ => zero_count = 0,
+        };
+    }
+
+    None
+}
+
+/// Splits an Annex B byte stream into its NAL units, start codes excluded.
+///
+/// If the payload does not contain any start code, it is considered as a
+/// single NAL unit. Any data preceding the first start code is ignored.
+pub fn nal_units(payload: &[u8]) -> Vec<&[u8]> {
+    let mut units = Vec::new();
+    let mut boundaries = get_nal_unit_boundaries(payload, 0);
+
+    if boundaries.is_none() {
+        if !payload.is_empty() {
+            units.push(payload);
+        }
+
+        return units;
+    }
+
+    while let Some((start, length)) = boundaries {
+        let previous_start = start + length;
+        boundaries = get_nal_unit_boundaries(payload, previous_start);
+
+        let unit = if let Some(boundaries) = &boundaries {
+            &payload[previous_start..boundaries.0]
+        } else {
+            &payload[previous_start..]
+        };
+
+        if !unit.is_empty() {
+            units.push(unit);
+        }
+    }
+
+    units
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_splits_an_annexb_stream_into_nal_units() {
+        let payload = [
+            0x00, 0x00, 0x00, 0x01, 0x67, 0x42, 0x00, 0x00, 0x01, 0x68, 0xce, 0x00, 0x00, 0x01,
+            0x65, 0x88,
+        ];
+
+        let units = nal_units(&payload);
+        assert_eq!(
+            vec![&[0x67, 0x42][..], &[0x68, 0xce][..], &[0x65, 0x88][..]],
+            units
+        );
+    }
+}
@@ -0,0 +1,711 @@
+use crate::{codecs::KeyframeDetector, packet::Packet, Depacketizer, PayloadGenerator};
+
+const AGGREGATION_HEADER_SIZE: usize = 1;
+const MAX_W_ELEMENTS: usize = 3;
+
+const Z_BIT: u8 = 0x80;
+const Y_BIT: u8 = 0x40;
+const W_SHIFT: usize = 4;
+const N_BIT: u8 = 0x08;
+
+const OBU_FORBIDDEN_BIT: u8 = 0x80;
+const OBU_TYPE_SHIFT: usize = 3;
+const OBU_TYPE_MASK: u8 = 0x0f;
+const OBU_EXTENSION_FLAG: u8 = 0x04;
+const OBU_HAS_SIZE_FIELD: u8 = 0x02;
+
+/// OBU type of a sequence header
+pub const OBU_TYPE_SEQUENCE_HEADER: u8 = 1;
+
+/// OBU type of a temporal delimiter
+pub const OBU_TYPE_TEMPORAL_DELIMITER: u8 = 2;
+
+/// OBU type of a frame header
+pub const OBU_TYPE_FRAME_HEADER: u8 = 3;
+
+/// OBU type of a frame, made of a frame header and tile groups
+pub const OBU_TYPE_FRAME: u8 = 6;
+
+/// OBU type of a tile list
+pub const OBU_TYPE_TILE_LIST: u8 = 8;
+
+const REDUCED_STILL_PICTURE_HEADER: u8 = 0x08;
+const SHOW_EXISTING_FRAME: u8 = 0x80;
+const FRAME_TYPE_SHIFT: usize = 5;
+const FRAME_TYPE_MASK: u8 = 0x03;
+const KEY_FRAME: u8 = 0;
+
+/// Decodes an unsigned LEB128 value. The decoded value and the number of bytes
+/// read are returned in a tuple defined this way: `(value, length)`.
+///
+/// If the data ends before the end of the value, `None` is returned.
+pub fn read_leb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+
+    for (index, byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (index * 7);
+
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+
+    None
+}
+
+/// Encodes an unsigned LEB128 value at the end of a buffer.
+pub fn write_leb128(mut value: u64, buffer: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buffer.push(byte);
+            return;
+        }
+
+        buffer.push(byte | 0x80);
+    }
+}
+
+/// Computes the number of bytes needed to encode a LEB128 value.
+fn leb128_size(mut value: usize) -> usize {
+    let mut size = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        size += 1;
+    }
+
+    size
+}
+
+/// Splits a temporal unit in the low overhead bitstream format into its OBUs.
+///
+/// The returned OBUs have their `obu_has_size_field` cleared and their size
+/// field removed, as expected in a RTP payload. If the temporal unit is not
+/// valid, `None` is returned.
+pub fn split_obus(temporal_unit: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut obus = Vec::new();
+    let mut offset = 0;
+
+    while offset < temporal_unit.len() {
+        let header = temporal_unit[offset];
+        if header & OBU_FORBIDDEN_BIT > 0 {
+            return None;
+        }
+
+        let header_size = if header & OBU_EXTENSION_FLAG > 0 {
+            2
+        } else {
+            1
+        };
+        if offset + header_size > temporal_unit.len() {
+            return None;
+        }
+
+        // Without size field, the OBU is spanning until the end of the data
+        let mut payload_offset = offset + header_size;
+        let payload_size = if header & OBU_HAS_SIZE_FIELD > 0 {
+            let (size, length) = read_leb128(&temporal_unit[payload_offset..])?;
+            payload_offset += length;
+
+            size as usize
+        } else {
+            temporal_unit.len() - payload_offset
+        };
+
+        if payload_offset + payload_size > temporal_unit.len() {
+            return None;
+        }
+
+        let mut obu = Vec::with_capacity(header_size + payload_size);
+        obu.push(header & !OBU_HAS_SIZE_FIELD);
+        obu.extend_from_slice(&temporal_unit[offset + 1..offset + header_size]);
+        obu.extend_from_slice(&temporal_unit[payload_offset..payload_offset + payload_size]);
+        obus.push(obu);
+
+        offset = payload_offset + payload_size;
+    }
+
+    Some(obus)
+}
+
+/// Retrieves the type of an OBU from its header.
+pub fn obu_type(header: u8) -> u8 {
+    (header >> OBU_TYPE_SHIFT) & OBU_TYPE_MASK
+}
+
+/// Retrieves the payload of an OBU without size field, skipping its header.
+fn obu_payload(obu: &[u8]) -> &[u8] {
+    let header_size = if obu[0] & OBU_EXTENSION_FLAG > 0 {
+        2
+    } else {
+        1
+    };
+
+    obu.get(header_size..).unwrap_or(&[])
+}
+
+/// Checks if OBUs without size field are starting a coded video sequence,
+/// meaning that they contain a sequence header and the first frame header
+/// describes a key frame.
+fn starts_coded_video_sequence(obus: &[Vec<u8>]) -> bool {
+    let sequence_header = match obus
+        .iter()
+        .find(|obu| obu_type(obu[0]) == OBU_TYPE_SEQUENCE_HEADER)
+        .and_then(|obu| obu_payload(obu).first())
+    {
+        Some(byte) => *byte,
+        None => return false,
+    };
+
+    let frame_header = obus.iter().find(|obu| {
+        let obu_type = obu_type(obu[0]);
+        obu_type == OBU_TYPE_FRAME_HEADER || obu_type == OBU_TYPE_FRAME
+    });
+
+    match frame_header {
+        // With a reduced still picture header, every frame is a key frame
+        Some(_) if sequence_header & REDUCED_STILL_PICTURE_HEADER > 0 => true,
+        Some(obu) => obu_payload(obu).first().is_some_and(|byte| {
+            byte & SHOW_EXISTING_FRAME == 0
+                && (byte >> FRAME_TYPE_SHIFT) & FRAME_TYPE_MASK == KEY_FRAME
+        }),
+        None => false,
+    }
+}
+
+/// A RTP payload under construction, made of OBU elements.
+#[derive(Default)]
+struct PendingPayload<'a> {
+    elements: Vec<&'a [u8]>,
+    size: usize,
+    continuation: bool,
+    fragmented: bool,
+}
+
+impl<'a> PendingPayload<'a> {
+    /// Computes the size of the payload if an element of `length` bytes is
+    /// added as its last element.
+    fn size_with(&self, length: usize) -> usize {
+        AGGREGATION_HEADER_SIZE + self.size + self.length_field_size(length) + length
+    }
+
+    /// Computes the size of the length field of an element of `length` bytes
+    /// added as the last element of the payload. When the W field can be used,
+    /// the last element has no length field.
+    fn length_field_size(&self, length: usize) -> usize {
+        if self.elements.len() < MAX_W_ELEMENTS {
+            0
+        } else {
+            leb128_size(length)
+        }
+    }
+
+    fn push(&mut self, element: &'a [u8]) {
+        self.size += leb128_size(element.len()) + element.len();
+        self.elements.push(element);
+    }
+
+    /// Produces the RTP payload, with its aggregation header:
+    ///
+    /// ```text
+    /// +-+-+-+-+-+-+-+-+
+    /// |Z|Y| W |N|-|-|-|
+    /// +-+-+-+-+-+-+-+-+
+    /// ```
+    ///
+    /// When the payload has 3 elements or less, the W field is used and the
+    /// last element has no length field.
+    fn build(&self, new_coded_video_sequence: bool) -> Vec<u8> {
+        let count = self.elements.len();
+        let with_count = count <= MAX_W_ELEMENTS;
+
+        let mut payload = Vec::with_capacity(AGGREGATION_HEADER_SIZE + self.size);
+        let mut header = 0;
+        if self.continuation {
+            header |= Z_BIT;
+        }
+        if self.fragmented {
+            header |= Y_BIT;
+        }
+        if with_count {
+            header |= (count as u8) << W_SHIFT;
+        }
+        if new_coded_video_sequence {
+            header |= N_BIT;
+        }
+        payload.push(header);
+
+        for (index, element) in self.elements.iter().enumerate() {
+            if !with_count || index < count - 1 {
+                write_leb128(element.len() as u64, &mut payload);
+            }
+
+            payload.extend_from_slice(element);
+        }
+
+        payload
+    }
+}
+
+/// This payload generator is responsible to generate RTP packet's payloads
+/// from AV1 data in order to send them into a RTP stream.
+///
+/// The data must be a temporal unit in the low overhead bitstream format,
+/// and the payloads follow the [RTP Payload Format For AV1]. The temporal
+/// delimiters and the tile lists are not transmitted.
+///
+/// [RTP Payload Format For AV1]: https://aomediacodec.github.io/av1-rtp-spec/
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AV1PayloadGenerator;
+
+impl PayloadGenerator for AV1PayloadGenerator {
+    fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
+        // A payload needs at least room for its header, a length and one byte
+        if payload.is_empty() || mtu <= AGGREGATION_HEADER_SIZE + 1 {
+            return None;
+        }
+
+        let obus: Vec<Vec<u8>> = split_obus(payload)?
+            .into_iter()
+            .filter(|obu| {
+                let obu_type = obu_type(obu[0]);
+                obu_type != OBU_TYPE_TEMPORAL_DELIMITER && obu_type != OBU_TYPE_TILE_LIST
+            })
+            .collect();
+
+        // The first packet of a temporal unit with a sequence header and a key
+        // frame is starting a new coded video sequence.
+        let new_coded_video_sequence = starts_coded_video_sequence(&obus);
+
+        let mut pending = Vec::new();
+        let mut current = PendingPayload::default();
+
+        for obu in &obus {
+            let mut remaining = &obu[..];
+
+            while !remaining.is_empty() {
+                // If the remaining part of the OBU fits, we add it to the payload
+                if current.size_with(remaining.len()) <= mtu {
+                    current.push(remaining);
+                    break;
+                }
+
+                // Otherwise, we fill the payload with a fragment of the OBU. The
+                // payload may already be full, as its size is counting a length
+                // field for its last element, which it does not have.
+                let available = mtu.saturating_sub(AGGREGATION_HEADER_SIZE + current.size);
+                let mut fragment_size = available.min(remaining.len());
+                while fragment_size > 0
+                    && current.length_field_size(fragment_size) + fragment_size > available
+                {
+                    fragment_size -= 1;
+                }
+
+                if fragment_size > 0 {
+                    current.push(&remaining[..fragment_size]);
+                    current.fragmented = true;
+                    remaining = &remaining[fragment_size..];
+                }
+
+                let continuation = current.fragmented;
+                pending.push(std::mem::replace(
+                    &mut current,
+                    PendingPayload {
+                        continuation,
+                        ..Default::default()
+                    },
+                ));
+            }
+        }
+
+        if !current.elements.is_empty() {
+            pending.push(current);
+        }
+
+        let payloads: Vec<Vec<u8>> = pending
+            .iter()
+            .enumerate()
+            .map(|(index, payload)| payload.build(new_coded_video_sequence && index == 0))
+            .collect();
+
+        if !payloads.is_empty() {
+            Some(payloads)
+        } else {
+            None
+        }
+    }
+}
+
+/// This depacketizer is responsible to rebuild AV1 temporal units from RTP
+/// packet's payloads produced according to the [RTP Payload Format For AV1].
+///
+/// The temporal units are emitted in the low overhead bitstream format, each
+/// OBU having a size field, when a packet with the marker bit is received.
+/// A temporal delimiter is added at the beginning of each temporal unit. A
+/// fragmented OBU with missing packets is dropped.
+///
+/// [RTP Payload Format For AV1]: https://aomediacodec.github.io/av1-rtp-spec/
+#[derive(Clone, Debug, Default)]
+pub struct AV1Depacketizer {
+    temporal_unit: Vec<u8>,
+    fragment: Vec<u8>,
+    new_coded_video_sequence: bool,
+    keyframe: bool,
+    last_sequence_number: Option<u16>,
+}
+
+impl AV1Depacketizer {
+    /// Indicates if the last emitted temporal unit is starting a new coded
+    /// video sequence, and therefore is a keyframe.
+    pub fn is_keyframe(&self) -> bool {
+        self.keyframe
+    }
+
+    /// Appends a complete OBU to the current temporal unit, adding it its
+    /// size field back.
+    fn push_obu(&mut self, obu: &[u8]) {
+        if obu.is_empty() || obu_type(obu[0]) == OBU_TYPE_TEMPORAL_DELIMITER {
+            return;
+        }
+
+        let header_size = if obu[0] & OBU_EXTENSION_FLAG > 0 {
+            2
+        } else {
+            1
+        };
+        if obu.len() < header_size {
+            return;
+        }
+
+        self.temporal_unit.push(obu[0] | OBU_HAS_SIZE_FIELD);
+        self.temporal_unit.extend_from_slice(&obu[1..header_size]);
+        write_leb128((obu.len() - header_size) as u64, &mut self.temporal_unit);
+        self.temporal_unit.extend_from_slice(&obu[header_size..]);
+    }
+}
+
+impl Depacketizer for AV1Depacketizer {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        // A fragmented OBU can not be completed if a packet is missing
+        if let Some(last_sequence_number) = self.last_sequence_number {
+            if packet.sequence_number != last_sequence_number.wrapping_add(1) {
+                self.fragment.clear();
+            }
+        }
+        self.last_sequence_number = Some(packet.sequence_number);
+
+        let payload = &packet.payload;
+        if payload.len() > AGGREGATION_HEADER_SIZE {
+            let header = payload[0];
+            let continuation = header & Z_BIT > 0;
+            let fragmented = header & Y_BIT > 0;
+            let count = ((header >> W_SHIFT) & 0x03) as usize;
+
+            if header & N_BIT > 0 {
+                self.new_coded_video_sequence = true;
+            }
+
+            // The fragment of the previous packet is not continued
+            if !continuation {
+                self.fragment.clear();
+            }
+
+            let mut offset = AGGREGATION_HEADER_SIZE;
+            let mut index = 0;
+
+            while offset < payload.len() {
+                // With the W field, the last element has no length field
+                let size = if count > 0 && index == count - 1 {
+                    payload.len() - offset
+                } else {
+                    let (size, length) = read_leb128(&payload[offset..])?;
+                    offset += length;
+
+                    size as usize
+                };
+
+                if offset + size > payload.len() {
+                    self.fragment.clear();
+                    return None;
+                }
+
+                let element = &payload[offset..offset + size];
+                let first = index == 0;
+                offset += size;
+                index += 1;
+
+                let last = offset == payload.len();
+
+                if first && continuation {
+                    // The first fragment has been lost, the OBU can not be rebuilt
+                    if self.fragment.is_empty() {
+                        continue;
+                    }
+
+                    self.fragment.extend_from_slice(element);
+                    if !(last && fragmented) {
+                        let fragment = std::mem::take(&mut self.fragment);
+                        self.push_obu(&fragment);
+                    }
+                } else if last && fragmented {
+                    self.fragment = Vec::from(element);
+                } else {
+                    self.push_obu(element);
+                }
+            }
+        }
+
+        if !packet.marker || self.temporal_unit.is_empty() {
+            return None;
+        }
+
+        let mut temporal_unit = Vec::with_capacity(2 + self.temporal_unit.len());
+        temporal_unit.push(OBU_TYPE_TEMPORAL_DELIMITER << OBU_TYPE_SHIFT | OBU_HAS_SIZE_FIELD);
+        temporal_unit.push(0x00);
+        temporal_unit.append(&mut self.temporal_unit);
+
+        self.keyframe = self.new_coded_video_sequence;
+        self.new_coded_video_sequence = false;
+
+        Some(temporal_unit)
+    }
+
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        // The first OBU element is not the continuation of a fragment
+        payload.first().is_some_and(|header| header & Z_BIT == 0)
+    }
+
+    fn reset(&mut self) {
+        self.temporal_unit.clear();
+        self.fragment.clear();
+        self.new_coded_video_sequence = false;
+    }
+}
+
+/// This keyframe detector is responsible to detect the AV1 keyframes from
+/// the aggregation header of the packets.
+///
+/// A keyframe starts with the packet having its N bit set, which is the
+/// first packet of a coded video sequence.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AV1KeyframeDetector;
+
+impl KeyframeDetector for AV1KeyframeDetector {
+    fn is_keyframe(&self, payload: &[u8]) -> bool {
+        payload.len() > AGGREGATION_HEADER_SIZE && payload[0] & N_BIT > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(sequence_number: u16, marker: bool, payload: &[u8]) -> Packet {
+        Packet {
+            marker,
+            sequence_number,
+            payload: Vec::from(payload),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_encodes_and_decodes_leb128_values() {
+        for value in &[0u64, 1, 127, 128, 300, 16_383, 16_384, 0xffff_ffff] {
+            let mut buffer = Vec::new();
+            write_leb128(*value, &mut buffer);
+
+            assert_eq!(leb128_size(*value as usize), buffer.len());
+            assert_eq!(Some((*value, buffer.len())), read_leb128(&buffer));
+        }
+
+        assert_eq!(None, read_leb128(&[0x80, 0x80]));
+    }
+
+    #[test]
+    fn it_splits_a_temporal_unit_into_obus() {
+        let temporal_unit = [
+            0x12, 0x00, // temporal delimiter
+            0x0a, 0x02, 0xaa, 0xbb, // sequence header
+            0x36, 0x20, 0x01, 0xcc, // frame, with extension
+        ];
+
+        let obus = split_obus(&temporal_unit);
+        assert_eq!(
+            Some(vec![
+                vec![0x10],
+                vec![0x08, 0xaa, 0xbb],
+                vec![0x34, 0x20, 0xcc]
+            ]),
+            obus
+        );
+    }
+
+    #[test]
+    fn it_returns_none_when_an_obu_is_truncated() {
+        let mut generator = AV1PayloadGenerator;
+
+        assert!(generator.generate(100, &[0x0a, 0x05, 0xaa]).is_none());
+    }
+
+    #[test]
+    fn it_generates_a_single_payload() {
+        let mut generator = AV1PayloadGenerator;
+        let temporal_unit = [
+            0x12, 0x00, // temporal delimiter
+            0x0a, 0x02, 0xaa, 0xbb, // sequence header
+            0x32, 0x03, 0x01, 0x02, 0x03, // frame
+        ];
+
+        let payloads = generator.generate(100, &temporal_unit);
+        assert_eq!(
+            Some(vec![vec![
+                0x28, // Z=0, Y=0, W=2, N=1
+                0x03, 0x08, 0xaa, 0xbb, // sequence header
+                0x30, 0x01, 0x02, 0x03, // frame
+            ]]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_does_not_start_a_coded_video_sequence_without_key_frame() {
+        let mut generator = AV1PayloadGenerator;
+        let temporal_unit = [
+            0x0a, 0x02, 0x00, 0xbb, // sequence header
+            0x32, 0x02, 0x20, 0x01, // inter frame
+        ];
+
+        let payloads = generator.generate(100, &temporal_unit);
+        assert_eq!(
+            Some(vec![vec![
+                0x20, // Z=0, Y=0, W=2, N=0
+                0x03, 0x08, 0x00, 0xbb, // sequence header
+                0x30, 0x20, 0x01, // inter frame
+            ]]),
+            payloads
+        );
+
+        let temporal_unit = [
+            0x0a, 0x02, 0x00, 0xbb, // sequence header
+            0x32, 0x02, 0x10, 0x01, // key frame
+        ];
+
+        let payloads = generator.generate(100, &temporal_unit).unwrap();
+        assert_eq!(0x28, payloads[0][0]);
+    }
+
+    #[test]
+    fn it_generates_fragmented_payloads() {
+        let mut generator = AV1PayloadGenerator;
+        let temporal_unit = [0x32, 0x07, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
+
+        let payloads = generator.generate(5, &temporal_unit);
+        assert_eq!(
+            Some(vec![
+                vec![0x50, 0x30, 0x01, 0x02, 0x03],
+                vec![0x90, 0x04, 0x05, 0x06, 0x07],
+            ]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_starts_a_new_payload_when_the_current_one_is_full() {
+        let mut generator = AV1PayloadGenerator;
+        let temporal_unit = [
+            0x32, 0x03, 0x01, 0x02, 0x03, 0x32, 0x03, 0x04, 0x05, 0x06, 0x32, 0x02, 0x07, 0x08,
+        ];
+
+        let payloads = generator.generate(5, &temporal_unit);
+        assert_eq!(
+            Some(vec![
+                vec![0x10, 0x30, 0x01, 0x02, 0x03],
+                vec![0x10, 0x30, 0x04, 0x05, 0x06],
+                vec![0x10, 0x30, 0x07, 0x08],
+            ]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_uses_length_fields_for_more_than_three_elements() {
+        let mut generator = AV1PayloadGenerator;
+        let temporal_unit = [
+            0x32, 0x01, 0x01, 0x32, 0x01, 0x02, 0x32, 0x01, 0x03, 0x32, 0x01, 0x04,
+        ];
+
+        let payloads = generator.generate(100, &temporal_unit);
+        assert_eq!(
+            Some(vec![vec![
+                0x00, 0x02, 0x30, 0x01, 0x02, 0x30, 0x02, 0x02, 0x30, 0x03, 0x02, 0x30, 0x04,
+            ]]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_depacketizes_what_has_been_generated() {
+        let mut generator = AV1PayloadGenerator;
+        let mut depacketizer = AV1Depacketizer::default();
+
+        let mut temporal_unit = vec![
+            0x12, 0x00, // temporal delimiter
+            0x0a, 0x02, 0xaa, 0xbb, // sequence header
+            0x36, 0x20, 0xc8, 0x01, // frame, with extension
+        ];
+        temporal_unit.extend((0..200).map(|i| i as u8));
+
+        for mtu in &[5, 8, 20, 100, 1200] {
+            let payloads = generator.generate(*mtu, &temporal_unit).unwrap();
+            let last = payloads.len() - 1;
+
+            let mut output = None;
+            for (index, payload) in payloads.iter().enumerate() {
+                output = depacketizer.depacketize(&packet(index as u16, index == last, payload));
+            }
+
+            assert_eq!(Some(temporal_unit.clone()), output);
+            assert!(depacketizer.is_keyframe());
+        }
+    }
+
+    #[test]
+    fn it_emits_a_temporal_unit_per_marker_bit() {
+        let mut depacketizer = AV1Depacketizer::default();
+
+        assert!(depacketizer
+            .depacketize(&packet(1, false, &[0x10, 0x30, 0x01]))
+            .is_none());
+
+        let output = depacketizer.depacketize(&packet(2, true, &[0x10, 0x30, 0x02]));
+        assert_eq!(
+            Some(vec![0x12, 0x00, 0x32, 0x01, 0x01, 0x32, 0x01, 0x02]),
+            output
+        );
+        assert!(!depacketizer.is_keyframe());
+    }
+
+    #[test]
+    fn it_drops_fragmented_obus_with_missing_packets() {
+        let mut depacketizer = AV1Depacketizer::default();
+
+        assert!(depacketizer
+            .depacketize(&packet(1, false, &[0x50, 0x30, 0x01, 0x02]))
+            .is_none());
+        assert!(depacketizer
+            .depacketize(&packet(3, true, &[0x90, 0x05, 0x06]))
+            .is_none());
+    }
+
+    #[test]
+    fn it_detects_keyframes() {
+        let detector = AV1KeyframeDetector;
+
+        assert!(detector.is_keyframe(&[0x18, 0x08, 0x00, 0x00]));
+        assert!(!detector.is_keyframe(&[0x10, 0x30, 0x00]));
+        assert!(!detector.is_keyframe(&[0x08]));
+    }
+}
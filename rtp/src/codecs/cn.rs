@@ -0,0 +1,212 @@
+use crate::{
+    packet::{self, Packet},
+    packetizer::Packetizer,
+    PayloadGenerator,
+};
+
+/// The static payload type assigned to comfort noise by the [RFC 3551].
+///
+/// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-6
+pub const PAYLOAD_TYPE: u8 = 13;
+
+/// The maximum noise level, in -dBov
+const MAX_NOISE_LEVEL: u8 = 127;
+
+/// Represents a comfort noise payload, as defined in the section 3 of the
+/// [RFC 3389].
+///
+/// ```text
+///  0 1 2 3 4 5 6 7
+/// +-+-+-+-+-+-+-+-+
+/// |0|   level     |  N1  |  N2  | ... |  NM  |
+/// +-+-+-+-+-+-+-+-+
+/// ```
+///
+/// [RFC 3389]: https://tools.ietf.org/html/rfc3389#section-3
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ComfortNoise {
+    /// The level of the noise, expressed in dBov after dropping the sign.
+    pub noise_level: u8,
+
+    /// The quantized reflection coefficients describing the spectrum of the
+    /// noise, if any.
+    pub reflection_coefficients: Vec<u8>,
+}
+
+impl ComfortNoise {
+    /// Instanciates a white noise of the provided level.
+    pub fn new(noise_level: u8) -> Self {
+        Self {
+            noise_level: noise_level.min(MAX_NOISE_LEVEL),
+            reflection_coefficients: Vec::new(),
+        }
+    }
+
+    /// Parses a comfort noise payload, returning `None` if it is empty or if
+    /// the noise level is not valid.
+    pub fn from_raw(payload: &[u8]) -> Option<Self> {
+        let noise_level = *payload.first()?;
+        if noise_level > MAX_NOISE_LEVEL {
+            return None;
+        }
+
+        Some(Self {
+            noise_level,
+            reflection_coefficients: Vec::from(&payload[1..]),
+        })
+    }
+
+    /// Serializes the comfort noise into a payload.
+    pub fn to_raw(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(1 + self.reflection_coefficients.len());
+        payload.push(self.noise_level.min(MAX_NOISE_LEVEL));
+        payload.extend_from_slice(&self.reflection_coefficients);
+
+        payload
+    }
+}
+
+/// This sender adds a discontinuous transmission mode to an audio
+/// packetizer, as described in the section 4 of the [RFC 3389].
+///
+/// While the voice activity detector reports speech, the frames are
+/// packetized as usual. During a silence, a comfort noise packet is sent at
+/// its beginning and then once per `interval`, the other frames being
+/// skipped.
+///
+/// [RFC 3389]: https://tools.ietf.org/html/rfc3389#section-4
+#[derive(Clone, Copy, Debug)]
+pub struct ComfortNoiseSender {
+    /// The RTP payload type of the comfort noise packets.
+    pub payload_type: u8,
+
+    /// The minimum duration between two comfort noise packets, in RTP clock
+    /// ticks.
+    pub interval: u32,
+
+    last_timestamp: Option<u32>,
+}
+
+impl ComfortNoiseSender {
+    /// Instanciates a new sender, using the static payload type of comfort
+    /// noise.
+    pub fn new(interval: u32) -> Self {
+        Self {
+            payload_type: PAYLOAD_TYPE,
+            interval,
+            last_timestamp: None,
+        }
+    }
+
+    /// Transforms an audio frame lasting `samples` RTP clock ticks into a
+    /// list of RTP packets, depending on the voice activity.
+    ///
+    /// During a silence, the frame is replaced by the provided comfort
+    /// noise when it is due, and an empty list is returned otherwise.
+    pub fn packetize<G>(
+        &mut self,
+        packetizer: &mut Packetizer<G>,
+        data: &[u8],
+        samples: u32,
+        silent: bool,
+        noise: &ComfortNoise,
+    ) -> Option<Vec<Packet>>
+    where
+        G: PayloadGenerator + Default,
+    {
+        if !silent {
+            self.last_timestamp = None;
+            return packetizer.packetize(data, samples);
+        }
+
+        let timestamp = packetizer.timestamp();
+        let due = match self.last_timestamp {
+            Some(last_timestamp) => timestamp.wrapping_sub(last_timestamp) >= self.interval,
+            None => true,
+        };
+
+        let mut packets = Vec::new();
+        if due {
+            self.last_timestamp = Some(timestamp);
+
+            packets.push(Packet {
+                version: packet::RTP_VERSION,
+                payload_type: self.payload_type,
+                sequence_number: packetizer.sequencer_mut().next_sequence_number(),
+                timestamp,
+                ssrc: packetizer.synchronization_source,
+                payload_offset: packet::HEADER_SIZE,
+                payload: noise.to_raw(),
+                ..Default::default()
+            });
+        }
+
+        packetizer.skip(samples);
+
+        Some(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packetizer::G711Packetizer;
+
+    #[test]
+    fn it_parses_and_serializes_comfort_noise() {
+        let payload = [0x40, 0x12, 0x34, 0x56];
+        let noise = ComfortNoise::from_raw(&payload).unwrap();
+
+        assert_eq!(64, noise.noise_level);
+        assert_eq!(vec![0x12, 0x34, 0x56], noise.reflection_coefficients);
+        assert_eq!(payload.to_vec(), noise.to_raw());
+
+        assert_eq!(vec![0x7f], ComfortNoise::new(200).to_raw());
+        assert!(ComfortNoise::from_raw(&[]).is_none());
+        assert!(ComfortNoise::from_raw(&[0x80]).is_none());
+    }
+
+    #[test]
+    fn it_sends_comfort_noise_during_silences() {
+        let mut packetizer = G711Packetizer::new(1500, 0, 0x1234abcd);
+        let mut sender = ComfortNoiseSender::new(480);
+        let noise = ComfortNoise::new(64);
+        let frame = [0xffu8; 160];
+
+        let speech = sender
+            .packetize(&mut packetizer, &frame, 160, false, &noise)
+            .unwrap();
+        assert_eq!(0, speech[0].payload_type);
+
+        // A comfort noise packet is sent every three frames
+        let mut silence = Vec::new();
+        for _ in 0..7 {
+            silence.push(
+                sender
+                    .packetize(&mut packetizer, &frame, 160, true, &noise)
+                    .unwrap(),
+            );
+        }
+
+        let counts: Vec<_> = silence.iter().map(|packets| packets.len()).collect();
+        assert_eq!(vec![1, 0, 0, 1, 0, 0, 1], counts);
+        assert_eq!(PAYLOAD_TYPE, silence[0][0].payload_type);
+        assert_eq!(vec![0x40], silence[0][0].payload);
+        assert_eq!(
+            160,
+            silence[0][0].timestamp.wrapping_sub(speech[0].timestamp)
+        );
+        assert_eq!(
+            speech[0].sequence_number.wrapping_add(1),
+            silence[0][0].sequence_number
+        );
+
+        let speech = sender
+            .packetize(&mut packetizer, &frame, 160, false, &noise)
+            .unwrap();
+        assert_eq!(
+            160 * 7,
+            speech[0].timestamp.wrapping_sub(silence[0][0].timestamp)
+        );
+    }
+}
@@ -0,0 +1,898 @@
+use crate::{
+    codecs::ulpfec,
+    packet::{self, Packet},
+    Sequencer,
+};
+use std::collections::VecDeque;
+
+/// The size of the fixed part of the FlexFEC header
+const FEC_HEADER_SIZE: usize = 8;
+
+/// The maximum number of SSRCs protected by a FlexFEC packet, as they are
+/// listed in its CSRC list
+const MAX_PROTECTED_SSRCS: usize = 15;
+
+/// The size of the protected header fields of a packet, laid out like the
+/// beginning of the FlexFEC header
+const BIT_STRING_HEADER_SIZE: usize = 8;
+
+/// The number of packets which can be protected with each part of a
+/// flexible mask
+const MASK_PARTS: [usize; 3] = [15, 31, 64];
+
+/// The number of packets of a SSRC which can be protected with a flexible
+/// mask, from `SN base` to `SN base + 109`
+pub const MAX_MASK_SIZE: usize = 110;
+
+/// The number of media packets kept by the decoder to recover the others
+const MEDIA_HISTORY_SIZE: usize = 512;
+
+/// The number of FEC packets kept by the decoder while they can not be
+/// used yet
+const FEC_HISTORY_SIZE: usize = 64;
+
+const RETRANSMISSION_MASK: u8 = 0x80;
+const FIXED_MASK: u8 = 0x40;
+const RECOVERY_MASK: u8 = 0x3f;
+
+/// Describes the packets of a SSRC protected by a FlexFEC packet, relatively
+/// to its SN base.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FlexfecMask {
+    /// A flexible mask, the bit `i` standing for the sequence number
+    /// `SN base + i`.
+    Flexible(u128),
+
+    /// A row of `length` consecutive packets (`L > 0` and `D <= 1`).
+    Row {
+        /// The number of packets of the row.
+        length: u8,
+
+        /// Indicates if column FEC packets are protecting the same packets
+        /// (`D = 1`), with a 2-D protection.
+        columns_follow: bool,
+    },
+
+    /// A column of `rows` packets, taken every `columns` packets (`L > 0`
+    /// and `D > 1`).
+    Column {
+        /// The number of columns of the protected block.
+        columns: u8,
+
+        /// The number of rows of the protected block.
+        rows: u8,
+    },
+}
+
+impl FlexfecMask {
+    /// Retrieves the offsets, relatively to the SN base, of the protected
+    /// packets.
+    pub fn offsets(&self) -> Vec<u16> {
+        match *self {
+            Self::Flexible(mask) => (0..MAX_MASK_SIZE as u16)
+                .filter(|offset| mask & (1 << offset) > 0)
+                .collect(),
+            Self::Row { length, .. } => (0..length as u16).collect(),
+            Self::Column { columns, rows } => {
+                (0..rows as u16).map(|row| row * columns as u16).collect()
+            }
+        }
+    }
+
+    fn is_fixed(&self) -> bool {
+        !matches!(self, Self::Flexible(_))
+    }
+}
+
+/// Describes the packets of a SSRC protected by a FlexFEC packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FlexfecStream {
+    /// The SSRC of the protected packets, listed in the CSRC list of the
+    /// FlexFEC packet (`CSRC_i`).
+    pub ssrc: u32,
+
+    /// The lowest sequence number of the protected packets (`SN base_i`).
+    pub sequence_number_base: u16,
+
+    /// The protected packets (`Mask`, or `L` and `D`).
+    pub mask: FlexfecMask,
+}
+
+impl FlexfecStream {
+    /// Retrieves the sequence numbers of the protected packets.
+    pub fn sequence_numbers(&self) -> Vec<u16> {
+        self.mask
+            .offsets()
+            .into_iter()
+            .map(|offset| self.sequence_number_base.wrapping_add(offset))
+            .collect()
+    }
+}
+
+/// Represents the payload of a FlexFEC packet, as defined in the section
+/// 4.2 of the [RFC 8627].
+///
+/// The protected SSRCs are listed in the CSRC list of the FlexFEC packet,
+/// and the FEC header is following this wire:
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|F|P|X|  CC   |M| PT recovery |        length recovery        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          TS recovery                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           SN base_i           |  k + Mask or L and D (F = 1)  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The `SN base_i` field and its mask are repeated for each protected SSRC,
+/// in the order of the CSRC list. A flexible mask (`F = 0`) is made of up
+/// to three parts of 15, 31 and 64 bits, each of the first two parts being
+/// preceded by a `k` bit set when it is the last one.
+///
+/// [RFC 8627]: https://tools.ietf.org/html/rfc8627#section-4.2
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FlexfecPayload {
+    /// The recovery bits of the `P`, `X` and `CC` fields.
+    pub recovery_flags: u8,
+
+    /// The recovery bits of the `M` and `PT` fields.
+    pub recovery_payload_type: u8,
+
+    /// The recovery bits of the lengths (`length recovery`).
+    pub length_recovery: u16,
+
+    /// The recovery bits of the timestamps (`TS recovery`).
+    pub timestamp_recovery: u32,
+
+    /// The protected packets of each SSRC.
+    pub streams: Vec<FlexfecStream>,
+
+    /// The recovery bits of the packets' data (repair payload).
+    pub payload: Vec<u8>,
+}
+
+impl FlexfecPayload {
+    /// Parses the payload of a FlexFEC packet protecting the SSRCs of its
+    /// CSRC list, returning `None` if it is malformed or if it is a
+    /// retransmission.
+    pub fn from_raw(payload: &[u8], ssrcs: &[u32]) -> Option<Self> {
+        if payload.len() < FEC_HEADER_SIZE
+            || payload[0] & RETRANSMISSION_MASK > 0
+            || ssrcs.is_empty()
+        {
+            return None;
+        }
+
+        let fixed = payload[0] & FIXED_MASK > 0;
+        let mut offset = FEC_HEADER_SIZE;
+        let mut streams = Vec::with_capacity(ssrcs.len());
+
+        for ssrc in ssrcs {
+            let header = payload.get(offset..offset + 4)?;
+            let sequence_number_base = u16::from_be_bytes([header[0], header[1]]);
+
+            let mask = if fixed {
+                offset += 4;
+
+                match (header[2], header[3]) {
+                    (0, _) => return None,
+                    (length, depth @ 0..=1) => FlexfecMask::Row {
+                        length,
+                        columns_follow: depth == 1,
+                    },
+                    (columns, rows) => FlexfecMask::Column { columns, rows },
+                }
+            } else {
+                offset += 2;
+                let (mask, length) = Self::read_mask(&payload[offset..])?;
+                offset += length;
+
+                FlexfecMask::Flexible(mask)
+            };
+
+            streams.push(FlexfecStream {
+                ssrc: *ssrc,
+                sequence_number_base,
+                mask,
+            });
+        }
+
+        Some(Self {
+            recovery_flags: payload[0] & RECOVERY_MASK,
+            recovery_payload_type: payload[1],
+            length_recovery: u16::from_be_bytes([payload[2], payload[3]]),
+            timestamp_recovery: u32::from_be_bytes([
+                payload[4], payload[5], payload[6], payload[7],
+            ]),
+            streams,
+            payload: Vec::from(&payload[offset..]),
+        })
+    }
+
+    /// Serializes the content into the payload of a FlexFEC packet. The
+    /// fixed masks are used when all the streams have one. The SSRCs of the
+    /// streams must be sent in the CSRC list of the packet, in the same
+    /// order, as given by [`ssrcs`](Self::ssrcs).
+    ///
+    /// `None` is returned if there is no stream or more than 15, or if a
+    /// fixed mask must be written as a flexible one while it is protecting
+    /// packets beyond `SN base + 109`.
+    pub fn to_raw(&self) -> Option<Vec<u8>> {
+        if self.streams.is_empty() || self.streams.len() > MAX_PROTECTED_SSRCS {
+            return None;
+        }
+
+        let fixed = self.streams.iter().all(|stream| stream.mask.is_fixed());
+
+        let mut payload = Vec::with_capacity(FEC_HEADER_SIZE + 16 * self.streams.len());
+        payload.push(self.recovery_flags & RECOVERY_MASK | if fixed { FIXED_MASK } else { 0 });
+        payload.push(self.recovery_payload_type);
+        payload.extend_from_slice(&self.length_recovery.to_be_bytes());
+        payload.extend_from_slice(&self.timestamp_recovery.to_be_bytes());
+
+        for stream in &self.streams {
+            payload.extend_from_slice(&stream.sequence_number_base.to_be_bytes());
+
+            match stream.mask {
+                FlexfecMask::Row {
+                    length,
+                    columns_follow,
+                } if fixed => payload.extend_from_slice(&[length, columns_follow as u8]),
+                FlexfecMask::Column { columns, rows } if fixed => {
+                    payload.extend_from_slice(&[columns, rows])
+                }
+                mask => {
+                    let mut flexible = 0u128;
+                    for offset in mask.offsets() {
+                        if offset as usize >= MAX_MASK_SIZE {
+                            return None;
+                        }
+                        flexible |= 1 << offset;
+                    }
+
+                    Self::write_mask(&mut payload, flexible);
+                }
+            }
+        }
+
+        payload.extend_from_slice(&self.payload);
+
+        Some(payload)
+    }
+
+    /// Reads a flexible mask. The mask and its length are returned in a
+    /// tuple defined this way: `(mask, length)`.
+    fn read_mask(data: &[u8]) -> Option<(u128, usize)> {
+        let mut mask = 0u128;
+        let mut offset = 0;
+        let mut position = 0;
+
+        for (index, size) in MASK_PARTS.iter().enumerate() {
+            let length = size.div_ceil(8);
+            let mut part = 0u64;
+            for byte in data.get(offset..offset + length)? {
+                part = (part << 8) | *byte as u64;
+            }
+            offset += length;
+
+            // The last part has no k bit
+            let last = index == MASK_PARTS.len() - 1 || part >> size > 0;
+
+            for bit in 0..*size {
+                if part & (1 << (size - 1 - bit)) > 0 {
+                    mask |= 1 << (position + bit);
+                }
+            }
+            position += size;
+
+            if last {
+                break;
+            }
+        }
+
+        Some((mask, offset))
+    }
+
+    /// Writes a flexible mask with as few parts as possible.
+    fn write_mask(payload: &mut Vec<u8>, mask: u128) {
+        let mut position = 0;
+
+        for (index, size) in MASK_PARTS.iter().enumerate() {
+            let last = index == MASK_PARTS.len() - 1 || mask >> (position + size) == 0;
+
+            let mut part = 0u64;
+            for bit in 0..*size {
+                if mask & (1 << (position + bit)) > 0 {
+                    part |= 1 << (size - 1 - bit);
+                }
+            }
+
+            if last && index < MASK_PARTS.len() - 1 {
+                part |= 1 << size;
+            }
+
+            let length = size.div_ceil(8);
+            payload.extend_from_slice(&part.to_be_bytes()[8 - length..]);
+            position += size;
+
+            if last {
+                break;
+            }
+        }
+    }
+
+    /// Retrieves the protected SSRCs, to send in the CSRC list of the
+    /// FlexFEC packet.
+    pub fn ssrcs(&self) -> Vec<u32> {
+        self.streams.iter().map(|stream| stream.ssrc).collect()
+    }
+
+    /// Retrieves the SSRC and the sequence number of the protected packets.
+    fn protected_packets(&self) -> Vec<(u32, u16)> {
+        self.streams
+            .iter()
+            .flat_map(|stream| {
+                stream
+                    .sequence_numbers()
+                    .into_iter()
+                    .map(move |sequence_number| (stream.ssrc, sequence_number))
+            })
+            .collect()
+    }
+}
+
+/// This encoder is responsible to generate FlexFEC packets, as stated in the
+/// [RFC 8627].
+///
+/// The FEC packets are sent into their own RTP stream, with their own SSRC
+/// and sequence numbers. They can protect the packets of several SSRCs with
+/// flexible masks, or the rows and the columns of blocks of packets.
+///
+/// [RFC 8627]: https://tools.ietf.org/html/rfc8627
+#[derive(Clone, Debug)]
+pub struct FlexfecEncoder {
+    /// The RTP payload type negotiated for FlexFEC.
+    pub payload_type: u8,
+
+    /// The synchronization source (SSRC) identifier of the FEC stream.
+    pub synchronization_source: u32,
+
+    sequencer: Sequencer,
+}
+
+impl FlexfecEncoder {
+    /// Instanciates a new encoder for a FEC stream.
+    pub fn new(payload_type: u8, ssrc: u32) -> Self {
+        Self {
+            payload_type,
+            synchronization_source: ssrc,
+            sequencer: Sequencer::new(),
+        }
+    }
+
+    /// Generates a FEC packet protecting the provided packets, which can
+    /// belong to several SSRCs, with flexible masks.
+    ///
+    /// `None` is returned if the packets are belonging to more than 15
+    /// SSRCs, if the packets of a SSRC are spanning more than 110 sequence
+    /// numbers, or if a packet can not be marshalled.
+    pub fn protect(&mut self, packets: &[Packet]) -> Option<Packet> {
+        // The SN base of each SSRC is its oldest sequence number
+        let mut bases: Vec<(u32, u16)> = Vec::new();
+        for packet in packets {
+            match bases.iter_mut().find(|(ssrc, _)| *ssrc == packet.ssrc) {
+                Some((_, base)) => {
+                    if (packet.sequence_number.wrapping_sub(*base) as i16) < 0 {
+                        *base = packet.sequence_number;
+                    }
+                }
+                None => bases.push((packet.ssrc, packet.sequence_number)),
+            }
+        }
+
+        let mut streams = Vec::with_capacity(bases.len());
+        for (ssrc, base) in bases {
+            let mut mask = 0u128;
+            for packet in packets.iter().filter(|packet| packet.ssrc == ssrc) {
+                let offset = packet.sequence_number.wrapping_sub(base) as usize;
+                if offset >= MAX_MASK_SIZE {
+                    return None;
+                }
+
+                mask |= 1 << offset;
+            }
+
+            streams.push(FlexfecStream {
+                ssrc,
+                sequence_number_base: base,
+                mask: FlexfecMask::Flexible(mask),
+            });
+        }
+
+        self.encode(packets, streams)
+    }
+
+    /// Generates a FEC packet per row of `columns` consecutive packets (1-D
+    /// protection), for each SSRC of the provided packets.
+    ///
+    /// The packets of a SSRC must have consecutive sequence numbers, and
+    /// their last row can be shorter than the others.
+    pub fn protect_rows(&mut self, packets: &[Packet], columns: u8) -> Option<Vec<Packet>> {
+        self.encode_rows(packets, columns, false)
+    }
+
+    /// Generates a FEC packet per row of `columns` consecutive packets, for
+    /// each SSRC of the provided packets, indicating if column FEC packets
+    /// are following.
+    fn encode_rows(
+        &mut self,
+        packets: &[Packet],
+        columns: u8,
+        columns_follow: bool,
+    ) -> Option<Vec<Packet>> {
+        if columns == 0 {
+            return None;
+        }
+
+        let mut output = Vec::new();
+        for group in Self::group(packets)? {
+            for row in group.chunks(columns as usize) {
+                let stream = FlexfecStream {
+                    ssrc: row[0].ssrc,
+                    sequence_number_base: row[0].sequence_number,
+                    mask: FlexfecMask::Row {
+                        length: row.len() as u8,
+                        columns_follow,
+                    },
+                };
+                let row: Vec<_> = row.iter().map(|packet| (*packet).clone()).collect();
+
+                output.push(self.encode(&row, vec![stream])?);
+            }
+        }
+
+        Some(output)
+    }
+
+    /// Generates a FEC packet per row and per column of blocks of
+    /// `columns * rows` consecutive packets (2-D protection), for each SSRC
+    /// of the provided packets.
+    ///
+    /// The packets of a SSRC must have consecutive sequence numbers, and
+    /// they must fill complete blocks.
+    pub fn protect_blocks(
+        &mut self,
+        packets: &[Packet],
+        columns: u8,
+        rows: u8,
+    ) -> Option<Vec<Packet>> {
+        let size = columns as usize * rows as usize;
+        if rows < 2 || size == 0 {
+            return None;
+        }
+
+        let mut output = Vec::new();
+        for group in Self::group(packets)? {
+            if group.len() % size != 0 {
+                return None;
+            }
+
+            for block in group.chunks(size) {
+                let block: Vec<_> = block.iter().map(|packet| (*packet).clone()).collect();
+                output.extend(self.encode_rows(&block, columns, true)?);
+
+                for column in 0..columns as usize {
+                    let protected: Vec<_> = block
+                        .iter()
+                        .skip(column)
+                        .step_by(columns as usize)
+                        .cloned()
+                        .collect();
+                    let stream = FlexfecStream {
+                        ssrc: block[column].ssrc,
+                        sequence_number_base: block[column].sequence_number,
+                        mask: FlexfecMask::Column { columns, rows },
+                    };
+
+                    output.push(self.encode(&protected, vec![stream])?);
+                }
+            }
+        }
+
+        Some(output)
+    }
+
+    /// Groups the packets by SSRC, checking that their sequence numbers are
+    /// consecutive.
+    fn group(packets: &[Packet]) -> Option<Vec<Vec<&Packet>>> {
+        let mut groups: Vec<Vec<&Packet>> = Vec::new();
+
+        for packet in packets {
+            match groups.iter_mut().find(|group| group[0].ssrc == packet.ssrc) {
+                Some(group) => {
+                    let last = group[group.len() - 1].sequence_number;
+                    if packet.sequence_number != last.wrapping_add(1) {
+                        return None;
+                    }
+
+                    group.push(packet);
+                }
+                None => groups.push(vec![packet]),
+            }
+        }
+
+        Some(groups)
+    }
+
+    /// Generates a FEC packet protecting the provided packets, described by
+    /// the provided streams.
+    fn encode(&mut self, packets: &[Packet], streams: Vec<FlexfecStream>) -> Option<Packet> {
+        let raws = packets
+            .iter()
+            .map(|packet| packet.to_raw().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let raws: Vec<_> = raws.iter().map(|raw| &raw[..]).collect();
+
+        let mut bits = ulpfec::protect(&raws);
+        let payload = FlexfecPayload {
+            recovery_flags: bits[0] & RECOVERY_MASK,
+            recovery_payload_type: bits[1],
+            length_recovery: u16::from_be_bytes([bits[2], bits[3]]),
+            timestamp_recovery: u32::from_be_bytes([bits[4], bits[5], bits[6], bits[7]]),
+            streams,
+            payload: bits.split_off(BIT_STRING_HEADER_SIZE),
+        };
+
+        Some(Packet {
+            version: packet::RTP_VERSION,
+            payload_type: self.payload_type,
+            sequence_number: self.sequencer.next_sequence_number(),
+            timestamp: packets.iter().map(|packet| packet.timestamp).max()?,
+            ssrc: self.synchronization_source,
+            csrc: payload.ssrcs(),
+            payload_offset: packet::HEADER_SIZE,
+            payload: payload.to_raw()?,
+            ..Default::default()
+        })
+    }
+}
+
+/// This decoder is responsible to recover the lost media packets from the
+/// received FlexFEC packets and the received media packets.
+///
+/// The FEC packets are identified by the SSRC of their stream. A FEC packet
+/// is able to recover a media packet once all the other packets it protects
+/// have been received, the recovered packets being used in turn to recover
+/// the other ones.
+#[derive(Clone, Debug)]
+pub struct FlexfecDecoder {
+    /// The synchronization source (SSRC) identifier of the FEC stream.
+    pub synchronization_source: u32,
+
+    media: VecDeque<((u32, u16), Vec<u8>)>,
+    fec: VecDeque<FlexfecPayload>,
+}
+
+impl FlexfecDecoder {
+    /// Instanciates a new decoder for a FEC stream.
+    pub fn new(ssrc: u32) -> Self {
+        Self {
+            synchronization_source: ssrc,
+            media: VecDeque::with_capacity(MEDIA_HISTORY_SIZE),
+            fec: VecDeque::with_capacity(FEC_HISTORY_SIZE),
+        }
+    }
+
+    /// Handles a received packet, either a media or a FEC one, returning the
+    /// media packets it allows to recover.
+    pub fn decode(&mut self, packet: &Packet) -> Vec<Packet> {
+        if packet.ssrc == self.synchronization_source {
+            let payload = match FlexfecPayload::from_raw(&packet.payload, &packet.csrc) {
+                Some(payload) => payload,
+                None => return Vec::new(),
+            };
+
+            if self.fec.len() == FEC_HISTORY_SIZE {
+                self.fec.pop_front();
+            }
+            self.fec.push_back(payload);
+        } else if let Ok(raw) = packet.to_raw() {
+            self.insert((packet.ssrc, packet.sequence_number), raw);
+        }
+
+        self.recover()
+    }
+
+    /// Stores a media packet, if it has not been received yet.
+    fn insert(&mut self, key: (u32, u16), raw: Vec<u8>) {
+        if self.find(key).is_some() {
+            return;
+        }
+
+        if self.media.len() == MEDIA_HISTORY_SIZE {
+            self.media.pop_front();
+        }
+        self.media.push_back((key, raw));
+    }
+
+    fn find(&self, key: (u32, u16)) -> Option<&[u8]> {
+        self.media
+            .iter()
+            .find(|(candidate, _)| *candidate == key)
+            .map(|(_, raw)| &raw[..])
+    }
+
+    /// Recovers as many packets as possible with the stored FEC packets.
+    fn recover(&mut self) -> Vec<Packet> {
+        let mut recovered = Vec::new();
+
+        loop {
+            let mut progress = false;
+            let mut index = 0;
+
+            while index < self.fec.len() {
+                let payload = &self.fec[index];
+                let protected = payload.protected_packets();
+                let missing: Vec<_> = protected
+                    .iter()
+                    .filter(|key| self.find(**key).is_none())
+                    .collect();
+
+                match missing.len() {
+                    // The FEC packet is useless now
+                    0 => {
+                        self.fec.remove(index);
+                        continue;
+                    }
+                    1 => {
+                        let (ssrc, sequence_number) = *missing[0];
+                        let packets: Vec<_> =
+                            protected.iter().filter_map(|key| self.find(*key)).collect();
+
+                        let mut bits =
+                            Vec::with_capacity(BIT_STRING_HEADER_SIZE + payload.payload.len());
+                        bits.push(payload.recovery_flags);
+                        bits.push(payload.recovery_payload_type);
+                        bits.extend_from_slice(&payload.length_recovery.to_be_bytes());
+                        bits.extend_from_slice(&payload.timestamp_recovery.to_be_bytes());
+                        bits.extend_from_slice(&payload.payload);
+
+                        let packet = ulpfec::recover(bits, &packets, sequence_number, ssrc);
+                        self.fec.remove(index);
+
+                        if let Some(packet) = packet {
+                            if let Some(raw) = &packet.raw {
+                                self.insert((ssrc, sequence_number), raw.clone());
+                            }
+
+                            recovered.push(packet);
+                            progress = true;
+                        }
+                        continue;
+                    }
+                    _ => index += 1,
+                }
+            }
+
+            if !progress {
+                break;
+            }
+        }
+
+        recovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEC_SSRC: u32 = 0xfecfec00;
+
+    fn media_packets(ssrc: u32, count: usize) -> Vec<Packet> {
+        (0..count)
+            .map(|index| {
+                let mut packet = Packet {
+                    version: packet::RTP_VERSION,
+                    marker: index % 4 == 3,
+                    payload_type: 96,
+                    sequence_number: 0xfff0_u16.wrapping_add(index as u16),
+                    timestamp: 3000 * (index as u32 / 4),
+                    ssrc,
+                    payload_offset: packet::HEADER_SIZE,
+                    payload: vec![(ssrc as usize + index) as u8; 20 + (index * 13) % 50],
+                    ..Default::default()
+                };
+
+                if index % 5 == 1 {
+                    packet.csrc = vec![0xdeadbeef, ssrc];
+                }
+
+                packet
+            })
+            .collect()
+    }
+
+    /// Checks that the lost packets have been recovered, and that the
+    /// recovered packets, including the ones recovered before their
+    /// arrival, are bit-exact.
+    fn check_recovered(packets: &[Packet], recovered: &[Packet], lost: &[usize]) {
+        for index in lost {
+            let original = &packets[*index];
+
+            assert!(recovered.iter().any(|packet| {
+                packet.ssrc == original.ssrc && packet.sequence_number == original.sequence_number
+            }));
+        }
+
+        for packet in recovered {
+            let original = packets
+                .iter()
+                .find(|original| {
+                    packet.ssrc == original.ssrc
+                        && packet.sequence_number == original.sequence_number
+                })
+                .unwrap();
+
+            assert_eq!(original.to_raw().unwrap(), packet.to_raw().unwrap());
+        }
+    }
+
+    #[test]
+    fn it_parses_and_writes_fec_payloads() {
+        let payload = FlexfecPayload {
+            recovery_flags: 0x12,
+            recovery_payload_type: 0xe0,
+            length_recovery: 0x1234,
+            timestamp_recovery: 0xdeadbeef,
+            streams: vec![
+                FlexfecStream {
+                    ssrc: 1,
+                    sequence_number_base: 100,
+                    mask: FlexfecMask::Flexible(0b101),
+                },
+                FlexfecStream {
+                    ssrc: 2,
+                    sequence_number_base: 200,
+                    mask: FlexfecMask::Flexible(1 << 109 | 1 << 20 | 1),
+                },
+            ],
+            payload: vec![0x01, 0x02, 0x03],
+        };
+
+        let raw = payload.to_raw().unwrap();
+        assert_eq!(vec![1, 2], payload.ssrcs());
+        assert_eq!(0x12, raw[0]);
+        // A single part mask with its k bit set
+        assert_eq!([0x00, 0x64, 0xd0, 0x00], raw[8..12]);
+        // The second mask is using the three parts, the last one ending
+        // with the bit 109
+        assert_eq!([0x00, 0xc8, 0x40, 0x00, 0x02, 0x00], raw[12..18]);
+        assert_eq!(0x01, raw[27]);
+        assert_eq!(FEC_HEADER_SIZE + 4 + 16 + 3, raw.len());
+        assert_eq!(Some(payload), FlexfecPayload::from_raw(&raw, &[1, 2]));
+
+        let fixed = FlexfecPayload {
+            streams: vec![FlexfecStream {
+                ssrc: 1,
+                sequence_number_base: 100,
+                mask: FlexfecMask::Column {
+                    columns: 4,
+                    rows: 3,
+                },
+            }],
+            ..Default::default()
+        };
+        let raw = fixed.to_raw().unwrap();
+        assert_eq!(FIXED_MASK, raw[0]);
+        assert_eq!([0x00, 0x64, 4, 3], raw[8..12]);
+        assert_eq!(vec![100, 104, 108], fixed.streams[0].sequence_numbers());
+        assert_eq!(Some(fixed), FlexfecPayload::from_raw(&raw, &[1]));
+    }
+
+    #[test]
+    fn it_recovers_packets_of_several_ssrcs_with_flexible_masks() {
+        let mut packets = media_packets(0x1111, 4);
+        packets.extend(media_packets(0x2222, 3));
+
+        let mut encoder = FlexfecEncoder::new(118, FEC_SSRC);
+        let mut decoder = FlexfecDecoder::new(FEC_SSRC);
+
+        let fec = encoder.protect(&packets[..5]).unwrap();
+        assert_eq!(FEC_SSRC, fec.ssrc);
+        assert_eq!(vec![0x1111, 0x2222], fec.csrc);
+        assert_eq!(
+            2,
+            FlexfecPayload::from_raw(&fec.payload, &fec.csrc)
+                .unwrap()
+                .streams
+                .len()
+        );
+        let second = encoder.protect(&packets[4..]).unwrap();
+        assert_eq!(fec.sequence_number.wrapping_add(1), second.sequence_number);
+
+        let mut recovered = Vec::new();
+        for (index, packet) in packets.iter().enumerate() {
+            if index != 2 && index != 6 {
+                recovered.extend(decoder.decode(packet));
+            }
+        }
+        assert!(recovered.is_empty());
+
+        recovered.extend(decoder.decode(&fec));
+        recovered.extend(decoder.decode(&second));
+        check_recovered(&packets, &recovered, &[2, 6]);
+    }
+
+    #[test]
+    fn it_recovers_packets_with_row_protection() {
+        let packets = media_packets(0x1111, 10);
+        let mut encoder = FlexfecEncoder::new(118, FEC_SSRC);
+        let mut decoder = FlexfecDecoder::new(FEC_SSRC);
+
+        let fec = encoder.protect_rows(&packets, 4).unwrap();
+        assert_eq!(3, fec.len());
+        // No column packets are following the row ones
+        assert_eq!(0x00, fec[0].payload[11]);
+
+        let lost = [1, 6, 8];
+        let mut recovered = Vec::new();
+        for (index, packet) in packets.iter().enumerate() {
+            if !lost.contains(&index) {
+                recovered.extend(decoder.decode(packet));
+            }
+        }
+        for fec in &fec {
+            recovered.extend(decoder.decode(fec));
+        }
+
+        check_recovered(&packets, &recovered, &lost);
+
+        // The sequence numbers must be consecutive
+        assert!(encoder
+            .protect_rows(&[packets[0].clone(), packets[2].clone()], 4)
+            .is_none());
+    }
+
+    #[test]
+    fn it_recovers_packets_with_block_protection() {
+        let packets = media_packets(0x1111, 12);
+        let mut encoder = FlexfecEncoder::new(118, FEC_SSRC);
+        let mut decoder = FlexfecDecoder::new(FEC_SSRC);
+
+        let fec = encoder.protect_blocks(&packets, 4, 3).unwrap();
+        assert_eq!(3 + 4, fec.len());
+
+        // The row packets are announcing the column ones
+        let row = FlexfecPayload::from_raw(&fec[0].payload, &fec[0].csrc).unwrap();
+        assert_eq!(
+            FlexfecMask::Row {
+                length: 4,
+                columns_follow: true,
+            },
+            row.streams[0].mask
+        );
+        assert_eq!(0x01, fec[0].payload[11]);
+
+        // A whole row and another packet of the same column are lost, which
+        // can not be recovered with a single dimension
+        let lost = [4, 5, 6, 7, 9];
+        let mut recovered = Vec::new();
+        for fec in &fec {
+            recovered.extend(decoder.decode(fec));
+        }
+        for (index, packet) in packets.iter().enumerate() {
+            if !lost.contains(&index) {
+                recovered.extend(decoder.decode(packet));
+            }
+        }
+
+        check_recovered(&packets, &recovered, &lost);
+
+        // The packets must fill complete blocks
+        assert!(encoder.protect_blocks(&packets[..10], 4, 3).is_none());
+    }
+}
@@ -1,36 +1,269 @@
-use crate::PayloadGenerator;
+use crate::{
+    codecs::ptime::PacketizationTime, packet::Packet, packetizer::Packetizer, PayloadGenerator,
+};
+
+/// The number of bytes of a G711 stream per millisecond of audio
+const BYTES_PER_MILLISECOND: usize = 8;
+
+/// The bias added to the magnitude of a sample before its μ-law encoding
+const MULAW_BIAS: i32 = 0x84;
+
+/// The maximum magnitude of a sample which can be μ-law encoded
+const MULAW_CLIP: i32 = 32635;
+
+/// The linear value of each μ-law encoded sample
+const MULAW_TO_LINEAR: [i16; 256] = build_decoding_table(G711Law::MuLaw);
+
+/// The linear value of each A-law encoded sample
+const ALAW_TO_LINEAR: [i16; 256] = build_decoding_table(G711Law::ALaw);
+
+/// The A-law encoded value of each μ-law encoded sample
+const MULAW_TO_ALAW: [u8; 256] = build_transcoding_table(G711Law::MuLaw);
+
+/// The μ-law encoded value of each A-law encoded sample
+const ALAW_TO_MULAW: [u8; 256] = build_transcoding_table(G711Law::ALaw);
+
+/// The companding law used to encode the samples of a G.711 stream, as
+/// defined in the [ITU-T G.711] recommendation.
+///
+/// [ITU-T G.711]: https://www.itu.int/rec/T-REC-G.711
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum G711Law {
+    /// The μ-law, used in North America and Japan (PCMU).
+    #[default]
+    MuLaw,
+
+    /// The A-law, used in Europe and the rest of the world (PCMA).
+    ALaw,
+}
+
+impl G711Law {
+    /// Retrieves the static payload type assigned to the law by the
+    /// [RFC 3551].
+    ///
+    /// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-6
+    pub fn payload_type(self) -> u8 {
+        match self {
+            Self::MuLaw => 0,
+            Self::ALaw => 8,
+        }
+    }
+
+    /// Encodes 16-bit linear PCM samples with the law.
+    pub fn encode(self, samples: &[i16]) -> Vec<u8> {
+        let encode = match self {
+            Self::MuLaw => linear_to_mulaw,
+            Self::ALaw => linear_to_alaw,
+        };
+
+        samples.iter().map(|&sample| encode(sample)).collect()
+    }
+
+    /// Decodes samples encoded with the law into 16-bit linear PCM samples.
+    pub fn decode(self, data: &[u8]) -> Vec<i16> {
+        let table = match self {
+            Self::MuLaw => &MULAW_TO_LINEAR,
+            Self::ALaw => &ALAW_TO_LINEAR,
+        };
+
+        data.iter().map(|&sample| table[sample as usize]).collect()
+    }
+
+    /// Converts samples encoded with the law into samples encoded with
+    /// another law, with a precomputed table mapping each encoded sample
+    /// directly to its counterpart.
+    pub fn transcode(self, data: &[u8], law: Self) -> Vec<u8> {
+        let table = match (self, law) {
+            (Self::MuLaw, Self::ALaw) => &MULAW_TO_ALAW,
+            (Self::ALaw, Self::MuLaw) => &ALAW_TO_MULAW,
+            _ => return Vec::from(data),
+        };
+
+        data.iter().map(|&sample| table[sample as usize]).collect()
+    }
+}
+
+/// Encodes a 16-bit linear PCM sample with the μ-law.
+pub const fn linear_to_mulaw(sample: i16) -> u8 {
+    let mut magnitude = sample as i32;
+    let sign = if magnitude < 0 {
+        magnitude = -magnitude;
+        0x80
+    } else {
+        0x00
+    };
+
+    if magnitude > MULAW_CLIP {
+        magnitude = MULAW_CLIP;
+    }
+    magnitude += MULAW_BIAS;
+
+    // The exponent is the position of the highest bit above the 7th one
+    let exponent = 7 - ((magnitude >> 7) as u8).leading_zeros() as i32;
+    let mantissa = (magnitude >> (exponent + 3)) & 0x0f;
+
+    !(sign | (exponent << 4) as u8 | mantissa as u8)
+}
+
+/// Decodes a μ-law encoded sample into a 16-bit linear PCM sample.
+pub const fn mulaw_to_linear(sample: u8) -> i16 {
+    MULAW_TO_LINEAR[sample as usize]
+}
+
+/// Encodes a 16-bit linear PCM sample with the A-law.
+pub const fn linear_to_alaw(sample: i16) -> u8 {
+    // The A-law is working on 13-bit samples
+    let mut magnitude = (sample >> 3) as i32;
+    let mask = if magnitude >= 0 {
+        0xd5
+    } else {
+        magnitude = -magnitude - 1;
+        0x55
+    };
+
+    // The segment is the position of the highest bit above the 5th one
+    let segment = 32 - (magnitude >> 5).leading_zeros() as i32;
+    if segment >= 8 {
+        return 0x7f ^ mask;
+    }
+
+    let mantissa = if segment < 2 {
+        (magnitude >> 1) & 0x0f
+    } else {
+        (magnitude >> segment) & 0x0f
+    };
+
+    ((segment << 4) as u8 | mantissa as u8) ^ mask
+}
+
+/// Decodes an A-law encoded sample into a 16-bit linear PCM sample.
+pub const fn alaw_to_linear(sample: u8) -> i16 {
+    ALAW_TO_LINEAR[sample as usize]
+}
+
+/// Converts a μ-law encoded sample into an A-law encoded one.
+pub const fn mulaw_to_alaw(sample: u8) -> u8 {
+    MULAW_TO_ALAW[sample as usize]
+}
+
+/// Converts an A-law encoded sample into a μ-law encoded one.
+pub const fn alaw_to_mulaw(sample: u8) -> u8 {
+    ALAW_TO_MULAW[sample as usize]
+}
+
+/// Computes the linear value of each sample encoded with a law.
+const fn build_decoding_table(law: G711Law) -> [i16; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let sample = index as u8;
+
+        table[index] = match law {
+            G711Law::MuLaw => {
+                let sample = !sample;
+                let exponent = (sample >> 4) & 0x07;
+                let mantissa = (sample & 0x0f) as i32;
+                let magnitude = (((mantissa << 3) + MULAW_BIAS) << exponent) - MULAW_BIAS;
+
+                if sample & 0x80 > 0 {
+                    -magnitude as i16
+                } else {
+                    magnitude as i16
+                }
+            }
+            G711Law::ALaw => {
+                let sample = sample ^ 0x55;
+                let segment = (sample >> 4) & 0x07;
+                let mut magnitude = ((sample & 0x0f) as i32) << 4;
+
+                if segment == 0 {
+                    magnitude += 8;
+                } else {
+                    magnitude = (magnitude + 0x108) << (segment - 1);
+                }
+
+                if sample & 0x80 > 0 {
+                    magnitude as i16
+                } else {
+                    -magnitude as i16
+                }
+            }
+        };
+
+        index += 1;
+    }
+
+    table
+}
+
+/// Computes the value of each sample encoded with a law, once encoded with
+/// the other law.
+const fn build_transcoding_table(law: G711Law) -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        table[index] = match law {
+            G711Law::MuLaw => linear_to_alaw(MULAW_TO_LINEAR[index]),
+            G711Law::ALaw => linear_to_mulaw(ALAW_TO_LINEAR[index]),
+        };
+
+        index += 1;
+    }
+
+    table
+}
 
 /// This payload generator is used when you want to send G711 packets
 /// into an RTP data stream.
+///
+/// The payloads are split on the MTU, and on the packetization time if one
+/// has been negotiated. Each byte is a sample of the 8 kHz RTP clock.
 #[derive(Clone, Copy, Debug, Default)]
-pub struct G711PayloadGenerator;
+pub struct G711PayloadGenerator {
+    /// The packetization time negotiated for the stream.
+    pub packetization_time: PacketizationTime,
+}
+
+impl From<PacketizationTime> for G711PayloadGenerator {
+    fn from(packetization_time: PacketizationTime) -> Self {
+        Self { packetization_time }
+    }
+}
 
 impl PayloadGenerator for G711PayloadGenerator {
     fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
-        if mtu == 0 || payload.len() == 0 {
-            return None;
-        }
-
-        let mut output = Vec::new();
-        let mut offset = 0;
+        self.packetization_time
+            .split(mtu, payload, BYTES_PER_MILLISECOND)
+    }
 
-        while payload.len() - offset > mtu {
-            let payload = Vec::from(&payload[offset..offset + mtu]);
+    fn duration(&self, payload: &[u8]) -> Option<u32> {
+        Some(payload.len() as u32)
+    }
 
-            output.push(payload);
-            offset += mtu;
-        }
+    fn is_audio(&self) -> bool {
+        true
+    }
+}
 
-        let payload = Vec::from(&payload[offset..]);
-        output.push(payload);
+impl Packetizer<G711PayloadGenerator> {
+    /// Encodes 16-bit linear PCM samples with the provided law, then
+    /// transforms them into a list of RTP packets.
+    ///
+    /// The payload type of the packetizer is set to the static one of the
+    /// law, 0 for PCMU and 8 for PCMA.
+    pub fn packetize_pcm(&mut self, samples: &[i16], law: G711Law) -> Option<Vec<Packet>> {
+        self.payload_type = law.payload_type();
 
-        Some(output)
+        self.packetize(&law.encode(samples), samples.len() as u32)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packetizer::G711Packetizer;
     use rand::RngCore;
 
     const LENGTH: usize = 10000;
@@ -78,4 +311,79 @@ mod tests {
         let payloads = generator.generate(10, &samples).unwrap();
         assert_eq!(1, payloads.len());
     }
+
+    #[test]
+    fn it_encodes_and_decodes_mulaw_samples() {
+        assert_eq!(0xff, linear_to_mulaw(0));
+        assert_eq!(0x80, linear_to_mulaw(i16::MAX));
+        assert_eq!(0x00, linear_to_mulaw(i16::MIN));
+
+        assert_eq!(0, mulaw_to_linear(0xff));
+        assert_eq!(32124, mulaw_to_linear(0x80));
+        assert_eq!(-32124, mulaw_to_linear(0x00));
+
+        // Each encoded sample is decoded to a value encoded the same way
+        for sample in 0..=255u8 {
+            if sample != 0x7f {
+                assert_eq!(sample, linear_to_mulaw(mulaw_to_linear(sample)));
+            }
+        }
+    }
+
+    #[test]
+    fn it_encodes_and_decodes_alaw_samples() {
+        assert_eq!(0xd5, linear_to_alaw(0));
+        assert_eq!(0xaa, linear_to_alaw(i16::MAX));
+        assert_eq!(0x2a, linear_to_alaw(i16::MIN));
+
+        assert_eq!(8, alaw_to_linear(0xd5));
+        assert_eq!(-8, alaw_to_linear(0x55));
+        assert_eq!(32256, alaw_to_linear(0xaa));
+
+        for sample in 0..=255u8 {
+            assert_eq!(sample, linear_to_alaw(alaw_to_linear(sample)));
+        }
+    }
+
+    #[test]
+    fn it_transcodes_samples_between_laws() {
+        let samples = [0i16, 1000, -1000, 16000, -16000];
+
+        let mulaw = G711Law::MuLaw.encode(&samples);
+        let alaw = G711Law::MuLaw.transcode(&mulaw, G711Law::ALaw);
+        assert_eq!(G711Law::ALaw.encode(&G711Law::MuLaw.decode(&mulaw)), alaw);
+
+        let decoded = G711Law::ALaw.decode(&alaw);
+        for (sample, decoded) in samples.iter().zip(decoded) {
+            assert!((*sample as i32 - decoded as i32).abs() <= (*sample as i32).abs() / 16 + 16);
+        }
+
+        assert_eq!(mulaw, G711Law::MuLaw.transcode(&mulaw, G711Law::MuLaw));
+    }
+
+    #[test]
+    fn it_packetizes_pcm_samples() {
+        let mut packetizer = G711Packetizer::new(1500, 96, 0x1234abcd);
+        let samples = [1000i16; 160];
+
+        let packets = packetizer.packetize_pcm(&samples, G711Law::ALaw).unwrap();
+        assert_eq!(1, packets.len());
+        assert_eq!(8, packets[0].payload_type);
+        assert_eq!(G711Law::ALaw.encode(&samples), packets[0].payload);
+
+        let next = packetizer.packetize_pcm(&samples, G711Law::MuLaw).unwrap();
+        assert_eq!(0, next[0].payload_type);
+        assert_eq!(160, next[0].timestamp.wrapping_sub(packets[0].timestamp));
+    }
+
+    #[test]
+    fn it_generates_packets_of_20_ms() {
+        let mut generator = G711PayloadGenerator::from(PacketizationTime::default().with_ptime(20));
+        let samples = [0x90u8; 400];
+
+        let payloads = generator.generate(MTU, &samples).unwrap();
+        let sizes: Vec<_> = payloads.iter().map(Vec::len).collect();
+        assert_eq!(vec![160, 160, 80], sizes);
+        assert_eq!(Some(160), generator.duration(&payloads[0]));
+    }
 }
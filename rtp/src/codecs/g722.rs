@@ -1,30 +1,43 @@
-use crate::PayloadGenerator;
+use crate::{codecs::ptime::PacketizationTime, PayloadGenerator};
+
+/// The number of bytes of a G722 stream per millisecond of audio
+const BYTES_PER_MILLISECOND: usize = 8;
 
 /// This payload generator is used when you want to send G722 packets
 /// into an RTP data stream.
+///
+/// The payloads are split on the MTU, and on the packetization time if one
+/// has been negotiated.
+///
+/// > Even if G722 is sampling the audio at 16 kHz, its RTP clock rate is
+/// > 8 kHz for historical reasons, as stated in the [RFC 3551]. Each byte,
+/// > containing two samples, is then a tick of the RTP clock.
+///
+/// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-4.5.2
 #[derive(Clone, Copy, Debug, Default)]
-pub struct G722PayloadGenerator;
+pub struct G722PayloadGenerator {
+    /// The packetization time negotiated for the stream.
+    pub packetization_time: PacketizationTime,
+}
+
+impl From<PacketizationTime> for G722PayloadGenerator {
+    fn from(packetization_time: PacketizationTime) -> Self {
+        Self { packetization_time }
+    }
+}
 
 impl PayloadGenerator for G722PayloadGenerator {
     fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
-        if mtu == 0 || payload.len() == 0 {
-            return None;
-        }
-
-        let mut output = Vec::new();
-        let mut offset = 0;
-
-        while payload.len() - offset > mtu {
-            let payload = Vec::from(&payload[offset..offset + mtu]);
-
-            output.push(payload);
-            offset += mtu;
-        }
+        self.packetization_time
+            .split(mtu, payload, BYTES_PER_MILLISECOND)
+    }
 
-        let payload = Vec::from(&payload[offset..]);
-        output.push(payload);
+    fn duration(&self, payload: &[u8]) -> Option<u32> {
+        Some(payload.len() as u32)
+    }
 
-        Some(output)
+    fn is_audio(&self) -> bool {
+        true
     }
 }
 
@@ -78,4 +91,15 @@ mod tests {
         let payloads = generator.generate(10, &samples).unwrap();
         assert_eq!(1, payloads.len());
     }
+
+    #[test]
+    fn it_generates_packets_of_20_ms() {
+        let mut generator = G722PayloadGenerator::from(PacketizationTime::default().with_ptime(20));
+        let samples = [0x90u8; 400];
+
+        let payloads = generator.generate(MTU, &samples).unwrap();
+        let sizes: Vec<_> = payloads.iter().map(Vec::len).collect();
+        assert_eq!(vec![160, 160, 80], sizes);
+        assert_eq!(Some(160), generator.duration(&payloads[0]));
+    }
 }
@@ -1,8 +1,26 @@
-use crate::PayloadGenerator;
+use crate::{
+    codecs::{annexb, KeyframeDetector},
+    errors::H264Error,
+    PayloadGenerator,
+};
 
 const FUA_HEADER_SIZE: usize = 2;
 const NAL_UNIT_TYPE_MASK: u8 = 0x1f;
 const NAL_UNIT_REF_IDC_MASK: u8 = 0x60;
+const NAL_UNIT_TYPE_STAP_A: u8 = 24;
+const NAL_UNIT_TYPE_FU_A: u8 = 28;
+
+/// NAL unit type of a coded slice of an IDR picture
+pub const NAL_UNIT_TYPE_IDR: u8 = 5;
+
+/// NAL unit type of a sequence parameter set (SPS)
+pub const NAL_UNIT_TYPE_SPS: u8 = 7;
+
+/// NAL unit type of a picture parameter set (PPS)
+pub const NAL_UNIT_TYPE_PPS: u8 = 8;
+
+/// Profiles for which the SPS carries chroma format and bit depth information
+const HIGH_PROFILES: [u8; 13] = [100, 110, 122, 244, 44, 83, 86, 118, 128, 138, 139, 134, 135];
 
 /// This payload generator is responsible to generate RTP packet's payloads
 /// from H.264 data in order to send them into a RTP stream.
@@ -10,29 +28,6 @@ const NAL_UNIT_REF_IDC_MASK: u8 = 0x60;
 pub struct H264PayloadGenerator;
 
 impl H264PayloadGenerator {
-    /// Determines the boundaries of a NAL unit of a H.264 payload. The
-    /// boundaries are returned into a tuple defined this way: `(start, length)`.
-    ///
-    /// If no boundaries can be found, `None` is returned.
-    fn get_nal_unit_boundaries(units: &[u8], start: usize) -> Option<(usize, usize)> {
-        let mut zero_count = 0;
-
-        for (index, byte) in units[start..].iter().enumerate() {
-            match *byte {
-                0u8 => zero_count += 1,
-                1u8 => {
-                    // If we've counted 2 or more zero byte, we've got our boundaries.
-                    if zero_count >= 2 {
-                        return Some((start + index - zero_count, zero_count + 1));
-                    }
-                }
-                _ => zero_count = 0,
-            };
-        }
-
-        None
-    }
-
     /// Generates RTP payloads from a NAL unit thanks to the MTU.
     fn generate_payloads_from_nal_unit(mtu: usize, unit: &[u8]) -> Option<Vec<Vec<u8>>> {
         let unit_type = unit[0] & NAL_UNIT_TYPE_MASK;
@@ -122,44 +117,689 @@ impl PayloadGenerator for H264PayloadGenerator {
             return None;
         }
 
+        // We'll produce a RTP payload for each NAL unit found. If the payload
+        // has no start code, it is considered as one big NAL unit.
         let mut output = Vec::new();
+        for unit in annexb::nal_units(payload) {
+            if let Some(mut payloads) = Self::generate_payloads_from_nal_unit(mtu, unit) {
+                output.append(&mut payloads);
+            }
+        }
 
-        // Retrieving NAL unit boundaries
-        let mut boundaries = Self::get_nal_unit_boundaries(payload, 0);
+        if output.len() > 0 {
+            Some(output)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads the bits of a RBSP (Raw Byte Sequence Payload), most significant
+/// bit first, and decodes the Exp-Golomb codes used by H.264 syntax elements.
+struct BitReader {
+    data: Vec<u8>,
+    position: usize,
+}
 
-        // If no boundaries are returned, then there is no NAL unit. In this case
-        // we'll consider the payload as a big NAL unit and produce payloads from it
-        if let None = boundaries {
-            let mut payloads = Self::generate_payloads_from_nal_unit(mtu, payload);
-            if let Some(payloads) = &mut payloads {
-                output.append(payloads);
+impl BitReader {
+    /// Instanciates a reader from the payload of a NAL unit (header
+    /// excluded), removing the emulation prevention bytes on the way.
+    fn new(payload: &[u8]) -> Self {
+        let mut data = Vec::with_capacity(payload.len());
+        let mut zero_count = 0;
 
-                return Some(output);
+        for byte in payload {
+            if zero_count >= 2 && *byte == 0x03 {
+                zero_count = 0;
+                continue;
             }
 
-            return None;
+            zero_count = if *byte == 0 { zero_count + 1 } else { 0 };
+            data.push(*byte);
+        }
+
+        Self { data, position: 0 }
+    }
+
+    /// Reads a single bit.
+    fn read_bit(&mut self) -> Result<u32, H264Error> {
+        let byte = self
+            .data
+            .get(self.position / 8)
+            .ok_or(H264Error::TruncatedBitstream)?;
+        let bit = (byte >> (7 - self.position % 8)) & 0x01;
+
+        self.position += 1;
+
+        Ok(bit as u32)
+    }
+
+    /// Reads a single bit as a flag.
+    fn read_flag(&mut self) -> Result<bool, H264Error> {
+        Ok(self.read_bit()? == 1)
+    }
+
+    /// Reads an unsigned integer written on `count` bits (`u(n)`), with `count`
+    /// lesser or equal to 32.
+    fn read_bits(&mut self, count: usize) -> Result<u32, H264Error> {
+        let mut value = 0u32;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()?;
         }
 
-        // We'll produce a RTP payload for each NAL unit found
-        while let Some((start, length)) = boundaries {
-            let previous_start = start + length;
-            boundaries = Self::get_nal_unit_boundaries(payload, previous_start);
+        Ok(value)
+    }
 
-            let mut payloads = if let Some(boundaries) = &boundaries {
-                Self::generate_payloads_from_nal_unit(mtu, &payload[previous_start..boundaries.0])
-            } else {
-                Self::generate_payloads_from_nal_unit(mtu, &payload[previous_start..])
-            };
+    /// Reads an unsigned Exp-Golomb-coded integer (`ue(v)`).
+    fn read_ue(&mut self) -> Result<u32, H264Error> {
+        let mut leading_zeros = 0;
+        while self.read_bit()? == 0 {
+            leading_zeros += 1;
 
-            if let Some(payloads) = &mut payloads {
-                output.append(payloads);
+            if leading_zeros > 31 {
+                return Err(H264Error::InvalidSyntaxElement {
+                    element: "exp-golomb code",
+                });
             }
         }
 
-        if output.len() > 0 {
-            Some(output)
+        let suffix = self.read_bits(leading_zeros)? as u64;
+
+        Ok(((1u64 << leading_zeros) - 1 + suffix) as u32)
+    }
+
+    /// Reads a signed Exp-Golomb-coded integer (`se(v)`).
+    fn read_se(&mut self) -> Result<i32, H264Error> {
+        let value = self.read_ue()? as i64;
+
+        if value % 2 == 1 {
+            Ok(((value + 1) / 2) as i32)
+        } else {
+            Ok((-(value / 2)) as i32)
+        }
+    }
+
+    /// Reads an unsigned Exp-Golomb-coded syntax element which can not exceed
+    /// `max`.
+    fn read_ue_max(&mut self, max: u32, element: &'static str) -> Result<u32, H264Error> {
+        let value = self.read_ue()?;
+        if value > max {
+            return Err(H264Error::InvalidSyntaxElement { element });
+        }
+
+        Ok(value)
+    }
+
+    /// Reads a signed Exp-Golomb-coded syntax element which must be within
+    /// `min` and `max`.
+    fn read_se_range(
+        &mut self,
+        min: i32,
+        max: i32,
+        element: &'static str,
+    ) -> Result<i32, H264Error> {
+        let value = self.read_se()?;
+        if value < min || value > max {
+            return Err(H264Error::InvalidSyntaxElement { element });
+        }
+
+        Ok(value)
+    }
+
+    /// Skips a scaling list of a parameter set, since its values are not needed
+    /// to describe the stream.
+    fn skip_scaling_list(&mut self, size: usize) -> Result<(), H264Error> {
+        let mut last_scale = 8;
+        let mut next_scale = 8;
+
+        for _ in 0..size {
+            if next_scale != 0 {
+                let delta_scale = self.read_se_range(-128, 127, "delta_scale")?;
+                next_scale = (last_scale + delta_scale + 256) % 256;
+            }
+
+            if next_scale != 0 {
+                last_scale = next_scale;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the NAL unit header and returns a bit reader positionned on the
+/// unit's payload.
+fn open_nal_unit(unit: &[u8], expected_type: u8) -> Result<BitReader, H264Error> {
+    let header = unit.first().ok_or(H264Error::TruncatedBitstream)?;
+
+    let unit_type = header & NAL_UNIT_TYPE_MASK;
+    if unit_type != expected_type {
+        return Err(H264Error::UnexpectedNalUnitType { unit_type });
+    }
+
+    Ok(BitReader::new(&unit[1..]))
+}
+
+/// Frame rate information carried by the timing information of the VUI
+/// (Video Usability Information) of a sequence parameter set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TimingInfo {
+    /// The number of time units of a clock operating at `time_scale` Hz that
+    /// corresponds to one increment of a clock tick counter.
+    pub num_units_in_tick: u32,
+
+    /// The number of time units that pass in one second.
+    pub time_scale: u32,
+
+    /// Indicates if the temporal distance between two consecutive pictures is
+    /// constant.
+    pub fixed_frame_rate: bool,
+}
+
+impl TimingInfo {
+    /// Computes the frame rate described by the timing information, one frame
+    /// being made of two fields.
+    pub fn frame_rate(&self) -> f64 {
+        if self.num_units_in_tick == 0 {
+            return 0.0;
+        }
+
+        self.time_scale as f64 / (2.0 * self.num_units_in_tick as f64)
+    }
+}
+
+/// Represents the fields of a H.264 sequence parameter set (SPS) which are
+/// needed to describe a video stream.
+///
+/// The parsing follows the section 7.3.2.1 of the [H.264 specification].
+///
+/// [H.264 specification]: https://www.itu.int/rec/T-REC-H.264
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SequenceParameterSet {
+    /// The profile to which the coded video sequence conforms.
+    pub profile_idc: u8,
+
+    /// The constraint flags (`constraint_set0_flag` to `constraint_set5_flag`)
+    /// and the two reserved bits, as they're written in the bitstream.
+    pub constraint_flags: u8,
+
+    /// The level to which the coded video sequence conforms.
+    pub level_idc: u8,
+
+    /// The identifier of the SPS referred to by the picture parameter sets.
+    pub seq_parameter_set_id: u32,
+
+    /// The chroma sampling relative to the luma sampling: `0` for monochrome,
+    /// `1` for 4:2:0, `2` for 4:2:2 and `3` for 4:4:4.
+    pub chroma_format_idc: u32,
+
+    /// Indicates if the three colour components of 4:4:4 chroma format are
+    /// coded separately.
+    pub separate_colour_plane: bool,
+
+    /// The bit depth of the luma samples.
+    pub bit_depth_luma: u32,
+
+    /// The bit depth of the chroma samples.
+    pub bit_depth_chroma: u32,
+
+    /// The maximum number of reference frames used for inter prediction.
+    pub max_num_ref_frames: u32,
+
+    /// Indicates if the coded video sequence only contains frames, and no
+    /// fields.
+    pub frame_mbs_only: bool,
+
+    /// The width of the decoded pictures in luma samples, after cropping.
+    pub width: u32,
+
+    /// The height of the decoded pictures in luma samples, after cropping.
+    pub height: u32,
+
+    /// The timing information of the VUI, if provided.
+    pub timing_info: Option<TimingInfo>,
+}
+
+impl SequenceParameterSet {
+    /// Parses a SPS from a NAL unit, start code excluded and NAL unit header
+    /// included.
+    pub fn from_raw(unit: &[u8]) -> Result<Self, H264Error> {
+        let mut reader = open_nal_unit(unit, NAL_UNIT_TYPE_SPS)?;
+
+        let profile_idc = reader.read_bits(8)? as u8;
+        let constraint_flags = reader.read_bits(8)? as u8;
+        let level_idc = reader.read_bits(8)? as u8;
+        let seq_parameter_set_id = reader.read_ue_max(31, "seq_parameter_set_id")?;
+
+        let mut chroma_format_idc = 1;
+        let mut separate_colour_plane = false;
+        let mut bit_depth_luma = 8;
+        let mut bit_depth_chroma = 8;
+
+        if HIGH_PROFILES.contains(&profile_idc) {
+            chroma_format_idc = reader.read_ue()?;
+            if chroma_format_idc > 3 {
+                return Err(H264Error::InvalidSyntaxElement {
+                    element: "chroma_format_idc",
+                });
+            }
+
+            if chroma_format_idc == 3 {
+                separate_colour_plane = reader.read_flag()?;
+            }
+
+            bit_depth_luma += reader.read_ue_max(6, "bit_depth_luma_minus8")?;
+            bit_depth_chroma += reader.read_ue_max(6, "bit_depth_chroma_minus8")?;
+
+            // qpprime_y_zero_transform_bypass_flag
+            reader.read_flag()?;
+
+            // seq_scaling_matrix_present_flag
+            if reader.read_flag()? {
+                let count = if chroma_format_idc != 3 { 8 } else { 12 };
+                for i in 0..count {
+                    if reader.read_flag()? {
+                        reader.skip_scaling_list(if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+
+        reader.read_ue_max(12, "log2_max_frame_num_minus4")?;
+
+        let pic_order_cnt_type = reader.read_ue()?;
+        match pic_order_cnt_type {
+            0 => {
+                reader.read_ue_max(12, "log2_max_pic_order_cnt_lsb_minus4")?;
+            }
+            1 => {
+                // delta_pic_order_always_zero_flag, offset_for_non_ref_pic and
+                // offset_for_top_to_bottom_field
+                reader.read_flag()?;
+                reader.read_se()?;
+                reader.read_se()?;
+
+                let cycle_length =
+                    reader.read_ue_max(255, "num_ref_frames_in_pic_order_cnt_cycle")?;
+                for _ in 0..cycle_length {
+                    reader.read_se()?;
+                }
+            }
+            2 => {}
+            _ => {
+                return Err(H264Error::InvalidSyntaxElement {
+                    element: "pic_order_cnt_type",
+                })
+            }
+        }
+
+        let max_num_ref_frames = reader.read_ue_max(16, "max_num_ref_frames")?;
+
+        // gaps_in_frame_num_value_allowed_flag
+        reader.read_flag()?;
+
+        let pic_width_in_mbs = reader.read_ue()? + 1;
+        let pic_height_in_map_units = reader.read_ue()? + 1;
+
+        let frame_mbs_only = reader.read_flag()?;
+        if !frame_mbs_only {
+            // mb_adaptive_frame_field_flag
+            reader.read_flag()?;
+        }
+
+        // direct_8x8_inference_flag
+        reader.read_flag()?;
+
+        let (mut crop_left, mut crop_right, mut crop_top, mut crop_bottom) = (0, 0, 0, 0);
+        if reader.read_flag()? {
+            crop_left = reader.read_ue()?;
+            crop_right = reader.read_ue()?;
+            crop_top = reader.read_ue()?;
+            crop_bottom = reader.read_ue()?;
+        }
+
+        let timing_info = if reader.read_flag()? {
+            Self::parse_vui_timing_info(&mut reader)?
         } else {
             None
+        };
+
+        // Computing the cropping units according to the chroma array type
+        let field_factor = if frame_mbs_only { 1 } else { 2 };
+        let (crop_unit_x, crop_unit_y) = if separate_colour_plane || chroma_format_idc == 0 {
+            (1, field_factor)
+        } else {
+            let sub_width = if chroma_format_idc == 3 { 1 } else { 2 };
+            let sub_height = if chroma_format_idc == 1 { 2 } else { 1 };
+
+            (sub_width, sub_height * field_factor)
+        };
+
+        let width = pic_width_in_mbs
+            .checked_mul(16)
+            .ok_or(H264Error::InvalidSyntaxElement {
+                element: "pic_width_in_mbs_minus1",
+            })?;
+        let height = pic_height_in_map_units
+            .checked_mul(16 * field_factor)
+            .ok_or(H264Error::InvalidSyntaxElement {
+                element: "pic_height_in_map_units_minus1",
+            })?;
+
+        let width = crop_left
+            .checked_add(crop_right)
+            .and_then(|crop| crop.checked_mul(crop_unit_x))
+            .and_then(|crop| width.checked_sub(crop))
+            .ok_or(H264Error::InvalidSyntaxElement {
+                element: "frame_crop_offset",
+            })?;
+        let height = crop_top
+            .checked_add(crop_bottom)
+            .and_then(|crop| crop.checked_mul(crop_unit_y))
+            .and_then(|crop| height.checked_sub(crop))
+            .ok_or(H264Error::InvalidSyntaxElement {
+                element: "frame_crop_offset",
+            })?;
+
+        Ok(Self {
+            profile_idc,
+            constraint_flags,
+            level_idc,
+            seq_parameter_set_id,
+            chroma_format_idc,
+            separate_colour_plane,
+            bit_depth_luma,
+            bit_depth_chroma,
+            max_num_ref_frames,
+            frame_mbs_only,
+            width,
+            height,
+            timing_info,
+        })
+    }
+
+    /// Parses the VUI parameters up to the timing information, which is the
+    /// only part of the VUI that we need.
+    fn parse_vui_timing_info(reader: &mut BitReader) -> Result<Option<TimingInfo>, H264Error> {
+        // aspect_ratio_info_present_flag
+        if reader.read_flag()? {
+            let aspect_ratio_idc = reader.read_bits(8)?;
+
+            // Extended_SAR: sar_width and sar_height
+            if aspect_ratio_idc == 255 {
+                reader.read_bits(32)?;
+            }
+        }
+
+        // overscan_info_present_flag and overscan_appropriate_flag
+        if reader.read_flag()? {
+            reader.read_flag()?;
+        }
+
+        // video_signal_type_present_flag
+        if reader.read_flag()? {
+            // video_format and video_full_range_flag
+            reader.read_bits(4)?;
+
+            // colour_description_present_flag, colour_primaries,
+            // transfer_characteristics and matrix_coefficients
+            if reader.read_flag()? {
+                reader.read_bits(24)?;
+            }
+        }
+
+        // chroma_loc_info_present_flag, chroma_sample_loc_type_top_field and
+        // chroma_sample_loc_type_bottom_field
+        if reader.read_flag()? {
+            reader.read_ue()?;
+            reader.read_ue()?;
+        }
+
+        if !reader.read_flag()? {
+            return Ok(None);
+        }
+
+        Ok(Some(TimingInfo {
+            num_units_in_tick: reader.read_bits(32)?,
+            time_scale: reader.read_bits(32)?,
+            fixed_frame_rate: reader.read_flag()?,
+        }))
+    }
+
+    /// Computes the frame rate from the VUI timing information, if provided.
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.timing_info.map(|timing_info| timing_info.frame_rate())
+    }
+
+    /// Formats the `profile-level-id` parameter of the H.264 SDP format
+    /// parameters, as defined in the [RFC 6184].
+    ///
+    /// [RFC 6184]: https://tools.ietf.org/html/rfc6184#section-8.1
+    pub fn profile_level_id(&self) -> String {
+        format!(
+            "{:02x}{:02x}{:02x}",
+            self.profile_idc, self.constraint_flags, self.level_idc
+        )
+    }
+}
+
+/// Represents the key fields of a H.264 picture parameter set (PPS).
+///
+/// The parsing follows the section 7.3.2.2 of the [H.264 specification] and
+/// stops after `redundant_pic_cnt_present_flag`.
+///
+/// [H.264 specification]: https://www.itu.int/rec/T-REC-H.264
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PictureParameterSet {
+    /// The identifier of the PPS referred to by the slices.
+    pub pic_parameter_set_id: u32,
+
+    /// The identifier of the SPS referred to by the PPS.
+    pub seq_parameter_set_id: u32,
+
+    /// Indicates if CABAC is used as entropy coding method instead of CAVLC.
+    pub entropy_coding_mode: bool,
+
+    /// Indicates if the picture order count of the bottom field is present in
+    /// the slice headers.
+    pub bottom_field_pic_order_in_frame_present: bool,
+
+    /// The number of slice groups of a picture.
+    pub num_slice_groups: u32,
+
+    /// The default number of active references of the list 0.
+    pub num_ref_idx_l0_default_active: u32,
+
+    /// The default number of active references of the list 1.
+    pub num_ref_idx_l1_default_active: u32,
+
+    /// Indicates if the weighted prediction is applied to P and SP slices.
+    pub weighted_pred: bool,
+
+    /// The weighted prediction mode applied to B slices.
+    pub weighted_bipred_idc: u8,
+
+    /// The initial value of the luma quantization parameter.
+    pub pic_init_qp: i32,
+
+    /// The initial value of the luma quantization parameter for SP and SI slices.
+    pub pic_init_qs: i32,
+
+    /// The offset to apply to the quantization parameter of the chroma components.
+    pub chroma_qp_index_offset: i32,
+
+    /// Indicates if the deblocking filter is controlled from the slice headers.
+    pub deblocking_filter_control_present: bool,
+
+    /// Indicates if the intra prediction only uses intra coded neighbours.
+    pub constrained_intra_pred: bool,
+
+    /// Indicates if the redundant picture count is present in the slice headers.
+    pub redundant_pic_cnt_present: bool,
+}
+
+impl PictureParameterSet {
+    /// Parses a PPS from a NAL unit, start code excluded and NAL unit header
+    /// included.
+    pub fn from_raw(unit: &[u8]) -> Result<Self, H264Error> {
+        let mut reader = open_nal_unit(unit, NAL_UNIT_TYPE_PPS)?;
+
+        let pic_parameter_set_id = reader.read_ue_max(255, "pic_parameter_set_id")?;
+        let seq_parameter_set_id = reader.read_ue_max(31, "seq_parameter_set_id")?;
+        let entropy_coding_mode = reader.read_flag()?;
+        let bottom_field_pic_order_in_frame_present = reader.read_flag()?;
+
+        let num_slice_groups = reader.read_ue_max(7, "num_slice_groups_minus1")? + 1;
+        if num_slice_groups > 1 {
+            Self::skip_slice_groups(&mut reader, num_slice_groups)?;
+        }
+
+        let num_ref_idx_l0_default_active =
+            reader.read_ue_max(31, "num_ref_idx_l0_default_active_minus1")? + 1;
+        let num_ref_idx_l1_default_active =
+            reader.read_ue_max(31, "num_ref_idx_l1_default_active_minus1")? + 1;
+        let weighted_pred = reader.read_flag()?;
+        let weighted_bipred_idc = reader.read_bits(2)? as u8;
+        // The lower bound of the QP depends on the bit depth, up to 14 bits
+        let pic_init_qp = 26 + reader.read_se_range(-62, 25, "pic_init_qp_minus26")?;
+        let pic_init_qs = 26 + reader.read_se_range(-26, 25, "pic_init_qs_minus26")?;
+        let chroma_qp_index_offset = reader.read_se_range(-12, 12, "chroma_qp_index_offset")?;
+        let deblocking_filter_control_present = reader.read_flag()?;
+        let constrained_intra_pred = reader.read_flag()?;
+        let redundant_pic_cnt_present = reader.read_flag()?;
+
+        Ok(Self {
+            pic_parameter_set_id,
+            seq_parameter_set_id,
+            entropy_coding_mode,
+            bottom_field_pic_order_in_frame_present,
+            num_slice_groups,
+            num_ref_idx_l0_default_active,
+            num_ref_idx_l1_default_active,
+            weighted_pred,
+            weighted_bipred_idc,
+            pic_init_qp,
+            pic_init_qs,
+            chroma_qp_index_offset,
+            deblocking_filter_control_present,
+            constrained_intra_pred,
+            redundant_pic_cnt_present,
+        })
+    }
+
+    /// Skips the slice group map description of the PPS.
+    fn skip_slice_groups(reader: &mut BitReader, num_slice_groups: u32) -> Result<(), H264Error> {
+        match reader.read_ue()? {
+            0 => {
+                // run_length_minus1
+                for _ in 0..num_slice_groups {
+                    reader.read_ue()?;
+                }
+            }
+            2 => {
+                // top_left and bottom_right
+                for _ in 0..num_slice_groups - 1 {
+                    reader.read_ue()?;
+                    reader.read_ue()?;
+                }
+            }
+            3..=5 => {
+                // slice_group_change_direction_flag and slice_group_change_rate_minus1
+                reader.read_flag()?;
+                reader.read_ue()?;
+            }
+            6 => {
+                // slice_group_id, coded on Ceil(Log2(num_slice_groups)) bits
+                let pic_size_in_map_units = reader.read_ue()? as u64 + 1;
+                let bits = 32 - (num_slice_groups - 1).leading_zeros() as usize;
+
+                for _ in 0..pic_size_in_map_units {
+                    reader.read_bits(bits)?;
+                }
+            }
+            1 => {}
+            _ => {
+                return Err(H264Error::InvalidSyntaxElement {
+                    element: "slice_group_map_type",
+                })
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Looks for the last sequence and picture parameter sets of an Annex B
+/// H.264 byte stream.
+///
+/// The parameter sets which can not be parsed are ignored.
+pub fn find_parameter_sets(
+    payload: &[u8],
+) -> (Option<SequenceParameterSet>, Option<PictureParameterSet>) {
+    let mut sps = None;
+    let mut pps = None;
+
+    for unit in annexb::nal_units(payload) {
+        match unit[0] & NAL_UNIT_TYPE_MASK {
+            NAL_UNIT_TYPE_SPS => {
+                if let Ok(parsed) = SequenceParameterSet::from_raw(unit) {
+                    sps = Some(parsed);
+                }
+            }
+            NAL_UNIT_TYPE_PPS => {
+                if let Ok(parsed) = PictureParameterSet::from_raw(unit) {
+                    pps = Some(parsed);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (sps, pps)
+}
+
+/// This keyframe detector is responsible to detect the H.264 keyframes, as
+/// packetized according to the [RFC 6184].
+///
+/// A keyframe starts with a SPS or an IDR slice, carried by a single NAL
+/// unit packet, a STAP-A packet, or the first fragment of a FU-A packet.
+///
+/// [RFC 6184]: https://tools.ietf.org/html/rfc6184
+#[derive(Clone, Copy, Debug, Default)]
+pub struct H264KeyframeDetector;
+
+impl H264KeyframeDetector {
+    /// Checks if a NAL unit type is starting a keyframe.
+    fn is_keyframe_unit(unit_type: u8) -> bool {
+        unit_type == NAL_UNIT_TYPE_IDR || unit_type == NAL_UNIT_TYPE_SPS
+    }
+}
+
+impl KeyframeDetector for H264KeyframeDetector {
+    fn is_keyframe(&self, payload: &[u8]) -> bool {
+        let header = match payload.first() {
+            Some(header) => header,
+            None => return false,
+        };
+
+        match header & NAL_UNIT_TYPE_MASK {
+            NAL_UNIT_TYPE_STAP_A => {
+                let mut offset = 1;
+                while offset + 2 < payload.len() {
+                    let size = u16::from_be_bytes([payload[offset], payload[offset + 1]]) as usize;
+                    if Self::is_keyframe_unit(payload[offset + 2] & NAL_UNIT_TYPE_MASK) {
+                        return true;
+                    }
+
+                    offset += 2 + size;
+                }
+
+                false
+            }
+            NAL_UNIT_TYPE_FU_A => payload.get(1).is_some_and(|fu_header| {
+                fu_header & 0x80 > 0 && Self::is_keyframe_unit(fu_header & NAL_UNIT_TYPE_MASK)
+            }),
+            unit_type => Self::is_keyframe_unit(unit_type),
         }
     }
 }
@@ -224,4 +864,175 @@ mod tests {
         let payloads = generator.generate(5, &payload);
         assert!(payloads.is_none());
     }
+
+    #[test]
+    fn it_parses_a_baseline_sequence_parameter_set() {
+        let unit = [0x67, 0x42, 0xc0, 0x1e, 0xe9, 0x01, 0x40, 0x7b, 0x20];
+
+        let sps = SequenceParameterSet::from_raw(&unit);
+        assert!(sps.is_ok());
+
+        let sps = sps.unwrap();
+        assert_eq!(66, sps.profile_idc);
+        assert_eq!(0xc0, sps.constraint_flags);
+        assert_eq!(30, sps.level_idc);
+        assert_eq!(1, sps.chroma_format_idc);
+        assert_eq!(640, sps.width);
+        assert_eq!(480, sps.height);
+        assert_eq!(None, sps.frame_rate());
+        assert_eq!("42c01e", sps.profile_level_id());
+    }
+
+    #[test]
+    fn it_parses_a_high_profile_sequence_parameter_set_with_cropping_and_vui() {
+        // The SPS contains emulation prevention bytes
+        let unit = [
+            0x67, 0x64, 0x00, 0x28, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0x84, 0x00, 0x00,
+            0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xf2, 0x10,
+        ];
+
+        let sps = SequenceParameterSet::from_raw(&unit);
+        assert!(sps.is_ok());
+
+        let sps = sps.unwrap();
+        assert_eq!(100, sps.profile_idc);
+        assert_eq!(40, sps.level_idc);
+        assert_eq!(8, sps.bit_depth_luma);
+        assert_eq!(4, sps.max_num_ref_frames);
+        assert_eq!(1920, sps.width);
+        assert_eq!(1080, sps.height);
+        assert_eq!(
+            Some(TimingInfo {
+                num_units_in_tick: 1,
+                time_scale: 60,
+                fixed_frame_rate: true,
+            }),
+            sps.timing_info
+        );
+        assert_eq!(Some(30.0), sps.frame_rate());
+        assert_eq!("640028", sps.profile_level_id());
+    }
+
+    #[test]
+    fn it_returns_an_error_when_the_sequence_parameter_set_is_truncated() {
+        let unit = [0x67, 0x42, 0xc0, 0x1e, 0xe8];
+
+        assert!(SequenceParameterSet::from_raw(&unit).is_err());
+    }
+
+    #[test]
+    fn it_returns_an_error_when_the_nal_unit_is_not_a_parameter_set() {
+        let unit = [0x65, 0x88, 0x84];
+
+        assert!(SequenceParameterSet::from_raw(&unit).is_err());
+        assert!(PictureParameterSet::from_raw(&unit).is_err());
+    }
+
+    #[test]
+    fn it_parses_a_picture_parameter_set() {
+        let unit = [0x68, 0xeb, 0xe3, 0xcb, 0x20];
+
+        let pps = PictureParameterSet::from_raw(&unit);
+        assert!(pps.is_ok());
+
+        let pps = pps.unwrap();
+        assert_eq!(0, pps.pic_parameter_set_id);
+        assert_eq!(0, pps.seq_parameter_set_id);
+        assert!(pps.entropy_coding_mode);
+        assert_eq!(1, pps.num_slice_groups);
+        assert_eq!(3, pps.num_ref_idx_l0_default_active);
+        assert!(pps.weighted_pred);
+        assert_eq!(2, pps.weighted_bipred_idc);
+        assert_eq!(23, pps.pic_init_qp);
+        assert_eq!(26, pps.pic_init_qs);
+        assert_eq!(-2, pps.chroma_qp_index_offset);
+        assert!(pps.deblocking_filter_control_present);
+    }
+
+    #[test]
+    fn it_finds_parameter_sets_in_an_annexb_stream() {
+        let payload = [
+            0x00, 0x00, 0x00, 0x01, 0x67, 0x42, 0xc0, 0x1e, 0xe9, 0x01, 0x40, 0x7b, 0x20, 0x00,
+            0x00, 0x01, 0x68, 0xeb, 0xe3, 0xcb, 0x20, 0x00, 0x00, 0x01, 0x65, 0x88, 0x84,
+        ];
+
+        let (sps, pps) = find_parameter_sets(&payload);
+        assert_eq!(640, sps.unwrap().width);
+        assert_eq!(23, pps.unwrap().pic_init_qp);
+    }
+
+    #[test]
+    fn it_detects_keyframes() {
+        let detector = H264KeyframeDetector;
+
+        // Single NAL unit packets
+        assert!(detector.is_keyframe(&[0x65, 0x88, 0x84]));
+        assert!(detector.is_keyframe(&[0x67, 0x42, 0xc0, 0x1e]));
+        assert!(!detector.is_keyframe(&[0x41, 0x9a, 0x02]));
+        assert!(!detector.is_keyframe(&[]));
+
+        // STAP-A packets
+        assert!(detector.is_keyframe(&[0x78, 0x00, 0x02, 0x09, 0x10, 0x00, 0x03, 0x67, 0x42, 0xc0]));
+        assert!(!detector.is_keyframe(&[0x78, 0x00, 0x02, 0x09, 0x10, 0x00, 0x02, 0x68, 0xce]));
+
+        // FU-A packets
+        assert!(detector.is_keyframe(&[0x7c, 0x85, 0x88, 0x84]));
+        assert!(!detector.is_keyframe(&[0x7c, 0x05, 0x88, 0x84]));
+        assert!(!detector.is_keyframe(&[0x7c, 0x81, 0x9a, 0x02]));
+    }
+
+    #[test]
+    fn it_rejects_out_of_range_syntax_elements() {
+        fn invalid<T>(result: Result<T, H264Error>) -> &'static str {
+            match result {
+                Err(H264Error::InvalidSyntaxElement { element }) => element,
+                _ => "",
+            }
+        }
+
+        // pic_width_in_mbs_minus1 = 2^32 - 2
+        let sps = [
+            0x67, 0x42, 0xc0, 0x1e, 0xda, 0x00, 0x00, 0x03, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff,
+            0xe4,
+        ];
+        assert_eq!(
+            "pic_width_in_mbs_minus1",
+            invalid(SequenceParameterSet::from_raw(&sps))
+        );
+
+        // frame_crop_left_offset = frame_crop_right_offset = 2^31
+        let sps = [
+            0x67, 0x42, 0xc0, 0x1e, 0xda, 0x05, 0x07, 0xf0, 0x00, 0x00, 0x03, 0x00, 0x10, 0x00,
+            0x00, 0x03, 0x00, 0x20, 0x00, 0x00, 0x03, 0x00, 0x20, 0x00, 0x00, 0x03, 0x00, 0x74,
+        ];
+        assert_eq!(
+            "frame_crop_offset",
+            invalid(SequenceParameterSet::from_raw(&sps))
+        );
+
+        // bit_depth_luma_minus8 = 2^32 - 2
+        let sps = [
+            0x67, 0x64, 0x00, 0x28, 0xa0, 0x00, 0x00, 0x03, 0x00, 0x1f, 0xff, 0xff, 0xff, 0xf2,
+        ];
+        assert_eq!(
+            "bit_depth_luma_minus8",
+            invalid(SequenceParameterSet::from_raw(&sps))
+        );
+
+        // delta_scale = 2^31 - 1
+        let sps = [
+            0x67, 0x64, 0x00, 0x28, 0xad, 0x80, 0x00, 0x00, 0x03, 0x00, 0xff, 0xff, 0xff, 0xfe,
+            0x80,
+        ];
+        assert_eq!("delta_scale", invalid(SequenceParameterSet::from_raw(&sps)));
+
+        // pic_init_qp_minus26 = 2^31 - 1
+        let pps = [
+            0x68, 0xce, 0x00, 0x00, 0x03, 0x00, 0x00, 0x7f, 0xff, 0xff, 0xff, 0x62,
+        ];
+        assert_eq!(
+            "pic_init_qp_minus26",
+            invalid(PictureParameterSet::from_raw(&pps))
+        );
+    }
 }
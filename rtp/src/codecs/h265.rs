@@ -0,0 +1,511 @@
+use crate::{
+    codecs::{annexb, KeyframeDetector},
+    packet::Packet,
+    Depacketizer, PayloadGenerator,
+};
+
+const NAL_UNIT_HEADER_SIZE: usize = 2;
+const FU_HEADER_SIZE: usize = 3;
+const AP_UNIT_SIZE_LENGTH: usize = 2;
+
+const NAL_UNIT_TYPE_SHIFT: usize = 1;
+const NAL_UNIT_TYPE_MASK: u8 = 0x3f;
+const FORBIDDEN_BIT_MASK: u8 = 0x80;
+const LAYER_ID_HIGH_MASK: u8 = 0x01;
+const LAYER_ID_LOW_MASK: u8 = 0xf8;
+const TID_MASK: u8 = 0x07;
+
+/// NAL unit types of the intra random access point (IRAP) pictures
+const NAL_UNIT_TYPE_IRAP: std::ops::RangeInclusive<u8> = 16..=23;
+
+/// NAL unit type of a video parameter set (VPS)
+const NAL_UNIT_TYPE_VPS: u8 = 32;
+
+/// NAL unit type of a sequence parameter set (SPS)
+const NAL_UNIT_TYPE_SPS: u8 = 33;
+
+/// NAL unit type of an access unit delimiter
+const NAL_UNIT_TYPE_AUD: u8 = 35;
+
+/// NAL unit type of filler data
+const NAL_UNIT_TYPE_FD: u8 = 38;
+
+/// Payload type of an aggregation packet (AP)
+pub const NAL_UNIT_TYPE_AP: u8 = 48;
+
+/// Payload type of a fragmentation unit (FU)
+pub const NAL_UNIT_TYPE_FU: u8 = 49;
+
+/// Payload type of a payload content information (PACI) packet
+pub const NAL_UNIT_TYPE_PACI: u8 = 50;
+
+/// Retrieves the type of a NAL unit from its two bytes header.
+fn nal_unit_type(header: &[u8]) -> u8 {
+    (header[0] >> NAL_UNIT_TYPE_SHIFT) & NAL_UNIT_TYPE_MASK
+}
+
+/// Retrieves the `nuh_layer_id` of a NAL unit from its two bytes header.
+fn layer_id(header: &[u8]) -> u8 {
+    (header[0] & LAYER_ID_HIGH_MASK) << 5 | (header[1] & LAYER_ID_LOW_MASK) >> 3
+}
+
+/// This payload generator is responsible to generate RTP packet's payloads
+/// from H.265/HEVC data in order to send them into a RTP stream.
+///
+/// It follows the [RFC 7798] and uses single NAL unit packets, aggregation
+/// packets (AP) and fragmentation units (FU). The decoding order number
+/// (DONL) field is never used.
+///
+/// [RFC 7798]: https://tools.ietf.org/html/rfc7798
+#[derive(Clone, Copy, Debug, Default)]
+pub struct H265PayloadGenerator;
+
+impl H265PayloadGenerator {
+    /// Produces the RTP payload of a list of NAL units which fits into one
+    /// RTP packet.
+    ///
+    /// If there is only one unit, a single NAL unit packet is produced.
+    /// Otherwise an aggregation packet is produced.
+    fn generate_payload_from_nal_units(units: &[&[u8]]) -> Vec<u8> {
+        if units.len() == 1 {
+            return Vec::from(units[0]);
+        }
+
+        // The payload header of an aggregation packet follows this wire:
+        //
+        // +---------------+---------------+
+        // |0|1|2|3|4|5|6|7|0|1|2|3|4|5|6|7|
+        // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+        // |F|   Type=48   |  LayerId  | TID |
+        // +-------------+-----------------+
+        //
+        // The F bit is set if any aggregated unit has it, and the LayerId and
+        // TID fields are the lowest of the aggregated units.
+        let forbidden = units.iter().fold(0, |forbidden, unit| {
+            forbidden | unit[0] & FORBIDDEN_BIT_MASK
+        });
+        let layer_id = units.iter().map(|unit| layer_id(unit)).min().unwrap_or(0);
+        let tid = units
+            .iter()
+            .map(|unit| unit[1] & TID_MASK)
+            .min()
+            .unwrap_or(0);
+
+        let size = units.iter().fold(NAL_UNIT_HEADER_SIZE, |size, unit| {
+            size + AP_UNIT_SIZE_LENGTH + unit.len()
+        });
+        let mut payload = Vec::with_capacity(size);
+
+        payload.push(forbidden | NAL_UNIT_TYPE_AP << NAL_UNIT_TYPE_SHIFT | layer_id >> 5);
+        payload.push((layer_id << 3) | tid);
+
+        for unit in units {
+            payload.extend_from_slice(&(unit.len() as u16).to_be_bytes());
+            payload.extend_from_slice(unit);
+        }
+
+        payload
+    }
+
+    /// Generates FU payloads from a NAL unit which does not fit into one RTP
+    /// packet.
+    fn generate_payloads_from_fragmented_nal_unit(mtu: usize, unit: &[u8]) -> Vec<Vec<u8>> {
+        let unit_type = nal_unit_type(unit);
+        let max_fragment_size = mtu - FU_HEADER_SIZE;
+
+        let mut payloads = Vec::new();
+        let mut unit_data_index = NAL_UNIT_HEADER_SIZE;
+
+        while unit_data_index < unit.len() {
+            let payload_size = max_fragment_size.min(unit.len() - unit_data_index);
+            let mut payload = Vec::with_capacity(FU_HEADER_SIZE + payload_size);
+
+            // The payload header is the NAL unit header with the FU type, and
+            // the FU header follows this wire:
+            //
+            // +---------------+
+            // |0|1|2|3|4|5|6|7|
+            // +-+-+-+-+-+-+-+-+
+            // |S|E|  FuType   |
+            // +---------------+
+            payload.push(
+                unit[0] & !(NAL_UNIT_TYPE_MASK << NAL_UNIT_TYPE_SHIFT)
+                    | NAL_UNIT_TYPE_FU << NAL_UNIT_TYPE_SHIFT,
+            );
+            payload.push(unit[1]);
+
+            let mut fu_header = unit_type;
+            if unit_data_index == NAL_UNIT_HEADER_SIZE {
+                fu_header |= 1 << 7;
+            }
+            if unit_data_index + payload_size == unit.len() {
+                fu_header |= 1 << 6;
+            }
+            payload.push(fu_header);
+
+            payload.extend_from_slice(&unit[unit_data_index..unit_data_index + payload_size]);
+            payloads.push(payload);
+
+            unit_data_index += payload_size;
+        }
+
+        payloads
+    }
+}
+
+impl PayloadGenerator for H265PayloadGenerator {
+    fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
+        if payload.is_empty() || mtu <= FU_HEADER_SIZE {
+            return None;
+        }
+
+        let mut output = Vec::new();
+
+        // NAL units waiting to be sent into the same RTP packet
+        let mut pending: Vec<&[u8]> = Vec::new();
+        let mut pending_size = NAL_UNIT_HEADER_SIZE;
+
+        for unit in annexb::nal_units(payload) {
+            if unit.len() <= NAL_UNIT_HEADER_SIZE {
+                continue;
+            }
+
+            let unit_type = nal_unit_type(unit);
+            if unit_type == NAL_UNIT_TYPE_AUD || unit_type == NAL_UNIT_TYPE_FD {
+                continue;
+            }
+
+            // If the unit can not be aggregated with the pending ones, we'll
+            // send the pending ones first.
+            if !pending.is_empty() && pending_size + AP_UNIT_SIZE_LENGTH + unit.len() > mtu {
+                output.push(Self::generate_payload_from_nal_units(&pending));
+
+                pending.clear();
+                pending_size = NAL_UNIT_HEADER_SIZE;
+            }
+
+            if unit.len() > mtu {
+                output.append(&mut Self::generate_payloads_from_fragmented_nal_unit(
+                    mtu, unit,
+                ));
+            } else {
+                pending.push(unit);
+                pending_size += AP_UNIT_SIZE_LENGTH + unit.len();
+            }
+        }
+
+        if !pending.is_empty() {
+            output.push(Self::generate_payload_from_nal_units(&pending));
+        }
+
+        if !output.is_empty() {
+            Some(output)
+        } else {
+            None
+        }
+    }
+}
+
+/// This depacketizer is responsible to rebuild H.265/HEVC access units from
+/// RTP packet's payloads produced according to the [RFC 7798].
+///
+/// The access units are emitted as Annex B byte streams when a packet with
+/// the marker bit is received. A fragmented NAL unit with missing packets
+/// is dropped.
+///
+/// [RFC 7798]: https://tools.ietf.org/html/rfc7798
+#[derive(Clone, Debug, Default)]
+pub struct H265Depacketizer {
+    access_unit: Vec<u8>,
+    fragment: Vec<u8>,
+    last_sequence_number: Option<u16>,
+}
+
+impl H265Depacketizer {
+    /// Appends a complete NAL unit to the current access unit.
+    fn push_nal_unit(&mut self, unit: &[u8]) {
+        self.access_unit.extend_from_slice(&annexb::START_CODE);
+        self.access_unit.extend_from_slice(unit);
+    }
+
+    /// Handles the payload of an aggregation packet.
+    fn depacketize_aggregation_packet(&mut self, payload: &[u8]) {
+        let mut offset = NAL_UNIT_HEADER_SIZE;
+
+        while offset + AP_UNIT_SIZE_LENGTH <= payload.len() {
+            let size = u16::from_be_bytes([payload[offset], payload[offset + 1]]) as usize;
+            offset += AP_UNIT_SIZE_LENGTH;
+
+            if size == 0 || offset + size > payload.len() {
+                return;
+            }
+
+            self.push_nal_unit(&payload[offset..offset + size]);
+            offset += size;
+        }
+    }
+
+    /// Handles the payload of a fragmentation unit.
+    fn depacketize_fragmentation_unit(&mut self, payload: &[u8]) {
+        if payload.len() <= FU_HEADER_SIZE {
+            return;
+        }
+
+        let fu_header = payload[2];
+        let start = fu_header & 0x80 > 0;
+        let end = fu_header & 0x40 > 0;
+
+        if start {
+            // Rebuilding the NAL unit header from the payload header and FU type
+            let fu_type = fu_header & NAL_UNIT_TYPE_MASK;
+
+            self.fragment.clear();
+            self.fragment.push(
+                payload[0] & !(NAL_UNIT_TYPE_MASK << NAL_UNIT_TYPE_SHIFT)
+                    | fu_type << NAL_UNIT_TYPE_SHIFT,
+            );
+            self.fragment.push(payload[1]);
+        } else if self.fragment.is_empty() {
+            // The first fragment has been lost, the unit can not be rebuilt
+            return;
+        }
+
+        self.fragment.extend_from_slice(&payload[FU_HEADER_SIZE..]);
+
+        if end {
+            let fragment = std::mem::take(&mut self.fragment);
+            self.push_nal_unit(&fragment);
+        }
+    }
+}
+
+impl Depacketizer for H265Depacketizer {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        // A fragmented NAL unit can not be completed if a packet is missing
+        if let Some(last_sequence_number) = self.last_sequence_number {
+            if packet.sequence_number != last_sequence_number.wrapping_add(1) {
+                self.fragment.clear();
+            }
+        }
+        self.last_sequence_number = Some(packet.sequence_number);
+
+        let payload = &packet.payload;
+        if payload.len() <= NAL_UNIT_HEADER_SIZE {
+            return None;
+        }
+
+        match nal_unit_type(payload) {
+            NAL_UNIT_TYPE_AP => self.depacketize_aggregation_packet(payload),
+            NAL_UNIT_TYPE_FU => self.depacketize_fragmentation_unit(payload),
+            // PACI packets are not supported
+            NAL_UNIT_TYPE_PACI => {}
+            _ => self.push_nal_unit(payload),
+        }
+
+        if packet.marker && !self.access_unit.is_empty() {
+            Some(std::mem::take(&mut self.access_unit))
+        } else {
+            None
+        }
+    }
+
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        if payload.len() <= NAL_UNIT_HEADER_SIZE {
+            return false;
+        }
+
+        // Only the first fragment of a fragmentation unit is starting it
+        nal_unit_type(payload) != NAL_UNIT_TYPE_FU
+            || (payload.len() > FU_HEADER_SIZE && payload[2] & 0x80 > 0)
+    }
+
+    fn reset(&mut self) {
+        self.access_unit.clear();
+        self.fragment.clear();
+    }
+}
+
+/// This keyframe detector is responsible to detect the H.265 keyframes, as
+/// packetized according to the [RFC 7798].
+///
+/// A keyframe starts with a VPS, a SPS or an intra random access point
+/// (IRAP) picture, carried by a single NAL unit packet, an aggregation
+/// packet, or the first fragment of a fragmentation unit.
+///
+/// [RFC 7798]: https://tools.ietf.org/html/rfc7798
+#[derive(Clone, Copy, Debug, Default)]
+pub struct H265KeyframeDetector;
+
+impl H265KeyframeDetector {
+    /// Checks if a NAL unit type is starting a keyframe.
+    fn is_keyframe_unit(unit_type: u8) -> bool {
+        NAL_UNIT_TYPE_IRAP.contains(&unit_type)
+            || unit_type == NAL_UNIT_TYPE_VPS
+            || unit_type == NAL_UNIT_TYPE_SPS
+    }
+}
+
+impl KeyframeDetector for H265KeyframeDetector {
+    fn is_keyframe(&self, payload: &[u8]) -> bool {
+        if payload.len() <= NAL_UNIT_HEADER_SIZE {
+            return false;
+        }
+
+        match nal_unit_type(payload) {
+            NAL_UNIT_TYPE_AP => {
+                let mut offset = NAL_UNIT_HEADER_SIZE;
+                while offset + AP_UNIT_SIZE_LENGTH < payload.len() {
+                    let size = u16::from_be_bytes([payload[offset], payload[offset + 1]]) as usize;
+                    offset += AP_UNIT_SIZE_LENGTH;
+
+                    if Self::is_keyframe_unit(nal_unit_type(&payload[offset..])) {
+                        return true;
+                    }
+
+                    offset += size;
+                }
+
+                false
+            }
+            NAL_UNIT_TYPE_FU => {
+                let fu_header = payload[NAL_UNIT_HEADER_SIZE];
+
+                fu_header & 0x80 > 0 && Self::is_keyframe_unit(fu_header & NAL_UNIT_TYPE_MASK)
+            }
+            NAL_UNIT_TYPE_PACI => false,
+            unit_type => Self::is_keyframe_unit(unit_type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(sequence_number: u16, marker: bool, payload: &[u8]) -> Packet {
+        Packet {
+            marker,
+            sequence_number,
+            payload: Vec::from(payload),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_generates_a_single_nal_unit_packet() {
+        let mut generator = H265PayloadGenerator;
+        let payload = [0x00, 0x00, 0x00, 0x01, 0x26, 0x01, 0xaf, 0x06];
+
+        let payloads = generator.generate(100, &payload);
+        assert_eq!(Some(vec![vec![0x26, 0x01, 0xaf, 0x06]]), payloads);
+    }
+
+    #[test]
+    fn it_generates_an_aggregation_packet() {
+        let mut generator = H265PayloadGenerator;
+        let payload = [
+            0x00, 0x00, 0x00, 0x01, 0x40, 0x01, 0x0c, // VPS
+            0x00, 0x00, 0x00, 0x01, 0x42, 0x01, 0x01, 0x01, // SPS
+            0x00, 0x00, 0x00, 0x01, 0x44, 0x01, 0xc1, // PPS
+        ];
+
+        let payloads = generator.generate(100, &payload);
+        assert_eq!(
+            Some(vec![vec![
+                0x60, 0x01, // PayloadHdr (Type=48)
+                0x00, 0x03, 0x40, 0x01, 0x0c, // VPS
+                0x00, 0x04, 0x42, 0x01, 0x01, 0x01, // SPS
+                0x00, 0x03, 0x44, 0x01, 0xc1, // PPS
+            ]]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_generates_fragmentation_units() {
+        let mut generator = H265PayloadGenerator;
+        let payload = [0x26, 0x01, 0x01, 0x02, 0x03, 0x04, 0x05];
+
+        let payloads = generator.generate(5, &payload);
+        assert_eq!(
+            Some(vec![
+                vec![0x62, 0x01, 0x93, 0x01, 0x02],
+                vec![0x62, 0x01, 0x13, 0x03, 0x04],
+                vec![0x62, 0x01, 0x53, 0x05],
+            ]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_skips_access_unit_delimiters() {
+        let mut generator = H265PayloadGenerator;
+        let payload = [0x00, 0x00, 0x01, 0x46, 0x01, 0x50];
+
+        assert!(generator.generate(100, &payload).is_none());
+    }
+
+    #[test]
+    fn it_returns_none_when_mtu_is_too_small() {
+        let mut generator = H265PayloadGenerator;
+
+        assert!(generator.generate(3, &[0x26, 0x01, 0xaf]).is_none());
+    }
+
+    #[test]
+    fn it_depacketizes_what_has_been_generated() {
+        let mut generator = H265PayloadGenerator;
+        let mut depacketizer = H265Depacketizer::default();
+
+        let mut access_unit = vec![
+            0x00, 0x00, 0x00, 0x01, 0x40, 0x01, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x42, 0x01, 0x01,
+            0x01, 0x00, 0x00, 0x00, 0x01, 0x26, 0x01,
+        ];
+        access_unit.extend((0..100).map(|i| i as u8 | 0x80));
+
+        let payloads = generator.generate(32, &access_unit).unwrap();
+        assert!(payloads.len() > 2);
+
+        let last = payloads.len() - 1;
+        let mut output = None;
+        for (index, payload) in payloads.iter().enumerate() {
+            output = depacketizer.depacketize(&packet(index as u16, index == last, payload));
+        }
+
+        assert_eq!(Some(access_unit), output);
+    }
+
+    #[test]
+    fn it_drops_fragmented_units_with_missing_packets() {
+        let mut depacketizer = H265Depacketizer::default();
+
+        assert!(depacketizer
+            .depacketize(&packet(1, false, &[0x62, 0x01, 0x93, 0x01, 0x02]))
+            .is_none());
+        assert!(depacketizer
+            .depacketize(&packet(3, true, &[0x62, 0x01, 0x53, 0x05]))
+            .is_none());
+    }
+
+    #[test]
+    fn it_detects_keyframes() {
+        let detector = H265KeyframeDetector;
+
+        // Single NAL unit packets
+        assert!(detector.is_keyframe(&[0x26, 0x01, 0xaf, 0x06]));
+        assert!(detector.is_keyframe(&[0x2a, 0x01, 0xaf, 0x06]));
+        assert!(!detector.is_keyframe(&[0x02, 0x01, 0xd0, 0x06]));
+        assert!(!detector.is_keyframe(&[0x26, 0x01]));
+
+        // Aggregation packets
+        assert!(detector.is_keyframe(&[
+            0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0x0c, 0x00, 0x04, 0x42, 0x01, 0x01, 0x01
+        ]));
+        assert!(!detector.is_keyframe(&[0x60, 0x01, 0x00, 0x03, 0x44, 0x01, 0xc1]));
+
+        // Fragmentation units
+        assert!(detector.is_keyframe(&[0x62, 0x01, 0x93, 0xaf, 0x06]));
+        assert!(!detector.is_keyframe(&[0x62, 0x01, 0x13, 0xaf, 0x06]));
+        assert!(!detector.is_keyframe(&[0x62, 0x01, 0x81, 0xd0, 0x06]));
+    }
+}
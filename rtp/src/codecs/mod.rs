@@ -1,12 +1,43 @@
+#[cfg(any(feature = "h264", feature = "h265"))]
+pub mod annexb;
+#[cfg(feature = "av1")]
+pub mod av1;
+#[cfg(feature = "cn")]
+pub mod cn;
+#[cfg(feature = "flexfec")]
+pub mod flexfec;
 #[cfg(feature = "g711")]
 pub mod g711;
 #[cfg(feature = "g722")]
 pub mod g722;
 #[cfg(feature = "h264")]
 pub mod h264;
+#[cfg(feature = "h265")]
+pub mod h265;
 #[cfg(feature = "opus")]
 pub mod opus;
+#[cfg(any(feature = "g711", feature = "g722"))]
+pub mod ptime;
+#[cfg(feature = "red")]
+pub mod red;
+#[cfg(feature = "rtx")]
+pub mod rtx;
+#[cfg(feature = "telephone-event")]
+pub mod telephone_event;
+#[cfg(feature = "ulpfec")]
+pub mod ulpfec;
 #[cfg(feature = "vp8")]
 pub mod vp8;
 #[cfg(feature = "vp9")]
 pub mod vp9;
+
+/// This trait defines the method that a keyframe detector structure should
+/// implement in order to know if a received RTP packet starts a keyframe,
+/// for instance to switch between layers or to serve a late joiner.
+///
+/// The detection only relies on the RTP packet's payload, without any
+/// previous packet.
+pub trait KeyframeDetector {
+    /// Checks if a RTP packet's payload is the start of a keyframe.
+    fn is_keyframe(&self, payload: &[u8]) -> bool;
+}
@@ -1,30 +1,789 @@
-use crate::PayloadGenerator;
+use crate::{errors::OpusError, packet::Packet, Depacketizer, PayloadGenerator};
+use std::ops::Range;
+
+/// The maximum duration of an Opus packet, 120 ms, in 48 kHz clock ticks
+const MAX_PACKET_DURATION: u32 = 5760;
+
+/// The maximum length of an Opus frame
+const MAX_FRAME_LENGTH: usize = 1275;
+
+const CONFIG_SHIFT: usize = 3;
+const STEREO_MASK: u8 = 0x04;
+const FRAME_COUNT_CODE_MASK: u8 = 0x03;
+const FRAME_COUNT_MASK: u8 = 0x3f;
+const VBR_MASK: u8 = 0x80;
+const PADDING_MASK: u8 = 0x40;
+
+/// The coding mode of an Opus packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OpusMode {
+    /// The SILK-only mode, used for speech.
+    Silk,
+
+    /// The hybrid SILK and CELT mode.
+    Hybrid,
+
+    /// The CELT-only mode, used for music and low latency.
+    Celt,
+}
+
+/// The audio bandwidth of an Opus packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OpusBandwidth {
+    /// 4 kHz bandwidth, 8 kHz effective sample rate.
+    Narrowband,
+
+    /// 6 kHz bandwidth, 12 kHz effective sample rate.
+    Mediumband,
+
+    /// 8 kHz bandwidth, 16 kHz effective sample rate.
+    Wideband,
+
+    /// 12 kHz bandwidth, 24 kHz effective sample rate.
+    SuperWideband,
+
+    /// 20 kHz bandwidth, 48 kHz effective sample rate.
+    Fullband,
+}
+
+/// Represents a parsed Opus packet, as defined in the section 3 of the
+/// [RFC 6716].
+///
+/// The packet starts with a TOC byte following this wire:
+///
+/// ```text
+///  0 1 2 3 4 5 6 7
+/// +-+-+-+-+-+-+-+-+
+/// | config  |s| c |
+/// +-+-+-+-+-+-+-+-+
+/// ```
+///
+/// [RFC 6716]: https://tools.ietf.org/html/rfc6716#section-3
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpusPacket {
+    /// The configuration number of the packet (`config`).
+    pub config: u8,
+
+    /// The coding mode, deduced from the configuration number.
+    pub mode: OpusMode,
+
+    /// The audio bandwidth, deduced from the configuration number.
+    pub bandwidth: OpusBandwidth,
+
+    /// Indicates if the packet is coded in stereo (`s`).
+    pub stereo: bool,
+
+    /// The duration of each frame in 48 kHz clock ticks, deduced from the
+    /// configuration number.
+    pub frame_duration: u32,
+
+    /// The position of each frame of the packet. A frame can be empty, which
+    /// means that no data has been transmitted for it (DTX).
+    pub frames: Vec<Range<usize>>,
+}
+
+impl OpusPacket {
+    /// Parses an Opus packet, checking the requirements of the section 3.4 of
+    /// the [RFC 6716].
+    ///
+    /// [RFC 6716]: https://tools.ietf.org/html/rfc6716#section-3.4
+    pub fn from_raw(packet: &[u8]) -> Result<Self, OpusError> {
+        let toc = *packet.first().ok_or(OpusError::EmptyPacket)?;
+
+        let config = toc >> CONFIG_SHIFT;
+        let (mode, bandwidth, frame_duration) = Self::decode_config(config);
+        let stereo = toc & STEREO_MASK > 0;
+
+        let frames = match toc & FRAME_COUNT_CODE_MASK {
+            // One frame
+            0 => {
+                Self::check_frame_length(packet.len() - 1)?;
+
+                vec![Range {
+                    start: 1,
+                    end: packet.len(),
+                }]
+            }
+            // Two frames of equal length
+            1 => {
+                let length = packet.len() - 1;
+                if !length.is_multiple_of(2) {
+                    return Err(OpusError::InvalidFrameLength);
+                }
+                Self::check_frame_length(length / 2)?;
+
+                vec![1..1 + length / 2, 1 + length / 2..packet.len()]
+            }
+            // Two frames of different lengths
+            2 => {
+                let (first, offset) = Self::read_frame_length(packet, 1)?;
+                if offset + first > packet.len() {
+                    return Err(OpusError::InvalidFrameLength);
+                }
+                Self::check_frame_length(packet.len() - offset - first)?;
+
+                vec![offset..offset + first, offset + first..packet.len()]
+            }
+            // An arbitrary number of frames
+            _ => Self::parse_arbitrary_frames(packet, frame_duration)?,
+        };
+
+        Ok(Self {
+            config,
+            mode,
+            bandwidth,
+            stereo,
+            frame_duration,
+            frames,
+        })
+    }
+
+    /// Computes the duration of the packet in 48 kHz clock ticks.
+    pub fn duration(&self) -> u32 {
+        self.frame_duration * self.frames.len() as u32
+    }
+
+    /// Converts an Opus packet into its self-delimiting form, as defined in
+    /// the appendix B of the [RFC 6716]: the length of the last frame is
+    /// inserted right before the frames.
+    ///
+    /// [RFC 6716]: https://tools.ietf.org/html/rfc6716#appendix-B
+    pub fn self_delimit(packet: &[u8]) -> Result<Vec<u8>, OpusError> {
+        let parsed = Self::from_raw(packet)?;
+        let start = parsed.frames[0].start;
+        let last = parsed.frames[parsed.frames.len() - 1].len();
+
+        let mut data = Vec::with_capacity(packet.len() + 2);
+        data.extend_from_slice(&packet[..start]);
+        Self::write_frame_length(&mut data, last);
+        data.extend_from_slice(&packet[start..]);
+
+        Ok(data)
+    }
+
+    /// Converts a self-delimited Opus packet, at the beginning of `data`,
+    /// back into its standard form. The packet and the length it was taking
+    /// in `data` are returned in a tuple defined this way: `(packet, length)`.
+    pub fn undelimit(data: &[u8]) -> Result<(Vec<u8>, usize), OpusError> {
+        let toc = *data.first().ok_or(OpusError::EmptyPacket)?;
+
+        // The frames' length is computed as `known + count * last`, where
+        // `last` is the self-delimiting length
+        let (offset, known, count, padding) = match toc & FRAME_COUNT_CODE_MASK {
+            0 => (1, 0, 1, 0),
+            1 => (1, 0, 2, 0),
+            2 => {
+                let (first, offset) = Self::read_frame_length(data, 1)?;
+                (offset, first, 1, 0)
+            }
+            _ => {
+                let header = *data
+                    .get(1)
+                    .ok_or(OpusError::InvalidFrameCount { count: 0 })?;
+                let count = (header & FRAME_COUNT_MASK) as usize;
+                if count == 0 {
+                    return Err(OpusError::InvalidFrameCount { count });
+                }
+
+                let (padding, mut offset) = Self::read_padding(data, header)?;
+                if header & VBR_MASK > 0 {
+                    let mut known = 0;
+                    for _ in 0..count - 1 {
+                        let (length, next) = Self::read_frame_length(data, offset)?;
+
+                        known += length;
+                        offset = next;
+                    }
+
+                    (offset, known, 1, padding)
+                } else {
+                    (offset, 0, count, padding)
+                }
+            }
+        };
+
+        let (last, start) = Self::read_frame_length(data, offset)?;
+        let end = start + known + count * last + padding;
+        if end > data.len() {
+            return Err(OpusError::InvalidFrameLength);
+        }
+
+        let mut packet = Vec::with_capacity(end - start + offset);
+        packet.extend_from_slice(&data[..offset]);
+        packet.extend_from_slice(&data[start..end]);
+        Self::from_raw(&packet)?;
+
+        Ok((packet, end))
+    }
+
+    /// Decodes a configuration number into the mode, bandwidth and frame
+    /// duration it stands for, as defined in the table 2 of the [RFC 6716].
+    ///
+    /// [RFC 6716]: https://tools.ietf.org/html/rfc6716#section-3.1
+    fn decode_config(config: u8) -> (OpusMode, OpusBandwidth, u32) {
+        match config {
+            0..=11 => {
+                let bandwidth = match config / 4 {
+                    0 => OpusBandwidth::Narrowband,
+                    1 => OpusBandwidth::Mediumband,
+                    _ => OpusBandwidth::Wideband,
+                };
+                let duration = [480, 960, 1920, 2880][(config % 4) as usize];
+
+                (OpusMode::Silk, bandwidth, duration)
+            }
+            12..=15 => {
+                let bandwidth = if config < 14 {
+                    OpusBandwidth::SuperWideband
+                } else {
+                    OpusBandwidth::Fullband
+                };
+                let duration = [480, 960][(config % 2) as usize];
+
+                (OpusMode::Hybrid, bandwidth, duration)
+            }
+            _ => {
+                let bandwidth = match (config - 16) / 4 {
+                    0 => OpusBandwidth::Narrowband,
+                    1 => OpusBandwidth::Wideband,
+                    2 => OpusBandwidth::SuperWideband,
+                    _ => OpusBandwidth::Fullband,
+                };
+                let duration = [120, 240, 480, 960][(config % 4) as usize];
+
+                (OpusMode::Celt, bandwidth, duration)
+            }
+        }
+    }
+
+    /// Reads a frame length coded on one or two bytes at the provided offset.
+    /// The length and the offset following it are returned in a tuple defined
+    /// this way: `(length, offset)`.
+    pub(crate) fn read_frame_length(
+        packet: &[u8],
+        offset: usize,
+    ) -> Result<(usize, usize), OpusError> {
+        let first = *packet.get(offset).ok_or(OpusError::InvalidFrameLength)? as usize;
+        if first < 252 {
+            return Ok((first, offset + 1));
+        }
+
+        let second = *packet
+            .get(offset + 1)
+            .ok_or(OpusError::InvalidFrameLength)? as usize;
+
+        Ok((second * 4 + first, offset + 2))
+    }
+
+    /// Writes a frame length on one or two bytes, as expected by
+    /// `read_frame_length`.
+    fn write_frame_length(data: &mut Vec<u8>, length: usize) {
+        if length < 252 {
+            data.push(length as u8);
+        } else {
+            let first = 252 + (length & 0x03);
+
+            data.push(first as u8);
+            data.push(((length - first) / 4) as u8);
+        }
+    }
+
+    /// Reads the padding length of a packet with an arbitrary number of
+    /// frames, following the byte provided as `header`. The length and the
+    /// offset following it are returned in a tuple defined this way:
+    /// `(length, offset)`.
+    fn read_padding(packet: &[u8], header: u8) -> Result<(usize, usize), OpusError> {
+        let mut offset = 2;
+        let mut padding = 0;
+
+        // The padding length is coded on as many bytes as needed, each 255
+        // meaning that another byte follows
+        if header & PADDING_MASK > 0 {
+            loop {
+                let byte = *packet.get(offset).ok_or(OpusError::InvalidFrameLength)? as usize;
+                offset += 1;
+
+                if byte == 255 {
+                    padding += 254;
+                } else {
+                    padding += byte;
+                    break;
+                }
+            }
+        }
+
+        Ok((padding, offset))
+    }
+
+    /// Checks that a frame length does not exceed the maximum one.
+    fn check_frame_length(length: usize) -> Result<(), OpusError> {
+        if length > MAX_FRAME_LENGTH {
+            Err(OpusError::InvalidFrameLength)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Parses the frames of a packet with an arbitrary number of frames
+    /// (code 3), which is following this wire after the TOC byte:
+    ///
+    /// ```text
+    ///  0 1 2 3 4 5 6 7
+    /// +-+-+-+-+-+-+-+-+
+    /// |v|p|     M     |
+    /// +-+-+-+-+-+-+-+-+
+    /// ```
+    fn parse_arbitrary_frames(
+        packet: &[u8],
+        frame_duration: u32,
+    ) -> Result<Vec<Range<usize>>, OpusError> {
+        let header = *packet
+            .get(1)
+            .ok_or(OpusError::InvalidFrameCount { count: 0 })?;
+        let vbr = header & VBR_MASK > 0;
+        let count = (header & FRAME_COUNT_MASK) as usize;
+
+        if count == 0 {
+            return Err(OpusError::InvalidFrameCount { count });
+        }
+
+        let duration = frame_duration * count as u32;
+        if duration > MAX_PACKET_DURATION {
+            return Err(OpusError::InvalidDuration { duration });
+        }
+
+        let (padding, mut offset) = Self::read_padding(packet, header)?;
+        if offset + padding > packet.len() {
+            return Err(OpusError::InvalidFrameLength);
+        }
+        let end = packet.len() - padding;
+
+        let mut lengths = Vec::with_capacity(count);
+        if vbr {
+            // The length of each frame but the last one is provided
+            for _ in 0..count - 1 {
+                let (length, next) = Self::read_frame_length(packet, offset)?;
+                Self::check_frame_length(length)?;
+
+                lengths.push(length);
+                offset = next;
+            }
+
+            let total: usize = lengths.iter().sum();
+            if offset + total > end {
+                return Err(OpusError::InvalidFrameLength);
+            }
+
+            let last = end - offset - total;
+            Self::check_frame_length(last)?;
+            lengths.push(last);
+        } else {
+            if offset > end || !(end - offset).is_multiple_of(count) {
+                return Err(OpusError::InvalidFrameLength);
+            }
+
+            let length = (end - offset) / count;
+            Self::check_frame_length(length)?;
+            lengths.resize(count, length);
+        }
+
+        let mut frames = Vec::with_capacity(count);
+        for length in lengths {
+            frames.push(offset..offset + length);
+            offset += length;
+        }
+
+        Ok(frames)
+    }
+}
 
 /// This payload generator is responsible to generate RTP packet's payloads
 /// from Opus data in order to send them into a RTP stream.
 ///
 /// > This payload generator is not using the MTU parameter for payload
-/// > generation.
+/// > generation, since an Opus packet can not be fragmented as stated in
+/// > the [RFC 7587].
+///
+/// The packets are checked before being sent, and their duration is
+/// computed from their TOC byte, so that the packetizer can advance the
+/// RTP timestamp by itself.
+///
+/// [RFC 7587]: https://tools.ietf.org/html/rfc7587#section-4.2
 #[derive(Clone, Copy, Debug, Default)]
 pub struct OpusPayloadGenerator;
 
 impl PayloadGenerator for OpusPayloadGenerator {
-    // TODO: investigate why the MTU is ignored here
     fn generate(&mut self, _mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
-        if payload.len() == 0 {
-            return None;
+        OpusPacket::from_raw(payload).ok()?;
+
+        Some(vec![Vec::from(payload)])
+    }
+
+    fn duration(&self, payload: &[u8]) -> Option<u32> {
+        OpusPacket::from_raw(payload)
+            .ok()
+            .map(|packet| packet.duration())
+    }
+
+    fn is_audio(&self) -> bool {
+        true
+    }
+}
+
+/// Describes the audio which has not been received before a packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OpusGap {
+    /// The sender has stopped to send packets during a silence (DTX), no
+    /// packet is missing.
+    Discontinuous {
+        /// The duration of the silence in 48 kHz clock ticks.
+        duration: u32,
+    },
+
+    /// Some packets have been lost.
+    Lost {
+        /// The duration of the lost audio in 48 kHz clock ticks.
+        duration: u32,
+
+        /// The number of lost packets.
+        packets: u16,
+    },
+}
+
+/// This depacketizer is responsible to extract Opus packets from RTP
+/// packet's payloads, as stated in the [RFC 7587].
+///
+/// Each payload is checked thanks to its TOC byte. The timestamp jumps
+/// between two packets are reported, distinguishing the silences where the
+/// sender has stopped to transmit (DTX) from the lost packets, in order to
+/// drive the packet loss concealment or the FEC of a decoder.
+///
+/// [RFC 7587]: https://tools.ietf.org/html/rfc7587
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpusDepacketizer {
+    expected_timestamp: Option<u32>,
+    last_sequence_number: Option<u16>,
+    gap: Option<OpusGap>,
+}
+
+impl OpusDepacketizer {
+    /// Retrieves the audio which has not been received before the last
+    /// depacketized packet, if any.
+    pub fn gap(&self) -> Option<OpusGap> {
+        self.gap
+    }
+
+    /// Looks for a gap between the last depacketized packet and the provided
+    /// one, which is lasting `duration` clock ticks.
+    fn track(&mut self, packet: &Packet, duration: u32) {
+        self.gap = None;
+        if let (Some(expected_timestamp), Some(last_sequence_number)) =
+            (self.expected_timestamp, self.last_sequence_number)
+        {
+            let elapsed = packet.timestamp.wrapping_sub(expected_timestamp) as i32;
+            let packets = packet
+                .sequence_number
+                .wrapping_sub(last_sequence_number)
+                .wrapping_sub(1);
+
+            // A late or duplicated packet is not starting a gap, and must not
+            // move the expectations backward
+            if elapsed < 0 || packets >= 0x8000 {
+                return;
+            }
+
+            if elapsed > 0 {
+                self.gap = Some(if packets == 0 {
+                    OpusGap::Discontinuous {
+                        duration: elapsed as u32,
+                    }
+                } else {
+                    OpusGap::Lost {
+                        duration: elapsed as u32,
+                        packets,
+                    }
+                });
+            }
         }
 
-        let mut output = Vec::new();
-        output.push(Vec::from(payload));
+        self.expected_timestamp = Some(packet.timestamp.wrapping_add(duration));
+        self.last_sequence_number = Some(packet.sequence_number);
+    }
+}
+
+impl Depacketizer for OpusDepacketizer {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        let parsed = OpusPacket::from_raw(&packet.payload).ok()?;
+        self.track(packet, parsed.duration());
+
+        Some(packet.payload.clone())
+    }
 
-        Some(output)
+    fn duration(&self, data: &[u8]) -> Option<u32> {
+        OpusPacket::from_raw(data)
+            .ok()
+            .map(|packet| packet.duration())
+    }
+}
+
+/// The parameters of a multistream Opus payload (`multiopus`), which are
+/// describing how the channels are coded into several Opus streams.
+///
+/// Each stream of a multistream packet, except the last one, is
+/// self-delimited as defined in the appendix B of the [RFC 6716].
+///
+/// [RFC 6716]: https://tools.ietf.org/html/rfc6716#appendix-B
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpusMultistreamConfig {
+    /// The number of Opus streams of a packet (`num_streams`).
+    pub num_streams: u8,
+
+    /// The number of streams coding two channels (`coupled_streams`), which
+    /// are the first ones of a packet.
+    pub coupled_streams: u8,
+
+    /// The decoded channel of each output channel (`channel_mapping`), 255
+    /// meaning a silent channel.
+    pub channel_mapping: Vec<u8>,
+}
+
+impl OpusMultistreamConfig {
+    /// Parses the parameters from a SDP `fmtp` attribute value, like
+    /// `channel_mapping=0,4,1,2,3,5;num_streams=4;coupled_streams=2`. The
+    /// other parameters are ignored.
+    pub fn from_fmtp(fmtp: &str) -> Option<Self> {
+        let mut num_streams = None;
+        let mut coupled_streams = None;
+        let mut channel_mapping = None;
+
+        for parameter in fmtp.split(';') {
+            let mut parts = parameter.splitn(2, '=');
+            let key = parts.next()?.trim();
+            let value = parts.next().unwrap_or("").trim();
+
+            match key {
+                "num_streams" => num_streams = Some(value.parse().ok()?),
+                "coupled_streams" => coupled_streams = Some(value.parse().ok()?),
+                "channel_mapping" => {
+                    channel_mapping = Some(
+                        value
+                            .split(',')
+                            .map(|channel| channel.trim().parse().ok())
+                            .collect::<Option<Vec<u8>>>()?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        let config = Self {
+            num_streams: num_streams?,
+            coupled_streams: coupled_streams?,
+            channel_mapping: channel_mapping?,
+        };
+
+        if config.is_valid() {
+            Some(config)
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves the number of output channels.
+    pub fn channel_count(&self) -> usize {
+        self.channel_mapping.len()
+    }
+
+    /// Splits a multistream packet into the standard Opus packet of each
+    /// stream. All the streams must have the same duration.
+    pub fn split(&self, payload: &[u8]) -> Result<Vec<Vec<u8>>, OpusError> {
+        if self.num_streams == 0 {
+            return Err(OpusError::InvalidStreamCount { count: 0 });
+        }
+
+        let mut streams = Vec::with_capacity(self.num_streams as usize);
+        let mut offset = 0;
+
+        for _ in 1..self.num_streams {
+            let (stream, length) = OpusPacket::undelimit(&payload[offset..])?;
+
+            streams.push(stream);
+            offset += length;
+        }
+        streams.push(Vec::from(&payload[offset..]));
+
+        Self::check_durations(&streams)?;
+
+        Ok(streams)
+    }
+
+    /// Joins the standard Opus packet of each stream into a multistream
+    /// packet. All the streams must have the same duration.
+    pub fn join<S: AsRef<[u8]>>(&self, streams: &[S]) -> Result<Vec<u8>, OpusError> {
+        if streams.is_empty() || streams.len() != self.num_streams as usize {
+            return Err(OpusError::InvalidStreamCount {
+                count: streams.len(),
+            });
+        }
+
+        Self::check_durations(streams)?;
+
+        let mut payload = Vec::new();
+        for stream in &streams[..streams.len() - 1] {
+            payload.extend(OpusPacket::self_delimit(stream.as_ref())?);
+        }
+        payload.extend_from_slice(streams[streams.len() - 1].as_ref());
+
+        Ok(payload)
+    }
+
+    /// Computes the duration of a multistream packet in 48 kHz clock ticks.
+    pub fn duration(&self, payload: &[u8]) -> Result<u32, OpusError> {
+        let streams = self.split(payload)?;
+
+        Ok(OpusPacket::from_raw(&streams[0])?.duration())
+    }
+
+    /// Checks that the parameters are consistent with each other.
+    fn is_valid(&self) -> bool {
+        let decoded_channels = self.num_streams as usize + self.coupled_streams as usize;
+
+        self.num_streams > 0
+            && self.coupled_streams <= self.num_streams
+            && decoded_channels < 255
+            && !self.channel_mapping.is_empty()
+            && self
+                .channel_mapping
+                .iter()
+                .all(|&channel| channel == 255 || (channel as usize) < decoded_channels)
+    }
+
+    /// Checks that the streams are valid Opus packets sharing the same
+    /// duration.
+    fn check_durations<S: AsRef<[u8]>>(streams: &[S]) -> Result<(), OpusError> {
+        let mut expected = None;
+
+        for stream in streams {
+            let duration = OpusPacket::from_raw(stream.as_ref())?.duration();
+
+            match expected {
+                None => expected = Some(duration),
+                Some(expected) if expected != duration => {
+                    return Err(OpusError::InvalidDuration { duration })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for OpusMultistreamConfig {
+    /// A single coupled stream, which is the layout of a stereo Opus stream.
+    fn default() -> Self {
+        Self {
+            num_streams: 1,
+            coupled_streams: 1,
+            channel_mapping: vec![0, 1],
+        }
+    }
+}
+
+/// This payload generator is responsible to generate RTP packet's payloads
+/// from multistream Opus data (`multiopus`), as produced by a multistream
+/// Opus encoder.
+///
+/// Like the `OpusPayloadGenerator`, the MTU parameter is not used and the
+/// duration of the packets is computed from their streams.
+#[derive(Clone, Debug, Default)]
+pub struct OpusMultistreamPayloadGenerator {
+    /// The parameters negotiated for the stream.
+    pub config: OpusMultistreamConfig,
+}
+
+impl OpusMultistreamPayloadGenerator {
+    /// Instanciates a new generator with the parameters negotiated for the
+    /// stream.
+    pub fn new(config: OpusMultistreamConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl PayloadGenerator for OpusMultistreamPayloadGenerator {
+    fn generate(&mut self, _mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
+        self.config.split(payload).ok()?;
+
+        Some(vec![Vec::from(payload)])
+    }
+
+    fn duration(&self, payload: &[u8]) -> Option<u32> {
+        self.config.duration(payload).ok()
+    }
+
+    fn is_audio(&self) -> bool {
+        true
+    }
+}
+
+/// This depacketizer is responsible to extract multistream Opus data
+/// (`multiopus`) from RTP packet's payloads.
+///
+/// The payloads are checked by splitting them into their streams, which can
+/// be retrieved afterwards. The gaps are reported like the
+/// `OpusDepacketizer` does.
+#[derive(Clone, Debug, Default)]
+pub struct OpusMultistreamDepacketizer {
+    /// The parameters negotiated for the stream.
+    pub config: OpusMultistreamConfig,
+
+    streams: Vec<Vec<u8>>,
+    depacketizer: OpusDepacketizer,
+}
+
+impl OpusMultistreamDepacketizer {
+    /// Instanciates a new depacketizer with the parameters negotiated for
+    /// the stream.
+    pub fn new(config: OpusMultistreamConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Retrieves the standard Opus packet of each stream of the last
+    /// depacketized packet.
+    pub fn streams(&self) -> &[Vec<u8>] {
+        &self.streams
+    }
+
+    /// Retrieves the audio which has not been received before the last
+    /// depacketized packet, if any.
+    pub fn gap(&self) -> Option<OpusGap> {
+        self.depacketizer.gap()
+    }
+}
+
+impl Depacketizer for OpusMultistreamDepacketizer {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        let streams = self.config.split(&packet.payload).ok()?;
+        let duration = OpusPacket::from_raw(&streams[0]).ok()?.duration();
+
+        self.depacketizer.track(packet, duration);
+        self.streams = streams;
+
+        Some(packet.payload.clone())
+    }
+
+    fn duration(&self, data: &[u8]) -> Option<u32> {
+        self.config.duration(data).ok()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packetizer::{OpusMultistreamPacketizer, OpusPacketizer};
 
     #[test]
     fn it_returns_a_rtp_payload() {
@@ -42,4 +801,265 @@ mod tests {
         let payloads = generator.generate(24, &[]);
         assert!(payloads.is_none());
     }
+
+    #[test]
+    fn it_returns_none_when_payload_is_malformed() {
+        let mut generator = OpusPayloadGenerator;
+
+        // Two frames of equal length with an odd length
+        let payloads = generator.generate(24, &[0x79, 0x01, 0x02, 0x03]);
+        assert!(payloads.is_none());
+    }
+
+    #[test]
+    fn it_parses_a_single_frame_packet() {
+        // config=15 (hybrid FB 20 ms), stereo, code 0
+        let packet = OpusPacket::from_raw(&[0x7c, 0x01, 0x02]).unwrap();
+
+        assert_eq!(OpusMode::Hybrid, packet.mode);
+        assert_eq!(OpusBandwidth::Fullband, packet.bandwidth);
+        assert!(packet.stereo);
+        assert_eq!(vec![1..3], packet.frames);
+        assert_eq!(960, packet.duration());
+    }
+
+    #[test]
+    fn it_parses_a_two_frames_packet() {
+        // config=1 (SILK NB 20 ms), mono, code 2 with a first frame of 1 byte
+        let packet = OpusPacket::from_raw(&[0x0a, 0x01, 0xaa, 0xbb, 0xcc]).unwrap();
+
+        assert_eq!(OpusMode::Silk, packet.mode);
+        assert!(!packet.stereo);
+        assert_eq!(vec![2..3, 3..5], packet.frames);
+        assert_eq!(1920, packet.duration());
+    }
+
+    #[test]
+    fn it_parses_an_arbitrary_frames_packet() {
+        // config=16 (CELT NB 2.5 ms), code 3 with 3 VBR frames and 2 bytes of padding
+        let packet =
+            OpusPacket::from_raw(&[0x83, 0xc3, 0x02, 0x01, 0x00, 0xaa, 0xbb, 0xcc, 0x00, 0x00])
+                .unwrap();
+
+        assert_eq!(OpusMode::Celt, packet.mode);
+        assert_eq!(vec![5..6, 6..6, 6..8], packet.frames);
+        assert_eq!(360, packet.duration());
+    }
+
+    #[test]
+    fn it_rejects_packets_longer_than_120_ms() {
+        // config=3 (SILK NB 60 ms), code 3 with 3 CBR frames
+        let packet = OpusPacket::from_raw(&[0x1b, 0x03, 0x01, 0x02, 0x03]);
+
+        assert!(packet.is_err());
+    }
+
+    #[test]
+    fn it_rejects_packets_without_frame() {
+        assert!(OpusPacket::from_raw(&[0x1b, 0x00]).is_err());
+        assert!(OpusPacket::from_raw(&[0x1b]).is_err());
+    }
+
+    #[test]
+    fn it_advances_the_timestamp_from_the_packet_duration() {
+        let mut packetizer = OpusPacketizer::new(1200, 111, 0x1234abcd);
+
+        let first = packetizer.packetize(&[0x7c, 0x01], 0).unwrap();
+        let second = packetizer.packetize(&[0x7c, 0x01], 0).unwrap();
+
+        assert_eq!(960, second[0].timestamp.wrapping_sub(first[0].timestamp));
+    }
+
+    #[test]
+    fn it_depacketizes_what_has_been_packetized() {
+        let mut packetizer = OpusPacketizer::new(1200, 111, 0x1234abcd);
+        let mut depacketizer = OpusDepacketizer::default();
+        let frames = [
+            vec![0x7c, 0x01, 0x02],
+            vec![0x7d, 0x03, 0x04],
+            vec![0x7c, 0x05],
+        ];
+
+        for frame in &frames {
+            let packets = packetizer.packetize(frame, 0).unwrap();
+            assert_eq!(1, packets.len());
+
+            assert_eq!(Some(frame.clone()), depacketizer.depacketize(&packets[0]));
+            assert_eq!(None, depacketizer.gap());
+        }
+    }
+
+    #[test]
+    fn it_returns_none_when_the_payload_is_malformed() {
+        let mut depacketizer = OpusDepacketizer::default();
+        let packet = Packet {
+            payload: vec![0x1b, 0x00],
+            ..Default::default()
+        };
+
+        assert!(depacketizer.depacketize(&packet).is_none());
+    }
+
+    #[test]
+    fn it_reports_discontinuous_transmissions_and_lost_packets() {
+        let mut depacketizer = OpusDepacketizer::default();
+        let packet = |sequence_number: u16, timestamp: u32| Packet {
+            sequence_number,
+            timestamp,
+            payload: vec![0x7c, 0x01],
+            ..Default::default()
+        };
+
+        // The timestamp and the sequence number are wrapping around
+        depacketizer
+            .depacketize(&packet(0xffff, 0xffff_fe00))
+            .unwrap();
+        depacketizer.depacketize(&packet(0, 0x01c0)).unwrap();
+        assert_eq!(None, depacketizer.gap());
+
+        // The sender has stopped to transmit during 100 ms
+        depacketizer
+            .depacketize(&packet(1, 0x01c0 + 960 + 4800))
+            .unwrap();
+        assert_eq!(
+            Some(OpusGap::Discontinuous { duration: 4800 }),
+            depacketizer.gap()
+        );
+
+        // Two packets of 20 ms have been lost
+        depacketizer
+            .depacketize(&packet(4, 0x01c0 + 960 * 4 + 4800))
+            .unwrap();
+        assert_eq!(
+            Some(OpusGap::Lost {
+                duration: 1920,
+                packets: 2
+            }),
+            depacketizer.gap()
+        );
+
+        // A late packet is not reported as a gap
+        depacketizer
+            .depacketize(&packet(3, 0x01c0 + 960 * 3 + 4800))
+            .unwrap();
+        assert_eq!(None, depacketizer.gap());
+
+        // The late packet has not moved the expectations backward
+        depacketizer
+            .depacketize(&packet(5, 0x01c0 + 960 * 5 + 4800))
+            .unwrap();
+        assert_eq!(None, depacketizer.gap());
+    }
+
+    #[test]
+    fn it_converts_packets_from_and_to_the_self_delimiting_framing() {
+        let mut vbr = vec![0x7f, 0x83, 0x02, 0x01];
+        vbr.extend(vec![0x11; 302]);
+        vbr.extend(vec![0x00; 2]);
+
+        let packets = vec![
+            vec![0x7c, 0x01, 0x02],
+            vec![0x7d, 0x01, 0x02, 0x03, 0x04],
+            vec![0x7e, 0x01, 0x02, 0x03, 0x04],
+            vec![0x7f, 0x02, 0x01, 0x02, 0x03, 0x04],
+            vbr,
+        ];
+
+        for packet in &packets {
+            let mut data = OpusPacket::self_delimit(packet).unwrap();
+            let length = data.len();
+            data.extend_from_slice(&[0x7c, 0x42]);
+
+            assert_eq!(
+                (packet.clone(), length),
+                OpusPacket::undelimit(&data).unwrap()
+            );
+        }
+
+        // The last frame length is inserted before the frames
+        assert_eq!(
+            vec![0x7f, 0x83, 0x02, 0x01, 0xfd, 0x0c],
+            OpusPacket::self_delimit(&packets[4]).unwrap()[..6].to_vec()
+        );
+    }
+
+    #[test]
+    fn it_parses_multistream_parameters_from_fmtp() {
+        let config = OpusMultistreamConfig::from_fmtp(
+            "minptime=10;useinbandfec=1; channel_mapping=0,4,1,2,3,5;num_streams=4;coupled_streams=2",
+        )
+        .unwrap();
+
+        assert_eq!(4, config.num_streams);
+        assert_eq!(2, config.coupled_streams);
+        assert_eq!(vec![0, 4, 1, 2, 3, 5], config.channel_mapping);
+        assert_eq!(6, config.channel_count());
+
+        assert!(OpusMultistreamConfig::from_fmtp("num_streams=4;coupled_streams=2").is_none());
+        assert!(OpusMultistreamConfig::from_fmtp(
+            "channel_mapping=0,6;num_streams=4;coupled_streams=2"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn it_splits_and_joins_multistream_packets() {
+        let config = OpusMultistreamConfig::from_fmtp(
+            "channel_mapping=0,4,1,2,3,5;num_streams=4;coupled_streams=2",
+        )
+        .unwrap();
+        let streams = vec![
+            vec![0x7c, 0x01, 0x02],
+            vec![0x7c, 0x03],
+            vec![0x78, 0x04, 0x05, 0x06],
+            vec![0x78, 0x07, 0x08],
+        ];
+
+        let payload = config.join(&streams).unwrap();
+        assert_eq!(
+            vec![0x7c, 0x02, 0x01, 0x02, 0x7c, 0x01, 0x03, 0x78, 0x03, 0x04, 0x05, 0x06, 0x78],
+            payload[..13].to_vec()
+        );
+
+        assert_eq!(streams, config.split(&payload).unwrap());
+        assert_eq!(960, config.duration(&payload).unwrap());
+
+        assert!(matches!(
+            config.join(&streams[..3]),
+            Err(OpusError::InvalidStreamCount { count: 3 })
+        ));
+
+        // The streams are not lasting the same duration
+        let mut streams = streams;
+        streams[1] = vec![0x70, 0x03];
+        assert!(config.join(&streams).is_err());
+    }
+
+    #[test]
+    fn it_depacketizes_what_has_been_packetized_as_multistream() {
+        let config = OpusMultistreamConfig::from_fmtp(
+            "channel_mapping=0,1,4,5,2,3;num_streams=4;coupled_streams=2",
+        )
+        .unwrap();
+        let streams = vec![
+            vec![0x7c, 0x01],
+            vec![0x7c, 0x02],
+            vec![0x78, 0x03],
+            vec![0x78, 0x04],
+        ];
+        let payload = config.join(&streams).unwrap();
+
+        let mut packetizer = OpusMultistreamPacketizer::new(1200, 111, 0x1234abcd);
+        packetizer.generator_mut().config = config.clone();
+        let mut depacketizer = OpusMultistreamDepacketizer::new(config);
+
+        let packets = packetizer.packetize(&payload, 0).unwrap();
+        assert_eq!(1, packets.len());
+
+        assert_eq!(Some(payload), depacketizer.depacketize(&packets[0]));
+        assert_eq!(streams, depacketizer.streams());
+
+        // The payload must contain all the streams
+        assert!(packetizer.packetize(&[0x7c, 0x01], 0).is_none());
+    }
 }
@@ -0,0 +1,118 @@
+/// Describes the packetization time negotiated for an audio stream, with the
+/// `ptime` and `maxptime` SDP attributes defined in the [RFC 4566].
+///
+/// When both are provided, the packets are carrying `ptime` milliseconds of
+/// audio, unless it's exceeding `maxptime`.
+///
+/// [RFC 4566]: https://tools.ietf.org/html/rfc4566#section-6
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PacketizationTime {
+    /// The duration of the audio to put into each packet (`ptime`), in
+    /// milliseconds.
+    pub ptime: Option<u32>,
+
+    /// The maximum duration of the audio to put into a packet (`maxptime`),
+    /// in milliseconds.
+    pub max_ptime: Option<u32>,
+}
+
+impl PacketizationTime {
+    /// Sets the duration of the audio to put into each packet.
+    pub fn with_ptime(mut self, ptime: u32) -> Self {
+        self.ptime = Some(ptime);
+        self
+    }
+
+    /// Sets the maximum duration of the audio to put into a packet.
+    pub fn with_max_ptime(mut self, max_ptime: u32) -> Self {
+        self.max_ptime = Some(max_ptime);
+        self
+    }
+
+    /// Retrieves the duration of the audio to put into each packet, in
+    /// milliseconds. If no packetization time has been negotiated, `None`
+    /// is returned.
+    pub fn duration(&self) -> Option<u32> {
+        match (self.ptime, self.max_ptime) {
+            (Some(ptime), Some(max_ptime)) => Some(ptime.min(max_ptime)),
+            (ptime, max_ptime) => ptime.or(max_ptime),
+        }
+    }
+
+    /// Splits audio data, made of `bytes_per_millisecond` bytes per
+    /// millisecond, into payloads of at most `mtu` bytes, and carrying at
+    /// most the packetization time.
+    ///
+    /// If no payloads can be generated, `None` is returned.
+    pub(crate) fn split(
+        &self,
+        mtu: usize,
+        data: &[u8],
+        bytes_per_millisecond: usize,
+    ) -> Option<Vec<Vec<u8>>> {
+        if mtu == 0 || data.is_empty() {
+            return None;
+        }
+
+        let mut size = mtu;
+        if let Some(duration) = self.duration() {
+            size = size.min(duration as usize * bytes_per_millisecond);
+        }
+        if size == 0 {
+            return None;
+        }
+
+        Some(data.chunks(size).map(Vec::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_resolves_the_packetization_time() {
+        assert_eq!(None, PacketizationTime::default().duration());
+        assert_eq!(
+            Some(20),
+            PacketizationTime::default().with_ptime(20).duration()
+        );
+        assert_eq!(
+            Some(30),
+            PacketizationTime::default().with_max_ptime(30).duration()
+        );
+        assert_eq!(
+            Some(30),
+            PacketizationTime::default()
+                .with_ptime(40)
+                .with_max_ptime(30)
+                .duration()
+        );
+    }
+
+    #[test]
+    fn it_splits_data_according_to_the_packetization_time() {
+        let data = [0x90u8; 100];
+
+        let payloads = PacketizationTime::default()
+            .with_ptime(4)
+            .split(1500, &data, 8)
+            .unwrap();
+        assert_eq!(4, payloads.len());
+        assert!(payloads[..3].iter().all(|payload| payload.len() == 32));
+        assert_eq!(4, payloads[3].len());
+
+        // The MTU is capping the packets
+        let payloads = PacketizationTime::default()
+            .with_ptime(4)
+            .split(10, &data, 8)
+            .unwrap();
+        assert_eq!(10, payloads.len());
+
+        assert!(PacketizationTime::default()
+            .with_ptime(0)
+            .split(1500, &data, 8)
+            .is_none());
+        assert!(PacketizationTime::default().split(0, &data, 8).is_none());
+    }
+}
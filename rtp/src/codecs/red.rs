@@ -0,0 +1,435 @@
+use crate::{
+    packet::{self, Packet},
+    PayloadGenerator,
+};
+use std::collections::VecDeque;
+
+/// The size of the header of a redundant block
+const REDUNDANT_HEADER_SIZE: usize = 4;
+
+/// The size of the header of the primary block
+const PRIMARY_HEADER_SIZE: usize = 1;
+
+/// The maximum timestamp offset of a redundant block, coded on 14 bits
+const MAX_TIMESTAMP_OFFSET: u32 = 0x3fff;
+
+/// The maximum length of a redundant block, coded on 10 bits
+const MAX_BLOCK_LENGTH: usize = 0x03ff;
+
+const FOLLOW_MASK: u8 = 0x80;
+const PAYLOAD_TYPE_MASK: u8 = 0x7f;
+
+/// Represents a block of a redundant audio payload, as defined in the
+/// section 3 of the [RFC 2198].
+///
+/// Each block but the last one, the primary block, is preceded by a header
+/// following this wire:
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |F|   block PT  |  timestamp offset         |   block length    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The primary block is only preceded by the `F` bit, unset, and its
+/// payload type.
+///
+/// [RFC 2198]: https://tools.ietf.org/html/rfc2198#section-3
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RedBlock {
+    /// The payload type of the block (`block PT`).
+    pub payload_type: u8,
+
+    /// The offset of the block's timestamp relative to the RTP packet's one
+    /// (`timestamp offset`), which is 0 for the primary block.
+    pub timestamp_offset: u16,
+
+    /// The payload of the block.
+    pub payload: Vec<u8>,
+}
+
+impl RedBlock {
+    /// Parses the blocks of a redundant audio payload, from the oldest one
+    /// to the primary one. `None` is returned if the payload is malformed.
+    pub fn parse(payload: &[u8]) -> Option<Vec<Self>> {
+        let mut headers = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let header = *payload.get(offset)?;
+            let payload_type = header & PAYLOAD_TYPE_MASK;
+
+            if header & FOLLOW_MASK == 0 {
+                headers.push((payload_type, 0, None));
+                offset += PRIMARY_HEADER_SIZE;
+                break;
+            }
+
+            let header = payload.get(offset + 1..offset + REDUNDANT_HEADER_SIZE)?;
+            let timestamp_offset = ((header[0] as u16) << 6) | (header[1] as u16 >> 2);
+            let length = ((header[1] as usize & 0x03) << 8) | header[2] as usize;
+
+            headers.push((payload_type, timestamp_offset, Some(length)));
+            offset += REDUNDANT_HEADER_SIZE;
+        }
+
+        let mut blocks = Vec::with_capacity(headers.len());
+        for (payload_type, timestamp_offset, length) in headers {
+            let end = match length {
+                Some(length) => offset + length,
+                None => payload.len(),
+            };
+
+            blocks.push(Self {
+                payload_type,
+                timestamp_offset,
+                payload: Vec::from(payload.get(offset..end)?),
+            });
+            offset = end;
+        }
+
+        Some(blocks)
+    }
+
+    /// Serializes blocks, from the oldest one to the primary one, into a
+    /// redundant audio payload.
+    pub fn write(blocks: &[Self]) -> Vec<u8> {
+        let (primary, redundant) = match blocks.split_last() {
+            Some(blocks) => blocks,
+            None => return Vec::new(),
+        };
+
+        let size = blocks
+            .iter()
+            .map(|block| block.payload.len())
+            .sum::<usize>()
+            + redundant.len() * REDUNDANT_HEADER_SIZE
+            + PRIMARY_HEADER_SIZE;
+        let mut payload = Vec::with_capacity(size);
+
+        for block in redundant {
+            let length = block.payload.len();
+
+            payload.push(FOLLOW_MASK | (block.payload_type & PAYLOAD_TYPE_MASK));
+            payload.push((block.timestamp_offset >> 6) as u8);
+            payload.push(((block.timestamp_offset << 2) as u8) | ((length >> 8) as u8 & 0x03));
+            payload.push(length as u8);
+        }
+        payload.push(primary.payload_type & PAYLOAD_TYPE_MASK);
+
+        for block in blocks {
+            payload.extend_from_slice(&block.payload);
+        }
+
+        payload
+    }
+}
+
+/// This payload generator is responsible to generate redundant audio
+/// payloads (`red`), as stated in the [RFC 2198].
+///
+/// It is wrapping another payload generator, like the Opus one, whose
+/// payloads are sent as primary blocks along with the `distance` previous
+/// ones. The timestamp offsets of the redundant blocks are computed from
+/// the duration of the payloads, so the wrapped generator must be able to
+/// compute it.
+///
+/// The previous payloads which do not fit into the MTU, or which are too
+/// old or too long to be described by a block header, are not sent again,
+/// and neither are the older ones. The redundant blocks are then always
+/// describing the packets just before the primary one.
+///
+/// [RFC 2198]: https://tools.ietf.org/html/rfc2198
+#[derive(Clone, Debug, Default)]
+pub struct RedPayloadGenerator<G: PayloadGenerator> {
+    /// The payload type of the primary encoding, like the one of Opus.
+    pub payload_type: u8,
+
+    /// The number of previous payloads to send again with each payload.
+    pub distance: usize,
+
+    generator: G,
+    history: VecDeque<(Vec<u8>, u32)>,
+}
+
+impl<G> RedPayloadGenerator<G>
+where
+    G: PayloadGenerator + Default,
+{
+    /// Instanciates a new generator for a primary encoding.
+    pub fn new(payload_type: u8, distance: usize) -> Self {
+        Self {
+            payload_type,
+            distance,
+            generator: G::default(),
+            history: VecDeque::with_capacity(distance),
+        }
+    }
+}
+
+impl<G: PayloadGenerator> RedPayloadGenerator<G> {
+    /// Retrieves the wrapped payload generator.
+    pub fn generator(&self) -> &G {
+        &self.generator
+    }
+
+    /// Retrieves a mutable reference to the wrapped payload generator.
+    pub fn generator_mut(&mut self) -> &mut G {
+        &mut self.generator
+    }
+
+    /// Wraps a primary payload with the previous ones which can be sent
+    /// again.
+    fn wrap(&self, mtu: usize, primary: &[u8]) -> Vec<u8> {
+        let mut size = PRIMARY_HEADER_SIZE + primary.len();
+        let mut timestamp_offset = 0;
+        let mut blocks = vec![RedBlock {
+            payload_type: self.payload_type,
+            timestamp_offset: 0,
+            payload: Vec::from(primary),
+        }];
+
+        for (payload, duration) in self.history.iter().rev().take(self.distance) {
+            timestamp_offset += duration;
+            if timestamp_offset > MAX_TIMESTAMP_OFFSET || payload.len() > MAX_BLOCK_LENGTH {
+                break;
+            }
+
+            size += REDUNDANT_HEADER_SIZE + payload.len();
+            if size > mtu {
+                break;
+            }
+
+            blocks.push(RedBlock {
+                payload_type: self.payload_type,
+                timestamp_offset: timestamp_offset as u16,
+                payload: payload.clone(),
+            });
+        }
+
+        blocks.reverse();
+        RedBlock::write(&blocks)
+    }
+}
+
+impl<G: PayloadGenerator> PayloadGenerator for RedPayloadGenerator<G> {
+    fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
+        if mtu <= PRIMARY_HEADER_SIZE {
+            return None;
+        }
+
+        let primaries = self
+            .generator
+            .generate(mtu - PRIMARY_HEADER_SIZE, payload)?;
+
+        let mut output = Vec::with_capacity(primaries.len());
+        for primary in primaries {
+            output.push(self.wrap(mtu, &primary));
+
+            if let Some(duration) = self.generator.duration(&primary) {
+                // The distance may have been lowered since the last payload
+                while !self.history.is_empty() && self.history.len() >= self.distance {
+                    self.history.pop_front();
+                }
+                if self.distance > 0 {
+                    self.history.push_back((primary, duration));
+                }
+            } else {
+                // The timestamp offsets can not be computed anymore
+                self.history.clear();
+            }
+        }
+
+        Some(output)
+    }
+
+    fn duration(&self, payload: &[u8]) -> Option<u32> {
+        let blocks = RedBlock::parse(payload)?;
+
+        self.generator.duration(&blocks.last()?.payload)
+    }
+
+    fn is_audio(&self) -> bool {
+        self.generator.is_audio()
+    }
+}
+
+/// This decoder is responsible to extract the primary packets from
+/// redundant audio packets, recovering the previous packets which have not
+/// been received thanks to the redundant blocks.
+///
+/// The packets are rebuilt with the payload type and the timestamp of their
+/// block. Their sequence number is deduced assuming that each packet was
+/// carrying a single primary block, and that the redundant blocks are
+/// describing the packets just before the primary one, as generated by
+/// [`RedPayloadGenerator`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RedDecoder {
+    last_timestamp: Option<u32>,
+}
+
+impl RedDecoder {
+    /// Decodes a redundant audio packet into the packets it contains which
+    /// have not been decoded yet, from the oldest one to the primary one.
+    pub fn decode(&mut self, packet: &Packet) -> Vec<Packet> {
+        let blocks = match RedBlock::parse(&packet.payload) {
+            Some(blocks) => blocks,
+            None => return Vec::new(),
+        };
+
+        let count = blocks.len();
+        let mut packets = Vec::with_capacity(count);
+
+        for (index, block) in blocks.into_iter().enumerate() {
+            let timestamp = packet.timestamp.wrapping_sub(block.timestamp_offset as u32);
+
+            // Skipping the blocks which have already been decoded
+            if let Some(last_timestamp) = self.last_timestamp {
+                if (timestamp.wrapping_sub(last_timestamp) as i32) <= 0 {
+                    continue;
+                }
+            }
+
+            packets.push(Packet {
+                version: packet::RTP_VERSION,
+                marker: packet.marker && index == count - 1,
+                payload_type: block.payload_type,
+                sequence_number: packet
+                    .sequence_number
+                    .wrapping_sub((count - 1 - index) as u16),
+                timestamp,
+                ssrc: packet.ssrc,
+                payload_offset: packet::HEADER_SIZE,
+                payload: block.payload,
+                ..Default::default()
+            });
+            self.last_timestamp = Some(timestamp);
+        }
+
+        packets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::opus::{OpusDepacketizer, OpusPayloadGenerator},
+        packetizer::RedPacketizer,
+        Depacketizer,
+    };
+
+    #[test]
+    fn it_parses_and_writes_blocks() {
+        let blocks = vec![
+            RedBlock {
+                payload_type: 111,
+                timestamp_offset: 960,
+                payload: vec![0x78, 0x01, 0x02],
+            },
+            RedBlock {
+                payload_type: 111,
+                timestamp_offset: 0,
+                payload: vec![0x78, 0x03],
+            },
+        ];
+
+        let payload = RedBlock::write(&blocks);
+        assert_eq!(
+            vec![0xef, 0x0f, 0x00, 0x03, 0x6f, 0x78, 0x01, 0x02, 0x78, 0x03],
+            payload
+        );
+        assert_eq!(Some(blocks), RedBlock::parse(&payload));
+
+        // The redundant block is longer than the payload
+        assert!(RedBlock::parse(&payload[..6]).is_none());
+    }
+
+    #[test]
+    fn it_generates_redundant_payloads() {
+        let mut generator = RedPayloadGenerator::<OpusPayloadGenerator>::new(111, 2);
+        let frames = [
+            vec![0x78, 0x01],
+            vec![0x78, 0x02],
+            vec![0x78, 0x03],
+            vec![0x78, 0x04],
+        ];
+
+        let payloads: Vec<_> = frames
+            .iter()
+            .map(|frame| generator.generate(1200, frame).unwrap().remove(0))
+            .collect();
+
+        let blocks = RedBlock::parse(&payloads[0]).unwrap();
+        assert_eq!(1, blocks.len());
+
+        let blocks = RedBlock::parse(&payloads[3]).unwrap();
+        assert_eq!(3, blocks.len());
+        assert_eq!(frames[1], blocks[0].payload);
+        assert_eq!(1920, blocks[0].timestamp_offset);
+        assert_eq!(frames[2], blocks[1].payload);
+        assert_eq!(960, blocks[1].timestamp_offset);
+        assert_eq!(frames[3], blocks[2].payload);
+        assert_eq!(Some(960), generator.duration(&payloads[3]));
+
+        // The redundant blocks are dropped when they do not fit into the MTU
+        let payloads = generator.generate(9, &frames[0]).unwrap();
+        assert_eq!(2, RedBlock::parse(&payloads[0]).unwrap().len());
+
+        // The history follows a lowered distance
+        generator.distance = 1;
+        let payloads = generator.generate(1200, &frames[1]).unwrap();
+        let blocks = RedBlock::parse(&payloads[0]).unwrap();
+        assert_eq!(2, blocks.len());
+        assert_eq!(frames[0], blocks[0].payload);
+    }
+
+    #[test]
+    fn it_only_sends_the_payloads_just_before_the_primary_one() {
+        let mut generator = RedPayloadGenerator::<OpusPayloadGenerator>::new(111, 2);
+        let mut long_frame = vec![0x78];
+        long_frame.resize(MAX_BLOCK_LENGTH + 1, 0x01);
+
+        generator.generate(1500, &[0x78, 0x01]).unwrap();
+        generator.generate(1500, &long_frame).unwrap();
+
+        // The first payload is not sent again, as it's not just before the
+        // primary one without the long one
+        let payloads = generator.generate(1500, &[0x78, 0x02]).unwrap();
+        assert_eq!(1, RedBlock::parse(&payloads[0]).unwrap().len());
+    }
+
+    #[test]
+    fn it_recovers_lost_packets() {
+        let mut packetizer = RedPacketizer::<OpusPayloadGenerator>::new(1200, 63, 0x1234abcd);
+        *packetizer.generator_mut() = RedPayloadGenerator::new(111, 2);
+        let mut decoder = RedDecoder::default();
+        let mut depacketizer = OpusDepacketizer::default();
+
+        let packets: Vec<_> = (1..=5u8)
+            .map(|frame| packetizer.packetize(&[0x78, frame], 0).unwrap().remove(0))
+            .collect();
+        assert_eq!(960, packets[1].timestamp.wrapping_sub(packets[0].timestamp));
+
+        // The second and the third packets are lost
+        let mut decoded = decoder.decode(&packets[0]);
+        decoded.extend(decoder.decode(&packets[3]));
+        decoded.extend(decoder.decode(&packets[4]));
+
+        assert_eq!(5, decoded.len());
+        for (index, packet) in decoded.iter().enumerate() {
+            assert_eq!(111, packet.payload_type);
+            assert_eq!(packets[index].sequence_number, packet.sequence_number);
+            assert_eq!(packets[index].timestamp, packet.timestamp);
+            assert_eq!(vec![0x78, index as u8 + 1], packet.payload);
+
+            assert!(depacketizer.depacketize(packet).is_some());
+            assert_eq!(None, depacketizer.gap());
+        }
+
+        // The packets which have already been decoded are skipped
+        assert!(decoder.decode(&packets[4]).is_empty());
+    }
+}
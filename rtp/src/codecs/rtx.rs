@@ -0,0 +1,208 @@
+use crate::{packet::Packet, Sequencer};
+
+/// The size of the original sequence number prepended to the payload
+const OSN_SIZE: usize = 2;
+
+/// Associates the payload types of the retransmission streams with the
+/// payload types of the original streams, as negotiated with the `apt`
+/// parameter of the [RFC 4588].
+///
+/// [RFC 4588]: https://tools.ietf.org/html/rfc4588#section-8.1
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RtxMapping {
+    payload_types: Vec<(u8, u8)>,
+}
+
+impl RtxMapping {
+    /// Associates a retransmission payload type with the payload type of the
+    /// original stream, replacing any previous association.
+    pub fn insert(&mut self, payload_type: u8, associated_payload_type: u8) {
+        self.payload_types
+            .retain(|(rtx, apt)| *rtx != payload_type && *apt != associated_payload_type);
+        self.payload_types
+            .push((payload_type, associated_payload_type));
+    }
+
+    /// Associates a retransmission payload type with the payload type given
+    /// by the `apt` parameter of its SDP `fmtp` attribute value, like
+    /// `apt=96;rtx-time=3000`. The associated payload type is returned.
+    pub fn insert_fmtp(&mut self, payload_type: u8, fmtp: &str) -> Option<u8> {
+        let associated_payload_type = fmtp.split(';').find_map(|parameter| {
+            let mut parts = parameter.splitn(2, '=');
+            if parts.next()?.trim() != "apt" {
+                return None;
+            }
+
+            parts.next()?.trim().parse().ok()
+        })?;
+
+        self.insert(payload_type, associated_payload_type);
+
+        Some(associated_payload_type)
+    }
+
+    /// Retrieves the retransmission payload type of an original payload
+    /// type.
+    pub fn payload_type(&self, associated_payload_type: u8) -> Option<u8> {
+        self.payload_types
+            .iter()
+            .find(|(_, apt)| *apt == associated_payload_type)
+            .map(|(rtx, _)| *rtx)
+    }
+
+    /// Retrieves the original payload type of a retransmission payload type.
+    pub fn associated_payload_type(&self, payload_type: u8) -> Option<u8> {
+        self.payload_types
+            .iter()
+            .find(|(rtx, _)| *rtx == payload_type)
+            .map(|(_, apt)| *apt)
+    }
+}
+
+/// This structure is responsible to wrap packets into retransmission
+/// packets, and to unwrap them, as stated in the section 4 of the
+/// [RFC 4588].
+///
+/// The retransmission packets are sent into their own RTP stream, with
+/// their own SSRC, payload types and sequence numbers. Their payload starts
+/// with the original sequence number (OSN):
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         RTP Header                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            OSN                |                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+/// |                  Original RTP Packet Payload                  |
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// [RFC 4588]: https://tools.ietf.org/html/rfc4588#section-4
+#[derive(Clone, Debug)]
+pub struct RtxStream {
+    /// The synchronization source (SSRC) identifier of the retransmission
+    /// stream.
+    pub synchronization_source: u32,
+
+    /// The synchronization source (SSRC) identifier of the original stream.
+    pub media_synchronization_source: u32,
+
+    /// The association between the payload types of both streams.
+    pub mapping: RtxMapping,
+
+    sequencer: Sequencer,
+}
+
+impl RtxStream {
+    /// Instanciates a new retransmission stream for an original one.
+    pub fn new(ssrc: u32, media_ssrc: u32, mapping: RtxMapping) -> Self {
+        Self {
+            synchronization_source: ssrc,
+            media_synchronization_source: media_ssrc,
+            mapping,
+            sequencer: Sequencer::new(),
+        }
+    }
+
+    /// Wraps an original packet into a retransmission packet. `None` is
+    /// returned if its payload type has no retransmission payload type.
+    pub fn wrap(&mut self, packet: &Packet) -> Option<Packet> {
+        let payload_type = self.mapping.payload_type(packet.payload_type)?;
+
+        let mut payload = Vec::with_capacity(OSN_SIZE + packet.payload.len());
+        payload.extend_from_slice(&packet.sequence_number.to_be_bytes());
+        payload.extend_from_slice(&packet.payload);
+
+        Some(Packet {
+            payload_type,
+            sequence_number: self.sequencer.next_sequence_number(),
+            ssrc: self.synchronization_source,
+            payload,
+            raw: None,
+            ..packet.clone()
+        })
+    }
+
+    /// Unwraps a retransmission packet into the original packet. `None` is
+    /// returned if its payload type is unknown, or if it does not contain
+    /// any original sequence number, like the padding-only packets.
+    pub fn unwrap(&self, packet: &Packet) -> Option<Packet> {
+        let payload_type = self.mapping.associated_payload_type(packet.payload_type)?;
+        if packet.payload.len() < OSN_SIZE {
+            return None;
+        }
+
+        Some(Packet {
+            payload_type,
+            sequence_number: u16::from_be_bytes([packet.payload[0], packet.payload[1]]),
+            ssrc: self.media_synchronization_source,
+            payload: Vec::from(&packet.payload[OSN_SIZE..]),
+            raw: None,
+            ..packet.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_maps_payload_types_from_fmtp() {
+        let mut mapping = RtxMapping::default();
+
+        assert_eq!(Some(96), mapping.insert_fmtp(97, "apt=96;rtx-time=3000"));
+        assert_eq!(Some(111), mapping.insert_fmtp(98, "rtx-time=3000; apt=111"));
+        assert_eq!(None, mapping.insert_fmtp(99, "rtx-time=3000"));
+
+        assert_eq!(Some(97), mapping.payload_type(96));
+        assert_eq!(Some(111), mapping.associated_payload_type(98));
+        assert_eq!(None, mapping.associated_payload_type(99));
+
+        mapping.insert(100, 96);
+        assert_eq!(Some(100), mapping.payload_type(96));
+        assert_eq!(None, mapping.associated_payload_type(97));
+    }
+
+    #[test]
+    fn it_wraps_and_unwraps_packets() {
+        let mut mapping = RtxMapping::default();
+        mapping.insert(97, 96);
+        let mut stream = RtxStream::new(0x5678, 0x1234, mapping);
+
+        let raw = [
+            0x90, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x00, 0x00, 0x12, 0x34, 0xbe, 0xde,
+            0x00, 0x01, 0x10, 0xff, 0x00, 0x00, 0x98, 0x36, 0xbe,
+        ];
+        let packet = Packet::from_raw(&raw).unwrap();
+
+        let first = stream.wrap(&packet).unwrap();
+        let second = stream.wrap(&packet).unwrap();
+        assert_eq!(97, first.payload_type);
+        assert_eq!(0x5678, first.ssrc);
+        assert_eq!(packet.timestamp, first.timestamp);
+        assert_eq!(packet.extension_payload, first.extension_payload);
+        assert_eq!(vec![0x69, 0x8f, 0x98, 0x36, 0xbe], first.payload);
+        assert_eq!(
+            first.sequence_number.wrapping_add(1),
+            second.sequence_number
+        );
+
+        let wrapped = Packet::from_raw(&first.to_raw().unwrap()).unwrap();
+        let unwrapped = stream.unwrap(&wrapped).unwrap();
+        assert_eq!(raw.to_vec(), unwrapped.to_raw().unwrap());
+
+        // The packets of the payload types without retransmission are left
+        let mut other = packet.clone();
+        other.payload_type = 111;
+        assert!(stream.wrap(&other).is_none());
+
+        // The padding-only packets do not contain any packet
+        let mut padding = wrapped;
+        padding.payload.clear();
+        assert!(stream.unwrap(&padding).is_none());
+    }
+}
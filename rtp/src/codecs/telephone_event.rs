@@ -0,0 +1,391 @@
+use crate::{packet, packet::Packet, Sequencer};
+
+/// The size of a telephone event payload
+const EVENT_SIZE: usize = 4;
+
+/// The number of times the end of an event is sent
+const END_PACKET_COUNT: usize = 3;
+
+const END_MASK: u8 = 0x80;
+const VOLUME_MASK: u8 = 0x3f;
+
+/// The digits of the DTMF events, indexed by their event code
+const DIGITS: [char; 16] = [
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '*', '#', 'A', 'B', 'C', 'D',
+];
+
+/// Retrieves the digit of a DTMF event code, if it is one.
+pub fn digit(event: u8) -> Option<char> {
+    DIGITS.get(event as usize).copied()
+}
+
+/// Retrieves the DTMF event code of a digit, if it is one.
+pub fn event(digit: char) -> Option<u8> {
+    let digit = digit.to_ascii_uppercase();
+
+    DIGITS
+        .iter()
+        .position(|&candidate| candidate == digit)
+        .map(|event| event as u8)
+}
+
+/// Represents a telephone event payload, as defined in the section 2.3 of
+/// the [RFC 4733].
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     event     |E|R| volume    |          duration             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// [RFC 4733]: https://tools.ietf.org/html/rfc4733#section-2.3
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TelephoneEvent {
+    /// The code of the event (`event`), 0 to 15 being the DTMF digits.
+    pub event: u8,
+
+    /// Indicates if the event has ended (`E`).
+    pub end: bool,
+
+    /// The power level of the tone, expressed in dBm0 after dropping the
+    /// sign (`volume`).
+    pub volume: u8,
+
+    /// The duration of the event since its beginning, in RTP clock ticks
+    /// (`duration`).
+    pub duration: u16,
+}
+
+impl TelephoneEvent {
+    /// Parses a telephone event payload, returning `None` if the payload is
+    /// too short.
+    pub fn from_raw(payload: &[u8]) -> Option<Self> {
+        if payload.len() < EVENT_SIZE {
+            return None;
+        }
+
+        Some(Self {
+            event: payload[0],
+            end: payload[1] & END_MASK > 0,
+            volume: payload[1] & VOLUME_MASK,
+            duration: u16::from_be_bytes([payload[2], payload[3]]),
+        })
+    }
+
+    /// Serializes the event into a telephone event payload.
+    pub fn to_raw(&self) -> Vec<u8> {
+        let mut flags = self.volume & VOLUME_MASK;
+        if self.end {
+            flags |= END_MASK;
+        }
+
+        let duration = self.duration.to_be_bytes();
+
+        vec![self.event, flags, duration[0], duration[1]]
+    }
+}
+
+/// This sender is responsible to generate the RTP packets of telephone
+/// events, as stated in the section 2.5.1 of the [RFC 4733].
+///
+/// The packets of an event share the timestamp of its beginning. The first
+/// one has its marker bit set, then the duration is updated until the end
+/// of the event, which is sent three times.
+///
+/// Since the events are sent into the audio stream, the sequencer of its
+/// packetizer must be provided to generate the packets.
+///
+/// [RFC 4733]: https://tools.ietf.org/html/rfc4733#section-2.5.1
+#[derive(Clone, Debug)]
+pub struct TelephoneEventSender {
+    /// The RTP payload type negotiated for the telephone events.
+    pub payload_type: u8,
+
+    /// The synchronization source (SSRC) identifier of the audio stream.
+    pub synchronization_source: u32,
+
+    /// The power level of the tones to send.
+    pub volume: u8,
+
+    current: Option<(TelephoneEvent, u32)>,
+}
+
+impl TelephoneEventSender {
+    /// Instanciates a new sender for the events of an audio stream.
+    pub fn new(payload_type: u8, ssrc: u32) -> Self {
+        Self {
+            payload_type,
+            synchronization_source: ssrc,
+            volume: 10,
+            current: None,
+        }
+    }
+
+    /// Starts a new event at the provided timestamp, generating its first
+    /// packet. The event in progress, if any, is dropped.
+    pub fn start(&mut self, sequencer: &mut Sequencer, event: u8, timestamp: u32) -> Packet {
+        let event = TelephoneEvent {
+            event,
+            end: false,
+            volume: self.volume,
+            duration: 0,
+        };
+        self.current = Some((event, timestamp));
+
+        self.packet(sequencer, event, timestamp, true)
+    }
+
+    /// Updates the duration of the event in progress, generating a packet
+    /// for it. `None` is returned if there is no event in progress.
+    pub fn update(&mut self, sequencer: &mut Sequencer, duration: u16) -> Option<Packet> {
+        let (mut event, timestamp) = self.current?;
+        event.duration = duration;
+        self.current = Some((event, timestamp));
+
+        Some(self.packet(sequencer, event, timestamp, false))
+    }
+
+    /// Ends the event in progress with its final duration, generating the
+    /// three packets of its end. `None` is returned if there is no event in
+    /// progress.
+    pub fn end(&mut self, sequencer: &mut Sequencer, duration: u16) -> Option<Vec<Packet>> {
+        let (mut event, timestamp) = self.current.take()?;
+        event.duration = duration;
+        event.end = true;
+
+        Some(
+            (0..END_PACKET_COUNT)
+                .map(|_| self.packet(sequencer, event, timestamp, false))
+                .collect(),
+        )
+    }
+
+    /// Indicates if an event is in progress.
+    pub fn is_sending(&self) -> bool {
+        self.current.is_some()
+    }
+
+    fn packet(
+        &self,
+        sequencer: &mut Sequencer,
+        event: TelephoneEvent,
+        timestamp: u32,
+        marker: bool,
+    ) -> Packet {
+        Packet {
+            version: packet::RTP_VERSION,
+            marker,
+            payload_type: self.payload_type,
+            sequence_number: sequencer.next_sequence_number(),
+            timestamp,
+            ssrc: self.synchronization_source,
+            payload_offset: packet::HEADER_SIZE,
+            payload: event.to_raw(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A notification emitted by the `TelephoneEventReceiver`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TelephoneEventNotification {
+    /// An event has begun, like a key being pressed.
+    Pressed {
+        /// The code of the event.
+        event: u8,
+
+        /// The timestamp of the beginning of the event.
+        timestamp: u32,
+    },
+
+    /// An event has ended, like a key being released.
+    Released {
+        /// The code of the event.
+        event: u8,
+
+        /// The timestamp of the beginning of the event.
+        timestamp: u32,
+
+        /// The last known duration of the event, in RTP clock ticks.
+        duration: u16,
+    },
+}
+
+/// This receiver is responsible to turn the received telephone event
+/// packets into press and release notifications.
+///
+/// The events are identified by their timestamp, so that the retransmitted
+/// packets are notified only once. If the end of an event has been lost,
+/// its release is notified when the next event begins.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TelephoneEventReceiver {
+    current: Option<(TelephoneEvent, u32)>,
+}
+
+impl TelephoneEventReceiver {
+    /// Handles a received telephone event packet, returning the
+    /// notifications it triggers.
+    pub fn receive(&mut self, packet: &Packet) -> Vec<TelephoneEventNotification> {
+        let mut notifications = Vec::new();
+
+        let event = match TelephoneEvent::from_raw(&packet.payload) {
+            Some(event) => event,
+            None => return notifications,
+        };
+
+        match self.current {
+            Some((current, timestamp)) if timestamp == packet.timestamp => {
+                if current.end {
+                    return notifications;
+                }
+            }
+            Some((current, timestamp)) => {
+                // Dropping the packets of an older event
+                if (packet.timestamp.wrapping_sub(timestamp) as i32) < 0 {
+                    return notifications;
+                }
+
+                if !current.end {
+                    notifications.push(TelephoneEventNotification::Released {
+                        event: current.event,
+                        timestamp,
+                        duration: current.duration,
+                    });
+                }
+
+                notifications.push(TelephoneEventNotification::Pressed {
+                    event: event.event,
+                    timestamp: packet.timestamp,
+                });
+            }
+            None => notifications.push(TelephoneEventNotification::Pressed {
+                event: event.event,
+                timestamp: packet.timestamp,
+            }),
+        }
+
+        if event.end {
+            notifications.push(TelephoneEventNotification::Released {
+                event: event.event,
+                timestamp: packet.timestamp,
+                duration: event.duration,
+            });
+        }
+
+        self.current = Some((event, packet.timestamp));
+
+        notifications
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packetizer::G711Packetizer;
+
+    #[test]
+    fn it_parses_and_serializes_events() {
+        let payload = [0x0b, 0x8a, 0x03, 0x20];
+        let event = TelephoneEvent::from_raw(&payload).unwrap();
+
+        assert_eq!(11, event.event);
+        assert!(event.end);
+        assert_eq!(10, event.volume);
+        assert_eq!(800, event.duration);
+        assert_eq!(Some('#'), digit(event.event));
+        assert_eq!(Some(11), super::event('#'));
+
+        assert_eq!(payload.to_vec(), event.to_raw());
+        assert!(TelephoneEvent::from_raw(&payload[..3]).is_none());
+    }
+
+    #[test]
+    fn it_sends_the_packets_of_an_event() {
+        let mut packetizer = G711Packetizer::new(1500, 0, 0x1234abcd);
+        let timestamp = packetizer.timestamp();
+        let mut sender = TelephoneEventSender::new(101, 0x1234abcd);
+
+        let start = sender.start(packetizer.sequencer_mut(), 5, timestamp);
+        let update = sender.update(packetizer.sequencer_mut(), 400).unwrap();
+        let end = sender.end(packetizer.sequencer_mut(), 800).unwrap();
+
+        assert!(start.marker);
+        assert!(!update.marker);
+        assert_eq!(END_PACKET_COUNT, end.len());
+        assert!(!sender.is_sending());
+        assert!(sender.update(packetizer.sequencer_mut(), 1200).is_none());
+
+        let packets: Vec<_> = [start, update].iter().chain(end.iter()).cloned().collect();
+        for (index, packet) in packets.iter().enumerate() {
+            assert_eq!(101, packet.payload_type);
+            assert_eq!(timestamp, packet.timestamp);
+            assert_eq!(
+                packets[0].sequence_number.wrapping_add(index as u16),
+                packet.sequence_number
+            );
+        }
+
+        let event = TelephoneEvent::from_raw(&packets[4].payload).unwrap();
+        assert!(event.end);
+        assert_eq!(800, event.duration);
+
+        // The audio packets are following the events
+        let audio = packetizer.packetize(&[0xff; 160], 160).unwrap();
+        assert_eq!(
+            packets[4].sequence_number.wrapping_add(1),
+            audio[0].sequence_number
+        );
+    }
+
+    #[test]
+    fn it_notifies_pressed_and_released_digits() {
+        let mut sequencer = Sequencer::new();
+        let mut sender = TelephoneEventSender::new(101, 0x1234abcd);
+        let mut receiver = TelephoneEventReceiver::default();
+
+        let start = sender.start(&mut sequencer, 1, 1000);
+        let end = sender.end(&mut sequencer, 800).unwrap();
+
+        assert_eq!(
+            vec![TelephoneEventNotification::Pressed {
+                event: 1,
+                timestamp: 1000
+            }],
+            receiver.receive(&start)
+        );
+        assert_eq!(
+            vec![TelephoneEventNotification::Released {
+                event: 1,
+                timestamp: 1000,
+                duration: 800
+            }],
+            receiver.receive(&end[0])
+        );
+        assert!(receiver.receive(&end[1]).is_empty());
+        assert!(receiver.receive(&end[2]).is_empty());
+
+        // The end of the next event is lost
+        let start = sender.start(&mut sequencer, 2, 3000);
+        let update = sender.update(&mut sequencer, 400).unwrap();
+        assert_eq!(1, receiver.receive(&start).len());
+        assert!(receiver.receive(&update).is_empty());
+
+        let start = sender.start(&mut sequencer, 3, 5000);
+        assert_eq!(
+            vec![
+                TelephoneEventNotification::Released {
+                    event: 2,
+                    timestamp: 3000,
+                    duration: 400
+                },
+                TelephoneEventNotification::Pressed {
+                    event: 3,
+                    timestamp: 5000
+                }
+            ],
+            receiver.receive(&start)
+        );
+    }
+}
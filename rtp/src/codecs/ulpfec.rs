@@ -0,0 +1,1092 @@
+use crate::{
+    packet::{self, Packet},
+    Sequencer,
+};
+use std::collections::VecDeque;
+
+/// The size of the FEC header
+const FEC_HEADER_SIZE: usize = 10;
+
+/// The size of the FEC level 0 header with a short mask
+const SHORT_LEVEL_HEADER_SIZE: usize = 4;
+
+/// The size of the FEC level 0 header with a long mask
+const LONG_LEVEL_HEADER_SIZE: usize = 8;
+
+/// The number of packets which can be protected with a short mask
+pub const SHORT_MASK_SIZE: usize = 16;
+
+/// The number of packets which can be protected with a long mask
+pub const LONG_MASK_SIZE: usize = 48;
+
+/// The size of the protected header fields of a packet, as they are laid
+/// out in its bit string: the first two bytes, the length and the timestamp
+const BIT_STRING_HEADER_SIZE: usize = 8;
+
+/// The number of media packets kept by the decoder to recover the others
+const MEDIA_HISTORY_SIZE: usize = 2 * LONG_MASK_SIZE;
+
+/// The number of FEC packets kept by the decoder while they can not be
+/// used yet
+const FEC_HISTORY_SIZE: usize = LONG_MASK_SIZE;
+
+const LONG_MASK: u8 = 0x40;
+const RECOVERY_MASK: u8 = 0x3f;
+
+/// The number of media packets up to which the mask tables are provided
+const MASK_TABLE_SIZE: usize = 12;
+
+/// The masks tuned for random losses, for each number of media packets from
+/// 1 to 12, and each number of FEC packets from 1 to the number of media
+/// packets. The bit `i` of a mask stands for the `i`th media packet.
+///
+/// They have been precomputed by a search of the masks recovering the most
+/// loss patterns, the patterns with the fewest losses coming first.
+const RANDOM_MASK_TABLE: [&[u16]; 78] = [
+    // 1 media packet
+    &[0x001],
+    // 2 media packets
+    &[0x003],
+    &[0x001, 0x002],
+    // 3 media packets
+    &[0x007],
+    &[0x003, 0x005],
+    &[0x001, 0x002, 0x004],
+    // 4 media packets
+    &[0x00f],
+    &[0x007, 0x00a],
+    &[0x003, 0x005, 0x009],
+    &[0x001, 0x002, 0x004, 0x008],
+    // 5 media packets
+    &[0x01f],
+    &[0x00b, 0x016],
+    &[0x005, 0x00b, 0x012],
+    &[0x003, 0x005, 0x009, 0x011],
+    &[0x001, 0x002, 0x004, 0x008, 0x010],
+    // 6 media packets
+    &[0x03f],
+    &[0x017, 0x02b],
+    &[0x013, 0x025, 0x00e],
+    &[0x003, 0x015, 0x00c, 0x030],
+    &[0x003, 0x005, 0x009, 0x011, 0x021],
+    &[0x001, 0x002, 0x004, 0x008, 0x010, 0x020],
+    // 7 media packets
+    &[0x07f],
+    &[0x02b, 0x057],
+    &[0x00f, 0x039, 0x053],
+    &[0x019, 0x023, 0x045, 0x016],
+    &[0x003, 0x015, 0x041, 0x00c, 0x030],
+    &[0x003, 0x005, 0x009, 0x011, 0x021, 0x041],
+    &[0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040],
+    // 8 media packets
+    &[0x0ff],
+    &[0x0ab, 0x05e],
+    &[0x035, 0x04f, 0x093],
+    &[0x093, 0x0c6, 0x06c, 0x0d8],
+    &[0x011, 0x029, 0x043, 0x085, 0x026],
+    &[0x021, 0x012, 0x042, 0x084, 0x028, 0x0e0],
+    &[0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081],
+    &[0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080],
+    // 9 media packets
+    &[0x1ff],
+    &[0x0af, 0x15e],
+    &[0x04f, 0x0b3, 0x125],
+    &[0x027, 0x089, 0x11a, 0x05c],
+    &[0x00d, 0x031, 0x0c1, 0x103, 0x056],
+    &[0x011, 0x023, 0x049, 0x105, 0x046, 0x082],
+    &[0x00b, 0x021, 0x041, 0x006, 0x082, 0x034, 0x108],
+    &[0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100,
+    ],
+    // 10 media packets
+    &[0x3ff],
+    &[0x15f, 0x2ab],
+    &[0x12d, 0x0b6, 0x24e],
+    &[0x04f, 0x0b9, 0x1c1, 0x213],
+    &[0x0a3, 0x309, 0x02c, 0x290, 0x1e0],
+    &[0x0a1, 0x203, 0x046, 0x094, 0x0c8, 0x380],
+    &[0x011, 0x023, 0x045, 0x089, 0x086, 0x102, 0x204],
+    &[0x201, 0x102, 0x024, 0x084, 0x018, 0x210, 0x140, 0x380],
+    &[
+        0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101, 0x201,
+    ],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100, 0x200,
+    ],
+    // 11 media packets
+    &[0x7ff],
+    &[0x2af, 0x55e],
+    &[0x12f, 0x4b3, 0x25a],
+    &[0x0ab, 0x117, 0x265, 0x459],
+    &[0x271, 0x705, 0x116, 0x22c, 0x3c0],
+    &[0x055, 0x4a1, 0x701, 0x406, 0x164, 0x448],
+    &[0x023, 0x089, 0x016, 0x184, 0x424, 0x0d0, 0x2a0],
+    &[0x021, 0x106, 0x064, 0x048, 0x230, 0x450, 0x4a0, 0x500],
+    &[
+        0x007, 0x041, 0x101, 0x022, 0x082, 0x204, 0x018, 0x210, 0x440,
+    ],
+    &[
+        0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101, 0x201, 0x401,
+    ],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100, 0x200, 0x400,
+    ],
+    // 12 media packets
+    &[0xfff],
+    &[0xaaf, 0x57e],
+    &[0x597, 0x92d, 0x276],
+    &[0x457, 0x88b, 0x13e, 0x2e6],
+    &[0x11d, 0x293, 0x847, 0x436, 0x0f8],
+    &[0x071, 0x08d, 0x381, 0x515, 0xc03, 0x0c2],
+    &[0x603, 0x016, 0x902, 0xa18, 0x430, 0x360, 0x8a0],
+    &[0x203, 0x406, 0x842, 0xc08, 0x250, 0x920, 0x1c0, 0x700],
+    &[
+        0x023, 0x201, 0x809, 0x102, 0x044, 0x910, 0x0a0, 0x860, 0xc00,
+    ],
+    &[
+        0x041, 0x802, 0x014, 0x044, 0x108, 0x420, 0x8c0, 0x480, 0x900, 0x600,
+    ],
+    &[
+        0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101, 0x201, 0x401, 0x801,
+    ],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100, 0x200, 0x400, 0x800,
+    ],
+];
+
+/// The masks tuned for bursty losses, laid out like the random ones.
+///
+/// They have been precomputed by a search of the masks recovering the most
+/// bursts of consecutive losses, the shortest bursts coming first, and then
+/// the most loss patterns like the random ones.
+const BURSTY_MASK_TABLE: [&[u16]; 78] = [
+    // 1 media packet
+    &[0x001],
+    // 2 media packets
+    &[0x003],
+    &[0x001, 0x002],
+    // 3 media packets
+    &[0x007],
+    &[0x003, 0x005],
+    &[0x001, 0x002, 0x004],
+    // 4 media packets
+    &[0x00f],
+    &[0x007, 0x00a],
+    &[0x003, 0x005, 0x009],
+    &[0x001, 0x002, 0x004, 0x008],
+    // 5 media packets
+    &[0x01f],
+    &[0x015, 0x01a],
+    &[0x005, 0x00b, 0x012],
+    &[0x003, 0x005, 0x009, 0x011],
+    &[0x001, 0x002, 0x004, 0x008, 0x010],
+    // 6 media packets
+    &[0x03f],
+    &[0x017, 0x03a],
+    &[0x015, 0x029, 0x032],
+    &[0x00d, 0x011, 0x006, 0x028],
+    &[0x003, 0x005, 0x009, 0x011, 0x021],
+    &[0x001, 0x002, 0x004, 0x008, 0x010, 0x020],
+    // 7 media packets
+    &[0x07f],
+    &[0x057, 0x03a],
+    &[0x039, 0x04b, 0x01e],
+    &[0x023, 0x00e, 0x052, 0x038],
+    &[0x009, 0x025, 0x022, 0x044, 0x050],
+    &[0x003, 0x005, 0x009, 0x011, 0x021, 0x041],
+    &[0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040],
+    // 8 media packets
+    &[0x0ff],
+    &[0x05d, 0x0ea],
+    &[0x04f, 0x096, 0x0e4],
+    &[0x02d, 0x0a3, 0x066, 0x0d2],
+    &[0x015, 0x043, 0x0a1, 0x048, 0x098],
+    &[0x041, 0x00a, 0x052, 0x084, 0x028, 0x090],
+    &[0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081],
+    &[0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080],
+    // 9 media packets
+    &[0x1ff],
+    &[0x0eb, 0x15d],
+    &[0x04f, 0x0f2, 0x164],
+    &[0x059, 0x145, 0x062, 0x096],
+    &[0x043, 0x191, 0x0c4, 0x148, 0x070],
+    &[0x029, 0x0c1, 0x105, 0x00a, 0x098, 0x110],
+    &[0x005, 0x021, 0x181, 0x082, 0x088, 0x050, 0x140],
+    &[0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100,
+    ],
+    // 10 media packets
+    &[0x3ff],
+    &[0x15f, 0x2ea],
+    &[0x24b, 0x0f2, 0x364],
+    &[0x069, 0x18b, 0x22e, 0x318],
+    &[0x0e1, 0x10d, 0x213, 0x02a, 0x150],
+    &[0x013, 0x20b, 0x241, 0x106, 0x284, 0x320],
+    &[0x041, 0x121, 0x302, 0x104, 0x208, 0x250, 0x2a0],
+    &[0x005, 0x081, 0x301, 0x202, 0x044, 0x108, 0x210, 0x120],
+    &[
+        0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101, 0x201,
+    ],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100, 0x200,
+    ],
+    // 11 media packets
+    &[0x7ff],
+    &[0x55d, 0x3ea],
+    &[0x25b, 0x327, 0x4b2],
+    &[0x1a3, 0x629, 0x472, 0x334],
+    &[0x0a5, 0x0f2, 0x322, 0x48a, 0x6c4],
+    &[0x10b, 0x131, 0x056, 0x09c, 0x304, 0x610],
+    &[0x281, 0x409, 0x10a, 0x20c, 0x310, 0x430, 0x260],
+    &[0x00b, 0x085, 0x102, 0x242, 0x028, 0x510, 0x4a0, 0x280],
+    &[
+        0x021, 0x041, 0x405, 0x102, 0x402, 0x204, 0x208, 0x410, 0x280,
+    ],
+    &[
+        0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101, 0x201, 0x401,
+    ],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100, 0x200, 0x400,
+    ],
+    // 12 media packets
+    &[0xfff],
+    &[0x57d, 0xbab],
+    &[0x27b, 0xb25, 0x792],
+    &[0x237, 0xd11, 0x17c, 0x6d4],
+    &[0x871, 0x8a6, 0xe42, 0x94c, 0xd10],
+    &[0x645, 0x883, 0xc22, 0x41c, 0x984, 0xa10],
+    &[0x083, 0x105, 0x849, 0x812, 0x48c, 0x310, 0x4b0],
+    &[0x019, 0x085, 0x282, 0x0c8, 0x828, 0xa10, 0x340, 0xc80],
+    &[
+        0x101, 0x022, 0x502, 0x484, 0x408, 0x610, 0x240, 0x880, 0xb00,
+    ],
+    &[
+        0x005, 0x083, 0x101, 0x201, 0x022, 0x602, 0x802, 0x808, 0x410, 0x440,
+    ],
+    &[
+        0x003, 0x005, 0x009, 0x011, 0x021, 0x041, 0x081, 0x101, 0x201, 0x401, 0x801,
+    ],
+    &[
+        0x001, 0x002, 0x004, 0x008, 0x010, 0x020, 0x040, 0x080, 0x100, 0x200, 0x400, 0x800,
+    ],
+];
+
+/// The pattern used to build the protection masks.
+///
+/// The `Random` and `Bursty` patterns come from tables precomputed up to 12
+/// media packets, where the media packets are protected several times. Above
+/// 12 media packets, they fall back to the interleaved pattern, as libwebrtc
+/// does. The other patterns protect each media packet a single time.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FecMaskPattern {
+    /// Each FEC packet protects a block of consecutive media packets, so
+    /// that a loss in each block can be recovered.
+    #[default]
+    Block,
+
+    /// The media packets are interleaved between the FEC packets, so that
+    /// a burst of as many losses as there are FEC packets can be recovered.
+    Interleaved,
+
+    /// The masks of a table tuned for random losses, recovering as many
+    /// losses of a few packets as possible.
+    Random,
+
+    /// The masks of a table tuned for bursty losses, recovering any burst
+    /// of as many losses as there are FEC packets, and then as many other
+    /// losses as possible.
+    Bursty,
+}
+
+impl FecMaskPattern {
+    /// Builds the protection masks of `fec_count` FEC packets protecting
+    /// `media_count` media packets. The bit `i` of a mask stands for the
+    /// `i`th media packet.
+    ///
+    /// There can not be more FEC packets than media packets.
+    pub fn masks(self, media_count: usize, fec_count: usize) -> Vec<u64> {
+        let media_count = media_count.min(LONG_MASK_SIZE);
+        let fec_count = fec_count.min(media_count);
+
+        if fec_count == 0 {
+            return Vec::new();
+        }
+
+        let table = match self {
+            Self::Random => Some(&RANDOM_MASK_TABLE),
+            Self::Bursty => Some(&BURSTY_MASK_TABLE),
+            Self::Block | Self::Interleaved => None,
+        };
+
+        if let Some(table) = table.filter(|_| media_count <= MASK_TABLE_SIZE) {
+            let index = media_count * (media_count - 1) / 2 + fec_count - 1;
+
+            return table[index].iter().map(|mask| u64::from(*mask)).collect();
+        }
+
+        let mut masks = vec![0u64; fec_count];
+        let block_size = media_count.div_ceil(fec_count);
+        for index in 0..media_count {
+            let fec = match self {
+                Self::Block => index / block_size,
+                _ => index % fec_count,
+            };
+
+            masks[fec] |= 1 << index;
+        }
+
+        masks
+    }
+}
+
+/// Builds the bit string of a raw packet, as defined in the section 10.2 of
+/// the [RFC 5109], padded with zeros up to `size` bytes of data.
+///
+/// [RFC 5109]: https://tools.ietf.org/html/rfc5109#section-10.2
+fn bit_string(raw: &[u8], size: usize) -> Vec<u8> {
+    let data = &raw[packet::HEADER_SIZE..];
+    let length = (data.len() as u16).to_be_bytes();
+
+    let mut bits = vec![0; BIT_STRING_HEADER_SIZE + size];
+    bits[0] = raw[0];
+    bits[1] = raw[1];
+    bits[2..4].copy_from_slice(&length);
+    bits[4..8].copy_from_slice(&raw[4..8]);
+
+    let copied = data.len().min(size);
+    bits[BIT_STRING_HEADER_SIZE..BIT_STRING_HEADER_SIZE + copied].copy_from_slice(&data[..copied]);
+
+    bits
+}
+
+/// Applies a XOR between two bit strings, the second one being at least as
+/// long as the first one.
+fn xor(bits: &mut [u8], other: &[u8]) {
+    bits.iter_mut()
+        .zip(other)
+        .for_each(|(byte, other)| *byte ^= other);
+}
+
+/// Represents the content of an ULPFEC packet with a single protection
+/// level, as defined in the section 7 of the [RFC 5109].
+///
+/// The FEC header is following this wire:
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |E|L|P|X|  CC   |M| PT recovery |            SN base            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          TS recovery                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        length recovery        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// It is followed by the level 0 header, made of the protection length and
+/// a mask of 16 bits, or 48 bits when `L` is set, and by the level 0
+/// payload.
+///
+/// [RFC 5109]: https://tools.ietf.org/html/rfc5109#section-7
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UlpfecPayload {
+    /// The recovery bits of the `P`, `X` and `CC` fields.
+    pub recovery_flags: u8,
+
+    /// The recovery bits of the `M` and `PT` fields.
+    pub recovery_payload_type: u8,
+
+    /// The lowest sequence number of the protected packets (`SN base`).
+    pub sequence_number_base: u16,
+
+    /// The recovery bits of the timestamps (`TS recovery`).
+    pub timestamp_recovery: u32,
+
+    /// The recovery bits of the lengths (`length recovery`).
+    pub length_recovery: u16,
+
+    /// The protected packets, the bit `i` standing for the sequence number
+    /// `SN base + i`.
+    pub mask: u64,
+
+    /// The recovery bits of the packets' data (level 0 payload).
+    pub payload: Vec<u8>,
+}
+
+impl UlpfecPayload {
+    /// Parses the payload of an ULPFEC packet, returning `None` if it is
+    /// malformed.
+    pub fn from_raw(payload: &[u8]) -> Option<Self> {
+        if payload.len() < FEC_HEADER_SIZE + SHORT_LEVEL_HEADER_SIZE {
+            return None;
+        }
+
+        let header_size = if payload[0] & LONG_MASK > 0 {
+            LONG_LEVEL_HEADER_SIZE
+        } else {
+            SHORT_LEVEL_HEADER_SIZE
+        };
+        if payload.len() < FEC_HEADER_SIZE + header_size {
+            return None;
+        }
+
+        let level = &payload[FEC_HEADER_SIZE..FEC_HEADER_SIZE + header_size];
+        let protection_length = u16::from_be_bytes([level[0], level[1]]) as usize;
+
+        // The first bit of the mask stands for the SN base
+        let mut mask = 0u64;
+        for (index, byte) in level[2..].iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) > 0 {
+                    mask |= 1 << (index * 8 + bit);
+                }
+            }
+        }
+
+        let start = FEC_HEADER_SIZE + header_size;
+        let payload_data = payload.get(start..start + protection_length)?;
+
+        Some(Self {
+            recovery_flags: payload[0] & RECOVERY_MASK,
+            recovery_payload_type: payload[1],
+            sequence_number_base: u16::from_be_bytes([payload[2], payload[3]]),
+            timestamp_recovery: u32::from_be_bytes([
+                payload[4], payload[5], payload[6], payload[7],
+            ]),
+            length_recovery: u16::from_be_bytes([payload[8], payload[9]]),
+            mask,
+            payload: Vec::from(payload_data),
+        })
+    }
+
+    /// Serializes the content into the payload of an ULPFEC packet. The long
+    /// mask is used when a packet beyond the 16th one is protected.
+    pub fn to_raw(&self) -> Vec<u8> {
+        let long = self.mask >= 1 << SHORT_MASK_SIZE;
+        let (header_size, mask_size) = if long {
+            (LONG_LEVEL_HEADER_SIZE, LONG_MASK_SIZE)
+        } else {
+            (SHORT_LEVEL_HEADER_SIZE, SHORT_MASK_SIZE)
+        };
+
+        let mut payload = Vec::with_capacity(FEC_HEADER_SIZE + header_size + self.payload.len());
+        payload.push(self.recovery_flags & RECOVERY_MASK | if long { LONG_MASK } else { 0 });
+        payload.push(self.recovery_payload_type);
+        payload.extend_from_slice(&self.sequence_number_base.to_be_bytes());
+        payload.extend_from_slice(&self.timestamp_recovery.to_be_bytes());
+        payload.extend_from_slice(&self.length_recovery.to_be_bytes());
+
+        payload.extend_from_slice(&(self.payload.len() as u16).to_be_bytes());
+        for index in 0..mask_size / 8 {
+            let mut byte = 0;
+            for bit in 0..8 {
+                if self.mask & (1 << (index * 8 + bit)) > 0 {
+                    byte |= 0x80 >> bit;
+                }
+            }
+            payload.push(byte);
+        }
+
+        payload.extend_from_slice(&self.payload);
+
+        payload
+    }
+
+    /// Retrieves the sequence numbers of the protected packets.
+    pub fn protected_sequence_numbers(&self) -> Vec<u16> {
+        (0..LONG_MASK_SIZE)
+            .filter(|index| self.mask & (1 << index) > 0)
+            .map(|index| self.sequence_number_base.wrapping_add(index as u16))
+            .collect()
+    }
+
+    /// Builds the recovery bits of raw packets. The sequence numbers are
+    /// expected to be relative to the SN base, and lower than 48.
+    fn protect(sequence_number_base: u16, packets: &[(u16, &[u8])]) -> Self {
+        let raws: Vec<_> = packets.iter().map(|(_, raw)| *raw).collect();
+        let mut bits = protect(&raws);
+
+        let mask = packets.iter().fold(0, |mask, (sequence_number, _)| {
+            mask | 1 << sequence_number.wrapping_sub(sequence_number_base)
+        });
+
+        Self {
+            recovery_flags: bits[0] & RECOVERY_MASK,
+            recovery_payload_type: bits[1],
+            sequence_number_base,
+            timestamp_recovery: u32::from_be_bytes([bits[4], bits[5], bits[6], bits[7]]),
+            length_recovery: u16::from_be_bytes([bits[2], bits[3]]),
+            mask,
+            payload: bits.split_off(BIT_STRING_HEADER_SIZE),
+        }
+    }
+
+    /// Recovers the raw packet of sequence number `sequence_number` from the
+    /// raw packets of the other protected ones.
+    fn recover(&self, sequence_number: u16, ssrc: u32, packets: &[&[u8]]) -> Option<Packet> {
+        let mut bits = Vec::with_capacity(BIT_STRING_HEADER_SIZE + self.payload.len());
+        bits.push(self.recovery_flags);
+        bits.push(self.recovery_payload_type);
+        bits.extend_from_slice(&self.length_recovery.to_be_bytes());
+        bits.extend_from_slice(&self.timestamp_recovery.to_be_bytes());
+        bits.extend_from_slice(&self.payload);
+
+        recover(bits, packets, sequence_number, ssrc)
+    }
+}
+
+/// Applies a XOR between the bit strings of raw packets, the shortest ones
+/// being padded with zeros. The result is laid out this way: the first two
+/// bytes of the headers, the lengths, the timestamps and then the data.
+pub(crate) fn protect(packets: &[&[u8]]) -> Vec<u8> {
+    let size = packets
+        .iter()
+        .map(|raw| raw.len() - packet::HEADER_SIZE)
+        .max()
+        .unwrap_or(0);
+
+    let mut bits = vec![0; BIT_STRING_HEADER_SIZE + size];
+    for raw in packets {
+        xor(&mut bits, &bit_string(raw, size));
+    }
+
+    bits
+}
+
+/// Recovers a raw packet from the recovery bits, laid out as `protect` does,
+/// and from the raw packets of the other protected ones.
+pub(crate) fn recover(
+    mut bits: Vec<u8>,
+    packets: &[&[u8]],
+    sequence_number: u16,
+    ssrc: u32,
+) -> Option<Packet> {
+    if bits.len() < BIT_STRING_HEADER_SIZE {
+        return None;
+    }
+
+    let size = bits.len() - BIT_STRING_HEADER_SIZE;
+    for raw in packets {
+        xor(&mut bits, &bit_string(raw, size));
+    }
+
+    let length = u16::from_be_bytes([bits[2], bits[3]]) as usize;
+    if length > size {
+        return None;
+    }
+
+    let mut raw = Vec::with_capacity(packet::HEADER_SIZE + length);
+    raw.push((packet::RTP_VERSION << 6) | (bits[0] & RECOVERY_MASK));
+    raw.push(bits[1]);
+    raw.extend_from_slice(&sequence_number.to_be_bytes());
+    raw.extend_from_slice(&bits[4..8]);
+    raw.extend_from_slice(&ssrc.to_be_bytes());
+    raw.extend_from_slice(&bits[BIT_STRING_HEADER_SIZE..BIT_STRING_HEADER_SIZE + length]);
+
+    Packet::from_raw(&raw).ok()
+}
+
+/// This encoder is responsible to generate ULPFEC packets protecting groups
+/// of media packets, as stated in the [RFC 5109].
+///
+/// The FEC packets are sent into the media stream, so the sequencer of its
+/// packetizer must be provided to generate them.
+///
+/// [RFC 5109]: https://tools.ietf.org/html/rfc5109
+#[derive(Clone, Copy, Debug)]
+pub struct UlpfecEncoder {
+    /// The RTP payload type negotiated for ULPFEC.
+    pub payload_type: u8,
+
+    /// The pattern used to build the protection masks.
+    pub mask_pattern: FecMaskPattern,
+}
+
+impl UlpfecEncoder {
+    /// Instanciates a new encoder using the block protection masks.
+    pub fn new(payload_type: u8) -> Self {
+        Self {
+            payload_type,
+            mask_pattern: FecMaskPattern::default(),
+        }
+    }
+
+    /// Generates `fec_count` FEC packets protecting a group of at most 48
+    /// media packets, provided in their sequence order.
+    ///
+    /// `None` is returned if the group is spanning more than 48 sequence
+    /// numbers, or if a packet can not be marshalled.
+    pub fn encode(
+        &self,
+        sequencer: &mut Sequencer,
+        packets: &[Packet],
+        fec_count: usize,
+    ) -> Option<Vec<Packet>> {
+        let base = packets.first()?.sequence_number;
+        if packets.last()?.sequence_number.wrapping_sub(base) as usize >= LONG_MASK_SIZE {
+            return None;
+        }
+
+        let masks = self
+            .mask_pattern
+            .masks(packets.len(), fec_count)
+            .iter()
+            .map(|mask| {
+                packets
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| mask & (1 << index) > 0)
+                    .fold(0, |mask, (_, packet)| {
+                        mask | 1 << packet.sequence_number.wrapping_sub(base)
+                    })
+            })
+            .collect::<Vec<u64>>();
+
+        self.encode_with_masks(sequencer, packets, &masks)
+    }
+
+    /// Generates a FEC packet per mask protecting a group of media packets,
+    /// provided in their sequence order. The bit `i` of a mask stands for
+    /// the sequence number of the first packet plus `i`.
+    ///
+    /// `None` is returned if the group is spanning more than 48 sequence
+    /// numbers, or if a packet can not be marshalled.
+    pub fn encode_with_masks(
+        &self,
+        sequencer: &mut Sequencer,
+        packets: &[Packet],
+        masks: &[u64],
+    ) -> Option<Vec<Packet>> {
+        let first = packets.first()?;
+        let last = packets.last()?;
+        let base = first.sequence_number;
+
+        if last.sequence_number.wrapping_sub(base) as usize >= LONG_MASK_SIZE {
+            return None;
+        }
+
+        let raws = packets
+            .iter()
+            .map(|packet| Some((packet.sequence_number, packet.to_raw().ok()?)))
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut output = Vec::with_capacity(masks.len());
+        for mask in masks {
+            let protected = raws
+                .iter()
+                .filter(|(sequence_number, _)| mask & (1 << sequence_number.wrapping_sub(base)) > 0)
+                .map(|(sequence_number, raw)| (*sequence_number, &raw[..]))
+                .collect::<Vec<_>>();
+
+            let payload = UlpfecPayload::protect(base, &protected);
+
+            output.push(Packet {
+                version: packet::RTP_VERSION,
+                payload_type: self.payload_type,
+                sequence_number: sequencer.next_sequence_number(),
+                timestamp: last.timestamp,
+                ssrc: first.ssrc,
+                payload_offset: packet::HEADER_SIZE,
+                payload: payload.to_raw(),
+                ..Default::default()
+            });
+        }
+
+        Some(output)
+    }
+}
+
+/// This decoder is responsible to recover the lost media packets from the
+/// received ULPFEC packets and the received media packets.
+///
+/// A FEC packet is able to recover a media packet once all the other
+/// packets it protects have been received. The recovered packets are used
+/// in turn to recover the other ones. The media packets are identified by
+/// their SSRC and their sequence number, so several streams can be
+/// protected with the same payload type.
+#[derive(Clone, Debug)]
+pub struct UlpfecDecoder {
+    /// The RTP payload type negotiated for ULPFEC.
+    pub payload_type: u8,
+
+    media: VecDeque<(u32, u16, Vec<u8>)>,
+    fec: VecDeque<(u32, UlpfecPayload)>,
+}
+
+impl UlpfecDecoder {
+    /// Instanciates a new decoder.
+    pub fn new(payload_type: u8) -> Self {
+        Self {
+            payload_type,
+            media: VecDeque::with_capacity(MEDIA_HISTORY_SIZE),
+            fec: VecDeque::with_capacity(FEC_HISTORY_SIZE),
+        }
+    }
+
+    /// Handles a received packet, either a media or a FEC one, returning the
+    /// media packets it allows to recover.
+    pub fn decode(&mut self, packet: &Packet) -> Vec<Packet> {
+        if packet.payload_type == self.payload_type {
+            let payload = match UlpfecPayload::from_raw(&packet.payload) {
+                Some(payload) => payload,
+                None => return Vec::new(),
+            };
+
+            if self.fec.len() == FEC_HISTORY_SIZE {
+                self.fec.pop_front();
+            }
+            self.fec.push_back((packet.ssrc, payload));
+        } else if let Ok(raw) = packet.to_raw() {
+            self.insert(packet.ssrc, packet.sequence_number, raw);
+        }
+
+        self.recover()
+    }
+
+    /// Stores a media packet, if it has not been received yet.
+    fn insert(&mut self, ssrc: u32, sequence_number: u16, raw: Vec<u8>) {
+        if self.find(ssrc, sequence_number).is_some() {
+            return;
+        }
+
+        if self.media.len() == MEDIA_HISTORY_SIZE {
+            self.media.pop_front();
+        }
+        self.media.push_back((ssrc, sequence_number, raw));
+    }
+
+    fn find(&self, ssrc: u32, sequence_number: u16) -> Option<&[u8]> {
+        self.media
+            .iter()
+            .find(|(candidate_ssrc, candidate, _)| {
+                *candidate_ssrc == ssrc && *candidate == sequence_number
+            })
+            .map(|(_, _, raw)| &raw[..])
+    }
+
+    /// Recovers as many packets as possible with the stored FEC packets.
+    fn recover(&mut self) -> Vec<Packet> {
+        let mut recovered = Vec::new();
+
+        loop {
+            let mut progress = false;
+            let mut index = 0;
+
+            while index < self.fec.len() {
+                let (ssrc, payload) = &self.fec[index];
+                let ssrc = *ssrc;
+                let sequence_numbers = payload.protected_sequence_numbers();
+                let missing: Vec<_> = sequence_numbers
+                    .iter()
+                    .filter(|sequence_number| self.find(ssrc, **sequence_number).is_none())
+                    .collect();
+
+                match missing.len() {
+                    // The FEC packet is useless now
+                    0 => {
+                        self.fec.remove(index);
+                        continue;
+                    }
+                    1 => {
+                        let sequence_number = *missing[0];
+                        let packets: Vec<_> = sequence_numbers
+                            .iter()
+                            .filter_map(|sequence_number| self.find(ssrc, *sequence_number))
+                            .collect();
+
+                        let packet = payload.recover(sequence_number, ssrc, &packets);
+                        self.fec.remove(index);
+
+                        if let Some(packet) = packet {
+                            if let Some(raw) = &packet.raw {
+                                self.insert(ssrc, sequence_number, raw.clone());
+                            }
+
+                            recovered.push(packet);
+                            progress = true;
+                        }
+                        continue;
+                    }
+                    _ => index += 1,
+                }
+            }
+
+            if !progress {
+                break;
+            }
+        }
+
+        recovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media_packets(count: usize) -> Vec<Packet> {
+        (0..count)
+            .map(|index| {
+                let mut packet = Packet {
+                    version: packet::RTP_VERSION,
+                    marker: index % 3 == 2,
+                    payload_type: 96 + (index % 2) as u8,
+                    sequence_number: 0xfffe_u16.wrapping_add(index as u16),
+                    timestamp: 3000 * (index as u32 / 3),
+                    ssrc: 0x1234abcd,
+                    payload_offset: packet::HEADER_SIZE,
+                    payload: vec![index as u8; 10 + index * 7],
+                    ..Default::default()
+                };
+
+                if index == 1 {
+                    packet.csrc = vec![0xdeadbeef];
+                    packet.extension = true;
+                    packet.extension_profile = Some(0xbede);
+                    packet.extension_payload = Some(vec![0x10, 0xff, 0x00, 0x00]);
+                }
+
+                packet
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_builds_protection_masks() {
+        assert_eq!(vec![0b0011, 0b1100], FecMaskPattern::Block.masks(4, 2));
+        assert_eq!(
+            vec![0b0101, 0b1010],
+            FecMaskPattern::Interleaved.masks(4, 2)
+        );
+        assert_eq!(vec![0b111], FecMaskPattern::Block.masks(3, 1));
+        assert_eq!(3, FecMaskPattern::Interleaved.masks(3, 5).len());
+        assert_eq!(vec![0b0111, 0b1010], FecMaskPattern::Random.masks(4, 2));
+        assert_eq!(
+            FecMaskPattern::Interleaved.masks(20, 4),
+            FecMaskPattern::Bursty.masks(20, 4)
+        );
+    }
+
+    /// Checks if the lost packets can be recovered from the masks, one by
+    /// one, like the decoder does.
+    fn is_recoverable(masks: &[u64], mut lost: u64) -> bool {
+        while lost != 0 {
+            match masks
+                .iter()
+                .map(|mask| mask & lost)
+                .find(|protected| protected.count_ones() == 1)
+            {
+                Some(recovered) => lost &= !recovered,
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    #[test]
+    fn it_provides_mask_tables_for_random_and_bursty_losses() {
+        for media_count in 1..=MASK_TABLE_SIZE {
+            for fec_count in 1..=media_count {
+                for pattern in [FecMaskPattern::Random, FecMaskPattern::Bursty].iter() {
+                    let masks = pattern.masks(media_count, fec_count);
+                    assert_eq!(fec_count, masks.len());
+                    assert!(masks.iter().all(|mask| *mask >> media_count == 0));
+
+                    // Any lost packet can be recovered
+                    for index in 0..media_count {
+                        assert!(is_recoverable(&masks, 1 << index));
+                    }
+                }
+
+                // Any burst of as many losses as there are FEC packets can be
+                // recovered with the bursty masks
+                let masks = FecMaskPattern::Bursty.masks(media_count, fec_count);
+                for start in 0..=media_count - fec_count {
+                    assert!(is_recoverable(&masks, ((1 << fec_count) - 1) << start));
+                }
+            }
+        }
+
+        // Any two lost packets can be recovered with the random masks, unlike
+        // with the interleaved ones
+        let random = FecMaskPattern::Random.masks(12, 4);
+        let interleaved = FecMaskPattern::Interleaved.masks(12, 4);
+        for first in 0..12 {
+            for second in first + 1..12 {
+                assert!(is_recoverable(&random, 1 << first | 1 << second));
+            }
+        }
+        assert!(!is_recoverable(&interleaved, 1 << 0 | 1 << 4));
+    }
+
+    #[test]
+    fn it_writes_fec_payloads() {
+        let packets = media_packets(2);
+        let mut sequencer = Sequencer::new();
+        let encoder = UlpfecEncoder::new(127);
+
+        let fec = encoder.encode(&mut sequencer, &packets, 1).unwrap();
+        assert_eq!(1, fec.len());
+        assert_eq!(127, fec[0].payload_type);
+        assert_eq!(packets[0].ssrc, fec[0].ssrc);
+
+        let raw = &fec[0].payload;
+        // P, X and CC recovery
+        assert_eq!(0x11, raw[0]);
+        // M and PT recovery
+        assert_eq!(96 ^ 97, raw[1]);
+        assert_eq!([0xff, 0xfe], raw[2..4]);
+        // Length recovery
+        assert_eq!(
+            (10 ^ (4 + 8 + 17)) as u16,
+            u16::from_be_bytes([raw[8], raw[9]])
+        );
+        // Protection length and short mask
+        assert_eq!([0x00, 29, 0xc0, 0x00], raw[10..14]);
+
+        let payload = UlpfecPayload::from_raw(raw).unwrap();
+        assert_eq!(vec![0xfffe, 0xffff], payload.protected_sequence_numbers());
+        assert_eq!(raw.to_vec(), payload.to_raw());
+    }
+
+    #[test]
+    fn it_returns_none_when_the_group_spans_too_many_sequence_numbers() {
+        let mut packets = media_packets(2);
+        let mut sequencer = Sequencer::new();
+        let encoder = UlpfecEncoder::new(127);
+
+        packets[0].sequence_number = 0;
+        packets[1].sequence_number = 50;
+        assert!(encoder.encode(&mut sequencer, &packets, 1).is_none());
+
+        packets[1].sequence_number = 100;
+        assert!(encoder.encode(&mut sequencer, &packets, 1).is_none());
+
+        packets[1].sequence_number = 47;
+        assert!(encoder.encode(&mut sequencer, &packets, 1).is_some());
+    }
+
+    #[test]
+    fn it_recovers_lost_packets() {
+        let packets = media_packets(6);
+        let mut sequencer = Sequencer::new();
+        let encoder = UlpfecEncoder::new(127);
+        let mut decoder = UlpfecDecoder::new(127);
+
+        let fec = encoder.encode(&mut sequencer, &packets, 2).unwrap();
+
+        // A packet of each group is lost
+        for index in [0, 2, 4, 5].iter() {
+            assert!(decoder.decode(&packets[*index]).is_empty());
+        }
+
+        let mut recovered = decoder.decode(&fec[0]);
+        recovered.extend(decoder.decode(&fec[1]));
+        assert_eq!(2, recovered.len());
+
+        for (recovered, index) in recovered.iter().zip([1, 3].iter()) {
+            assert_eq!(
+                packets[*index].to_raw().unwrap(),
+                recovered.to_raw().unwrap()
+            );
+            assert_eq!(packets[*index].marker, recovered.marker);
+            assert_eq!(packets[*index].payload_type, recovered.payload_type);
+            assert_eq!(packets[*index].timestamp, recovered.timestamp);
+            assert_eq!(packets[*index].csrc, recovered.csrc);
+        }
+
+        // A FEC packet received twice does not recover anything
+        assert!(decoder.decode(&fec[0]).is_empty());
+    }
+
+    #[test]
+    fn it_recovers_lost_packets_of_several_streams() {
+        let packets = media_packets(2);
+        let mut sequencer = Sequencer::new();
+        let encoder = UlpfecEncoder::new(127);
+        let mut decoder = UlpfecDecoder::new(127);
+
+        let fec = encoder.encode(&mut sequencer, &packets, 1).unwrap();
+
+        // A packet of another stream with the same sequence number is not
+        // used to recover the lost one
+        let mut other = packets[1].clone();
+        other.ssrc = 0x5678ef01;
+        other.raw = None;
+        assert!(decoder.decode(&other).is_empty());
+
+        assert!(decoder.decode(&packets[0]).is_empty());
+        let recovered = decoder.decode(&fec[0]);
+        assert_eq!(1, recovered.len());
+        assert_eq!(packets[1].to_raw().unwrap(), recovered[0].to_raw().unwrap());
+    }
+
+    #[test]
+    fn it_recovers_lost_packets_with_random_masks() {
+        let packets = media_packets(8);
+        let mut sequencer = Sequencer::new();
+        let mut encoder = UlpfecEncoder::new(127);
+        encoder.mask_pattern = FecMaskPattern::Random;
+        let mut decoder = UlpfecDecoder::new(127);
+
+        let fec = encoder.encode(&mut sequencer, &packets, 3).unwrap();
+
+        // Two lost packets are recovered with three FEC packets
+        let mut recovered = Vec::new();
+        for (index, packet) in packets.iter().enumerate() {
+            if index != 2 && index != 5 {
+                recovered.extend(decoder.decode(packet));
+            }
+        }
+        for fec in &fec {
+            recovered.extend(decoder.decode(fec));
+        }
+
+        let mut sequence_numbers: Vec<_> = recovered
+            .iter()
+            .map(|packet| packet.sequence_number.wrapping_sub(0xfffe))
+            .collect();
+        sequence_numbers.sort_unstable();
+        assert_eq!(vec![2, 5], sequence_numbers);
+    }
+
+    #[test]
+    fn it_recovers_bursts_of_lost_packets_with_long_masks() {
+        let packets = media_packets(20);
+        let mut sequencer = Sequencer::new();
+        let mut encoder = UlpfecEncoder::new(127);
+        encoder.mask_pattern = FecMaskPattern::Interleaved;
+        let mut decoder = UlpfecDecoder::new(127);
+
+        let fec = encoder.encode(&mut sequencer, &packets, 4).unwrap();
+        assert_eq!(LONG_MASK, fec[0].payload[0] & LONG_MASK);
+
+        // The FEC packets are received before the media ones
+        for fec in &fec {
+            assert!(decoder.decode(fec).is_empty());
+        }
+
+        let mut recovered = Vec::new();
+        for (index, packet) in packets.iter().enumerate() {
+            if !(8..12).contains(&index) {
+                recovered.extend(decoder.decode(packet));
+            }
+        }
+
+        let mut sequence_numbers: Vec<_> = recovered
+            .iter()
+            .map(|packet| packet.sequence_number.wrapping_sub(0xfffe))
+            .collect();
+        sequence_numbers.sort_unstable();
+        assert_eq!(vec![8, 9, 10, 11], sequence_numbers);
+
+        for packet in &recovered {
+            let index = packet.sequence_number.wrapping_sub(0xfffe) as usize;
+            assert_eq!(packets[index].to_raw().unwrap(), packet.to_raw().unwrap());
+        }
+    }
+}
@@ -1,43 +1,230 @@
-use crate::PayloadGenerator;
+use crate::{codecs::KeyframeDetector, packet::Packet, Depacketizer, PayloadGenerator};
+use rand::Rng;
 
-const VP8_HEADER_SIZE: usize = 1;
+const VP8_MAX_HEADER_SIZE: usize = 6;
+const MAX_PARTITION_INDEX: u8 = 0x07;
+
+/// The maximum number of partition sizes of a frame, the last of its 9
+/// partitions spanning until the end of the frame.
+pub const VP8_MAX_PARTITION_SIZES: usize = 8;
+
+const VP8_FRAME_TAG_SIZE: usize = 3;
+const VP8_KEYFRAME_HEADER_SIZE: usize = 10;
+const VP8_START_CODE: [u8; 3] = [0x9d, 0x01, 0x2a];
+
+/// Defines how the PictureID field of the VP8 payload descriptor is written.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PictureIdMode {
+    /// The PictureID field is not included in the payload descriptor.
+    #[default]
+    None,
+
+    /// The PictureID field is written on 7 bits.
+    Short,
+
+    /// The PictureID field is written on 15 bits.
+    Long,
+}
+
+impl PictureIdMode {
+    /// Retrieves the value from which the PictureID overflows.
+    fn modulo(self) -> u16 {
+        match self {
+            Self::None => 1,
+            Self::Short => 0x80,
+            Self::Long => 0x8000,
+        }
+    }
+}
+
+/// Describes the VP8 frame which is going to be packetized, in order to fill
+/// the optional fields of the payload descriptor.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VP8FrameMetadata {
+    /// Indicates if the frame can be discarded without affecting any other
+    /// frame (N bit).
+    pub non_reference: bool,
+
+    /// The running index of the temporal base layer frames (TL0PICIDX).
+    pub tl0_pic_idx: Option<u8>,
+
+    /// The temporal layer index of the frame (TID).
+    pub temporal_layer_id: Option<u8>,
+
+    /// Indicates if the frame only depends on the base layer frames (Y bit).
+    /// It's ignored when `temporal_layer_id` is not provided.
+    pub layer_sync: bool,
+
+    /// The running index of the frames carrying a new key frame update
+    /// (KEYIDX).
+    pub key_idx: Option<u8>,
+
+    /// The sizes of the frame's partitions, in bytes. The sizes following the
+    /// first zero are ignored, and the last partition is spanning until the
+    /// end of the frame. If only zeros are provided, the frame is considered
+    /// as one partition.
+    pub partition_sizes: [usize; VP8_MAX_PARTITION_SIZES],
+}
 
 /// This payload generator is responsible to generate RTP packet's payloads
 /// from VP8 data in order to send them into a RTP stream.
+///
+/// The payload descriptor follows the [RFC 7741]:
+///
+/// ```text
+///       0 1 2 3 4 5 6 7
+///      +-+-+-+-+-+-+-+-+
+///      |X|R|N|S|R| PID | (REQUIRED)
+///      +-+-+-+-+-+-+-+-+
+/// X:   |I|L|T|K| RSV   | (OPTIONAL)
+///      +-+-+-+-+-+-+-+-+
+/// I:   |M| PictureID   | (OPTIONAL)
+///      +-+-+-+-+-+-+-+-+
+///      |   PictureID   | (OPTIONAL)
+///      +-+-+-+-+-+-+-+-+
+/// L:   |   TL0PICIDX   | (OPTIONAL)
+///      +-+-+-+-+-+-+-+-+
+/// T/K: |TID|Y| KEYIDX  | (OPTIONAL)
+///      +-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The `metadata` field describes the next frame to packetize, and the
+/// PictureID is incremented after each frame.
+///
+/// [RFC 7741]: https://tools.ietf.org/html/rfc7741
 #[derive(Clone, Copy, Debug, Default)]
-pub struct VP8PayloadGenerator;
+pub struct VP8PayloadGenerator {
+    /// How the PictureID field is written, if it is.
+    pub picture_id_mode: PictureIdMode,
+
+    /// The PictureID of the next frame.
+    pub picture_id: u16,
+
+    /// The metadata of the next frame.
+    pub metadata: VP8FrameMetadata,
+}
+
+impl VP8PayloadGenerator {
+    /// Instanciates a generator writing the PictureID field with the provided
+    /// mode. The initial PictureID is randomly generated.
+    pub fn with_picture_id_mode(picture_id_mode: PictureIdMode) -> Self {
+        Self {
+            picture_id_mode,
+            picture_id: rand::thread_rng().gen_range(0, picture_id_mode.modulo()),
+            metadata: VP8FrameMetadata::default(),
+        }
+    }
+
+    /// Builds the payload descriptor of a packet.
+    fn descriptor(&self, start: bool, partition_index: u8) -> Vec<u8> {
+        let metadata = &self.metadata;
+        let mut descriptor = Vec::with_capacity(VP8_MAX_HEADER_SIZE);
+
+        let mut required = partition_index.min(MAX_PARTITION_INDEX);
+        if metadata.non_reference {
+            required |= 0x20;
+        }
+        if start {
+            required |= 0x10;
+        }
+        descriptor.push(required);
+
+        let mut extension = 0;
+        if self.picture_id_mode != PictureIdMode::None {
+            extension |= 0x80;
+        }
+        if metadata.tl0_pic_idx.is_some() {
+            extension |= 0x40;
+        }
+        if metadata.temporal_layer_id.is_some() {
+            extension |= 0x20;
+        }
+        if metadata.key_idx.is_some() {
+            extension |= 0x10;
+        }
+
+        if extension == 0 {
+            return descriptor;
+        }
+
+        descriptor[0] |= 0x80;
+        descriptor.push(extension);
+
+        match self.picture_id_mode {
+            PictureIdMode::None => {}
+            PictureIdMode::Short => descriptor.push(self.picture_id as u8 & 0x7f),
+            PictureIdMode::Long => {
+                descriptor.push(0x80 | (self.picture_id >> 8) as u8 & 0x7f);
+                descriptor.push(self.picture_id as u8);
+            }
+        }
+
+        if let Some(tl0_pic_idx) = metadata.tl0_pic_idx {
+            descriptor.push(tl0_pic_idx);
+        }
+
+        if metadata.temporal_layer_id.is_some() || metadata.key_idx.is_some() {
+            let mut byte = 0;
+            if let Some(temporal_layer_id) = metadata.temporal_layer_id {
+                byte |= (temporal_layer_id & 0x03) << 6;
+
+                if metadata.layer_sync {
+                    byte |= 0x20;
+                }
+            }
+            if let Some(key_idx) = metadata.key_idx {
+                byte |= key_idx & 0x1f;
+            }
+
+            descriptor.push(byte);
+        }
+
+        descriptor
+    }
+}
 
 impl PayloadGenerator for VP8PayloadGenerator {
     fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
-        if mtu <= VP8_HEADER_SIZE {
+        let header_size = self.descriptor(false, 0).len();
+        if mtu <= header_size {
             return None;
         }
 
-        let mut output = Vec::new();
+        // Computing the partitions boundaries, the last partition is spanning
+        // until the end of the frame.
+        let mut partitions = Vec::new();
+        let mut partition_start = 0;
+        for size in &self.metadata.partition_sizes {
+            if *size == 0 || partition_start + size >= payload.len() {
+                break;
+            }
 
-        let max_fragment_size = mtu - VP8_HEADER_SIZE;
-        let mut data_remaining = payload.len();
-        let mut data_index = 0;
+            partitions.push(&payload[partition_start..partition_start + size]);
+            partition_start += size;
+        }
+        partitions.push(&payload[partition_start..]);
 
-        while data_remaining > 0 {
-            let payload_size = max_fragment_size.min(data_remaining);
-            let mut generated = Vec::with_capacity(VP8_HEADER_SIZE + payload_size);
+        let mut output = Vec::new();
+        let max_fragment_size = mtu - header_size;
 
-            // Initializing the generated payload
-            for _ in 0..VP8_HEADER_SIZE + payload_size {
-                generated.push(0x00u8);
-            }
+        for (partition_index, partition) in partitions.iter().enumerate() {
+            let mut data_index = 0;
+
+            while data_index < partition.len() {
+                let payload_size = max_fragment_size.min(partition.len() - data_index);
 
-            generated[VP8_HEADER_SIZE..]
-                .copy_from_slice(&payload[data_index..data_index + payload_size]);
-            output.push(generated);
+                let mut generated = self.descriptor(data_index == 0, partition_index as u8);
+                generated.extend_from_slice(&partition[data_index..data_index + payload_size]);
+                output.push(generated);
 
-            data_remaining -= payload_size;
-            data_index += payload_size;
+                data_index += payload_size;
+            }
         }
 
-        if output.len() > 0 {
-            output[0][0] = 0x10;
+        if !output.is_empty() {
+            // Incrementing picture ID and checking if it has overflowed
+            let modulo = self.picture_id_mode.modulo();
+            self.picture_id = (self.picture_id % modulo + 1) % modulo;
 
             Some(output)
         } else {
@@ -46,10 +233,304 @@ impl PayloadGenerator for VP8PayloadGenerator {
     }
 }
 
+/// Represents a parsed VP8 payload descriptor, as defined in the [RFC 7741].
+///
+/// [RFC 7741]: https://tools.ietf.org/html/rfc7741#section-4.2
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VP8PayloadDescriptor {
+    /// Indicates if the frame can be discarded without affecting any other
+    /// frame (N bit).
+    pub non_reference: bool,
+
+    /// Indicates if the payload starts a VP8 partition (S bit).
+    pub start_of_partition: bool,
+
+    /// The index of the partition the payload belongs to (PID).
+    pub partition_index: u8,
+
+    /// The PictureID of the frame, if provided.
+    pub picture_id: Option<u16>,
+
+    /// The running index of the temporal base layer frames (TL0PICIDX), if
+    /// provided.
+    pub tl0_pic_idx: Option<u8>,
+
+    /// The temporal layer index of the frame (TID), if provided.
+    pub temporal_layer_id: Option<u8>,
+
+    /// Indicates if the frame only depends on the base layer frames (Y bit).
+    pub layer_sync: bool,
+
+    /// The running index of the frames carrying a new key frame update
+    /// (KEYIDX), if provided.
+    pub key_idx: Option<u8>,
+
+    /// The size of the descriptor in bytes.
+    pub size: usize,
+}
+
+impl VP8PayloadDescriptor {
+    /// Parses the payload descriptor at the beginning of a RTP packet's payload.
+    ///
+    /// If the descriptor is truncated, `None` is returned.
+    pub fn from_raw(payload: &[u8]) -> Option<Self> {
+        let required = *payload.first()?;
+
+        let mut descriptor = Self {
+            non_reference: required & 0x20 > 0,
+            start_of_partition: required & 0x10 > 0,
+            partition_index: required & MAX_PARTITION_INDEX,
+            size: 1,
+            ..Default::default()
+        };
+
+        if required & 0x80 == 0 {
+            return Some(descriptor);
+        }
+
+        let extension = *payload.get(1)?;
+        let mut offset = 2;
+
+        if extension & 0x80 > 0 {
+            let byte = *payload.get(offset)?;
+            offset += 1;
+
+            // The M bit indicates a 15 bits PictureID
+            if byte & 0x80 > 0 {
+                let low = *payload.get(offset)?;
+                offset += 1;
+
+                descriptor.picture_id = Some(((byte & 0x7f) as u16) << 8 | low as u16);
+            } else {
+                descriptor.picture_id = Some(byte as u16);
+            }
+        }
+
+        if extension & 0x40 > 0 {
+            descriptor.tl0_pic_idx = Some(*payload.get(offset)?);
+            offset += 1;
+        }
+
+        if extension & 0x30 > 0 {
+            let byte = *payload.get(offset)?;
+            offset += 1;
+
+            if extension & 0x20 > 0 {
+                descriptor.temporal_layer_id = Some(byte >> 6);
+                descriptor.layer_sync = byte & 0x20 > 0;
+            }
+            if extension & 0x10 > 0 {
+                descriptor.key_idx = Some(byte & 0x1f);
+            }
+        }
+
+        descriptor.size = offset;
+
+        Some(descriptor)
+    }
+}
+
+/// Represents the uncompressed header at the beginning of a VP8 frame, as
+/// defined in the section 9.1 of the [RFC 6386].
+///
+/// [RFC 6386]: https://tools.ietf.org/html/rfc6386#section-9.1
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VP8FrameHeader {
+    /// Indicates if the frame is a key frame.
+    pub keyframe: bool,
+
+    /// The version of the reconstruction filter and loop filter.
+    pub version: u8,
+
+    /// Indicates if the frame is meant to be displayed.
+    pub show_frame: bool,
+
+    /// The size of the first partition in bytes.
+    pub first_partition_size: u32,
+
+    /// The width of the frame in pixels. Only provided by key frames.
+    pub width: Option<u16>,
+
+    /// The height of the frame in pixels. Only provided by key frames.
+    pub height: Option<u16>,
+}
+
+impl VP8FrameHeader {
+    /// Parses the uncompressed header of a VP8 frame.
+    ///
+    /// If the header is truncated, or if a key frame does not have a valid
+    /// start code, `None` is returned.
+    pub fn from_raw(frame: &[u8]) -> Option<Self> {
+        if frame.len() < VP8_FRAME_TAG_SIZE {
+            return None;
+        }
+
+        let tag = frame[0] as u32 | (frame[1] as u32) << 8 | (frame[2] as u32) << 16;
+        let mut header = Self {
+            keyframe: tag & 0x01 == 0,
+            version: ((tag >> 1) & 0x07) as u8,
+            show_frame: (tag >> 4) & 0x01 > 0,
+            first_partition_size: tag >> 5,
+            width: None,
+            height: None,
+        };
+
+        if header.keyframe {
+            if frame.len() < VP8_KEYFRAME_HEADER_SIZE
+                || frame[VP8_FRAME_TAG_SIZE..VP8_FRAME_TAG_SIZE + 3] != VP8_START_CODE
+            {
+                return None;
+            }
+
+            // The two upper bits of the dimensions are the scaling factors
+            let offset = VP8_FRAME_TAG_SIZE + VP8_START_CODE.len();
+            header.width = Some(u16::from_le_bytes([frame[offset], frame[offset + 1]]) & 0x3fff);
+            header.height =
+                Some(u16::from_le_bytes([frame[offset + 2], frame[offset + 3]]) & 0x3fff);
+        }
+
+        Some(header)
+    }
+}
+
+/// This depacketizer is responsible to rebuild VP8 frames from RTP packet's
+/// payloads produced according to the [RFC 7741].
+///
+/// A frame starts with a packet having its S bit set with the partition
+/// index `0`, and ends with a packet having the marker bit. If a packet of
+/// the frame is missing, the frame is dropped.
+///
+/// [RFC 7741]: https://tools.ietf.org/html/rfc7741
+#[derive(Clone, Debug, Default)]
+pub struct VP8Depacketizer {
+    frame: Vec<u8>,
+    complete: bool,
+    descriptor: Option<VP8PayloadDescriptor>,
+    frame_header: Option<VP8FrameHeader>,
+    last_sequence_number: Option<u16>,
+}
+
+impl VP8Depacketizer {
+    /// Retrieves the payload descriptor of the last received packet.
+    pub fn descriptor(&self) -> Option<&VP8PayloadDescriptor> {
+        self.descriptor.as_ref()
+    }
+
+    /// Retrieves the uncompressed header of the last emitted frame.
+    pub fn frame_header(&self) -> Option<&VP8FrameHeader> {
+        self.frame_header.as_ref()
+    }
+
+    /// Indicates if the last emitted frame is a key frame.
+    pub fn is_keyframe(&self) -> bool {
+        self.frame_header
+            .map(|frame_header| frame_header.keyframe)
+            .unwrap_or(false)
+    }
+}
+
+impl Depacketizer for VP8Depacketizer {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        // The current frame can not be completed if a packet is missing
+        if let Some(last_sequence_number) = self.last_sequence_number {
+            if packet.sequence_number != last_sequence_number.wrapping_add(1) {
+                self.complete = false;
+            }
+        }
+        self.last_sequence_number = Some(packet.sequence_number);
+
+        self.descriptor = VP8PayloadDescriptor::from_raw(&packet.payload);
+        let descriptor = match &self.descriptor {
+            Some(descriptor) => descriptor,
+            None => {
+                self.complete = false;
+                return None;
+            }
+        };
+
+        // The first packet of the frame resets the frame under construction
+        if descriptor.start_of_partition && descriptor.partition_index == 0 {
+            self.frame.clear();
+            self.complete = true;
+        }
+
+        if !self.complete {
+            return None;
+        }
+
+        self.frame
+            .extend_from_slice(&packet.payload[descriptor.size..]);
+
+        if !packet.marker {
+            return None;
+        }
+
+        self.complete = false;
+
+        let frame = std::mem::take(&mut self.frame);
+        let frame_header = VP8FrameHeader::from_raw(&frame)?;
+        self.frame_header = Some(frame_header);
+
+        Some(frame)
+    }
+
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        VP8PayloadDescriptor::from_raw(payload).is_some_and(|descriptor| {
+            descriptor.start_of_partition && descriptor.partition_index == 0
+        })
+    }
+
+    fn reset(&mut self) {
+        self.frame.clear();
+        self.complete = false;
+    }
+}
+
+/// This keyframe detector is responsible to detect the VP8 keyframes, as
+/// packetized according to the [RFC 7741].
+///
+/// A keyframe starts with a packet having its S bit set with the partition
+/// index `0`, whose frame tag has its P bit cleared.
+///
+/// [RFC 7741]: https://tools.ietf.org/html/rfc7741#section-4.3
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VP8KeyframeDetector;
+
+impl KeyframeDetector for VP8KeyframeDetector {
+    fn is_keyframe(&self, payload: &[u8]) -> bool {
+        let descriptor = match VP8PayloadDescriptor::from_raw(payload) {
+            Some(descriptor) => descriptor,
+            None => return false,
+        };
+
+        descriptor.start_of_partition
+            && descriptor.partition_index == 0
+            && payload
+                .get(descriptor.size)
+                .is_some_and(|frame_tag| frame_tag & 0x01 == 0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const VP8_HEADER_SIZE: usize = 1;
+
+    /// A VP8 key frame of 640x480 pixels, with a truncated first partition.
+    const KEYFRAME: [u8; 12] = [
+        0x50, 0x02, 0x00, 0x9d, 0x01, 0x2a, 0x80, 0x02, 0xe0, 0x01, 0x00, 0x01,
+    ];
+
+    fn packet(sequence_number: u16, marker: bool, payload: &[u8]) -> Packet {
+        Packet {
+            marker,
+            sequence_number,
+            payload: Vec::from(payload),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn it_generates_rtp_payload() {
         let mut generator = VP8PayloadGenerator::default();
@@ -79,4 +560,186 @@ mod tests {
         let payloads = generator.generate(1, &payload);
         assert!(payloads.is_none());
     }
+
+    #[test]
+    fn it_generates_rtp_payloads_with_a_long_picture_id() {
+        let mut generator = VP8PayloadGenerator {
+            picture_id_mode: PictureIdMode::Long,
+            picture_id: 0x7fff,
+            metadata: VP8FrameMetadata::default(),
+        };
+
+        let payloads = generator.generate(6, &[0x01, 0x02, 0x03]);
+        assert_eq!(
+            Some(vec![
+                vec![0x90, 0x80, 0xff, 0xff, 0x01, 0x02],
+                vec![0x80, 0x80, 0xff, 0xff, 0x03],
+            ]),
+            payloads
+        );
+
+        let payloads = generator.generate(6, &[0x04]);
+        assert_eq!(Some(vec![vec![0x90, 0x80, 0x80, 0x00, 0x04]]), payloads);
+    }
+
+    #[test]
+    fn it_generates_rtp_payloads_with_a_short_picture_id() {
+        let mut generator = VP8PayloadGenerator {
+            picture_id_mode: PictureIdMode::Short,
+            picture_id: 0x7f,
+            metadata: VP8FrameMetadata::default(),
+        };
+
+        let payloads = generator.generate(10, &[0x01]);
+        assert_eq!(Some(vec![vec![0x90, 0x80, 0x7f, 0x01]]), payloads);
+        assert_eq!(0, generator.picture_id);
+
+        // An out of range picture ID does not overflow
+        generator.picture_id = 0xffff;
+        assert!(generator.generate(10, &[0x02]).is_some());
+        assert_eq!(0, generator.picture_id);
+    }
+
+    #[test]
+    fn it_generates_rtp_payloads_with_temporal_layer_fields() {
+        let mut generator = VP8PayloadGenerator {
+            picture_id_mode: PictureIdMode::Long,
+            picture_id: 0x1234,
+            metadata: VP8FrameMetadata {
+                non_reference: true,
+                tl0_pic_idx: Some(0x42),
+                temporal_layer_id: Some(2),
+                layer_sync: true,
+                key_idx: Some(5),
+                partition_sizes: [0; VP8_MAX_PARTITION_SIZES],
+            },
+        };
+
+        let payloads = generator.generate(100, &[0x01]);
+        assert_eq!(
+            Some(vec![vec![0xb0, 0xf0, 0x92, 0x34, 0x42, 0xa5, 0x01]]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_generates_rtp_payloads_for_each_partition() {
+        let mut generator = VP8PayloadGenerator::default();
+        generator.metadata.partition_sizes[..2].copy_from_slice(&[3, 1]);
+
+        let payloads = generator.generate(3, &[0x01, 0x02, 0x03, 0x04, 0x05]);
+        assert_eq!(
+            Some(vec![
+                vec![0x10, 0x01, 0x02],
+                vec![0x00, 0x03],
+                vec![0x11, 0x04],
+                vec![0x12, 0x05],
+            ]),
+            payloads
+        );
+    }
+
+    #[test]
+    fn it_parses_a_full_payload_descriptor() {
+        let payload = [0xb0, 0xf0, 0x92, 0x34, 0x42, 0xa5, 0x01];
+
+        let descriptor = VP8PayloadDescriptor::from_raw(&payload);
+        assert_eq!(
+            Some(VP8PayloadDescriptor {
+                non_reference: true,
+                start_of_partition: true,
+                partition_index: 0,
+                picture_id: Some(0x1234),
+                tl0_pic_idx: Some(0x42),
+                temporal_layer_id: Some(2),
+                layer_sync: true,
+                key_idx: Some(5),
+                size: 6,
+            }),
+            descriptor
+        );
+    }
+
+    #[test]
+    fn it_returns_none_when_the_payload_descriptor_is_truncated() {
+        assert!(VP8PayloadDescriptor::from_raw(&[0x90, 0x80, 0x92]).is_none());
+        assert!(VP8PayloadDescriptor::from_raw(&[]).is_none());
+    }
+
+    #[test]
+    fn it_parses_a_keyframe_header() {
+        let header = VP8FrameHeader::from_raw(&KEYFRAME);
+        assert_eq!(
+            Some(VP8FrameHeader {
+                keyframe: true,
+                version: 0,
+                show_frame: true,
+                first_partition_size: 18,
+                width: Some(640),
+                height: Some(480),
+            }),
+            header
+        );
+    }
+
+    #[test]
+    fn it_parses_an_interframe_header() {
+        let header = VP8FrameHeader::from_raw(&[0x31, 0x02, 0x00, 0x00]).unwrap();
+        assert!(!header.keyframe);
+        assert_eq!(None, header.width);
+    }
+
+    #[test]
+    fn it_depacketizes_what_has_been_generated() {
+        let mut generator = VP8PayloadGenerator::with_picture_id_mode(PictureIdMode::Long);
+        let mut depacketizer = VP8Depacketizer::default();
+        generator.metadata.partition_sizes[0] = 4;
+
+        let payloads = generator.generate(8, &KEYFRAME).unwrap();
+        let last = payloads.len() - 1;
+
+        let mut output = None;
+        for (index, payload) in payloads.iter().enumerate() {
+            output = depacketizer.depacketize(&packet(index as u16, index == last, payload));
+        }
+
+        assert_eq!(Some(Vec::from(&KEYFRAME[..])), output);
+        assert!(depacketizer.is_keyframe());
+        assert_eq!(Some(640), depacketizer.frame_header().unwrap().width);
+        assert_eq!(
+            Some(generator.picture_id.wrapping_sub(1) & 0x7fff),
+            depacketizer.descriptor().unwrap().picture_id
+        );
+    }
+
+    #[test]
+    fn it_drops_frames_with_missing_packets() {
+        let mut depacketizer = VP8Depacketizer::default();
+
+        assert!(depacketizer
+            .depacketize(&packet(1, false, &[0x10, 0x50, 0x02]))
+            .is_none());
+        assert!(depacketizer
+            .depacketize(&packet(3, true, &[0x00, 0x00, 0x9d]))
+            .is_none());
+
+        // The next frame can be rebuilt
+        let output = depacketizer.depacketize(&packet(4, true, &[0x10, 0x31, 0x02, 0x00]));
+        assert_eq!(Some(vec![0x31, 0x02, 0x00]), output);
+        assert!(!depacketizer.is_keyframe());
+    }
+
+    #[test]
+    fn it_detects_keyframes() {
+        let detector = VP8KeyframeDetector;
+
+        assert!(detector.is_keyframe(&[0x10, 0x50, 0x02, 0x00]));
+        assert!(detector.is_keyframe(&[0x90, 0x80, 0x81, 0x23, 0x50, 0x02, 0x00]));
+        // An interframe
+        assert!(!detector.is_keyframe(&[0x10, 0x51, 0x02, 0x00]));
+        // A packet which does not start the first partition
+        assert!(!detector.is_keyframe(&[0x00, 0x50, 0x02, 0x00]));
+        assert!(!detector.is_keyframe(&[0x11, 0x50, 0x02, 0x00]));
+        assert!(!detector.is_keyframe(&[0x10]));
+    }
 }
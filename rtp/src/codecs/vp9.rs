@@ -1,14 +1,279 @@
-use crate::PayloadGenerator;
+use crate::{
+    codecs::KeyframeDetector, packet::Packet, sequencer::extend_sequence_number, Depacketizer,
+    PayloadGenerator,
+};
 use rand::Rng;
+use std::collections::VecDeque;
 
-const VP9_HEADER_SIZE: usize = 3;
+/// The maximum number of references of a frame or a picture (P_DIFF).
+pub const VP9_MAX_REFERENCES: usize = 3;
+
+/// The maximum number of spatial layers of a scalability structure (N_S).
+pub const VP9_MAX_SPATIAL_LAYERS: usize = 8;
+
+/// The maximum number of pictures of a group of pictures (N_G).
+pub const VP9_MAX_PICTURE_GROUPS: usize = 255;
+
+const VP9_MAX_REFERENCE_INDEX: u8 = 0x7f;
+const VP9_FILTER_HISTORY_SIZE: usize = 512;
+
+/// Retrieves the references of a frame or a picture, which end at the first
+/// zero difference.
+fn references(reference_indices: &[u8]) -> &[u8] {
+    let count = reference_indices
+        .iter()
+        .position(|reference| *reference == 0)
+        .unwrap_or(reference_indices.len());
+
+    &reference_indices[..count]
+}
+
+/// Parses the references of a frame or a picture into a fixed size array.
+fn references_from_raw(raw: &[u8]) -> [u8; VP9_MAX_REFERENCES] {
+    let mut reference_indices = [0; VP9_MAX_REFERENCES];
+    for (index, reference) in raw.iter().take(VP9_MAX_REFERENCES).enumerate() {
+        reference_indices[index] = *reference;
+    }
+
+    reference_indices
+}
+
+/// Describes the layer of a VP9 frame (L byte of the payload descriptor).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VP9LayerIndices {
+    /// The temporal layer index of the frame (TID).
+    pub temporal_layer_id: u8,
+
+    /// Indicates if the frame is a switching up point (U bit).
+    pub switching_up_point: bool,
+
+    /// The spatial layer index of the frame (SID).
+    pub spatial_layer_id: u8,
+
+    /// Indicates if the frame depends on the frame of the spatial layer just
+    /// below in the same picture (D bit).
+    pub inter_layer_dependency: bool,
+}
+
+/// Describes a picture of a group of pictures (GOF) in the scalability
+/// structure.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VP9PictureGroup {
+    /// The temporal layer index of the picture (T).
+    pub temporal_layer_id: u8,
+
+    /// Indicates if the picture is a switching up point (U bit).
+    pub switching_up_point: bool,
+
+    /// The differences between the PictureID of the picture and the ones of
+    /// its references (P_DIFF). The references end at the first zero.
+    pub reference_indices: [u8; VP9_MAX_REFERENCES],
+}
+
+/// Describes the scalability structure (SS) of a VP9 stream, sent with the
+/// key frames.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VP9ScalabilityStructure {
+    /// The number of spatial layers of the stream, between 1 and 8.
+    pub spatial_layer_count: u8,
+
+    /// The resolutions `(width, height)` of the spatial layers, only the
+    /// first `spatial_layer_count` ones being used. If `None`, the
+    /// resolutions are not sent.
+    pub resolutions: Option<[(u16, u16); VP9_MAX_SPATIAL_LAYERS]>,
+
+    /// The number of pictures of the group of pictures. If zero, the group
+    /// of pictures is not sent.
+    pub picture_group_count: u8,
+
+    /// The description of the group of pictures, only the first
+    /// `picture_group_count` ones being used.
+    pub picture_groups: [VP9PictureGroup; VP9_MAX_PICTURE_GROUPS],
+}
+
+impl VP9ScalabilityStructure {
+    /// Retrieves the description of the pictures of the group of pictures.
+    pub fn pictures(&self) -> &[VP9PictureGroup] {
+        &self.picture_groups[..self.picture_group_count as usize]
+    }
+
+    /// Checks if the structure can be written in a payload descriptor.
+    fn is_valid(&self) -> bool {
+        (1..=VP9_MAX_SPATIAL_LAYERS).contains(&(self.spatial_layer_count as usize))
+    }
+
+    /// Writes the scalability structure at the end of a payload descriptor.
+    fn write(&self, descriptor: &mut Vec<u8>) {
+        let mut header = (self.spatial_layer_count - 1) << 5;
+        if self.resolutions.is_some() {
+            header |= 0x10;
+        }
+        if self.picture_group_count > 0 {
+            header |= 0x08;
+        }
+        descriptor.push(header);
+
+        if let Some(resolutions) = &self.resolutions {
+            for (width, height) in &resolutions[..self.spatial_layer_count as usize] {
+                descriptor.extend_from_slice(&width.to_be_bytes());
+                descriptor.extend_from_slice(&height.to_be_bytes());
+            }
+        }
+
+        if self.picture_group_count > 0 {
+            descriptor.push(self.picture_group_count);
+
+            for picture in self.pictures() {
+                let references = references(&picture.reference_indices);
+
+                let mut byte =
+                    (picture.temporal_layer_id & 0x07) << 5 | (references.len() as u8) << 2;
+                if picture.switching_up_point {
+                    byte |= 0x10;
+                }
+                descriptor.push(byte);
+                descriptor.extend_from_slice(references);
+            }
+        }
+    }
+
+    /// Parses a scalability structure. The parsed structure and its size in
+    /// bytes are returned in a tuple defined this way: `(structure, size)`.
+    ///
+    /// If the structure is truncated, `None` is returned.
+    fn from_raw(raw: &[u8]) -> Option<(Self, usize)> {
+        let header = *raw.first()?;
+        let mut offset = 1;
+
+        let mut structure = Self {
+            spatial_layer_count: (header >> 5) + 1,
+            ..Default::default()
+        };
+
+        if header & 0x10 > 0 {
+            let mut resolutions = [(0, 0); VP9_MAX_SPATIAL_LAYERS];
+
+            for resolution in &mut resolutions[..structure.spatial_layer_count as usize] {
+                let raw = raw.get(offset..offset + 4)?;
+                offset += 4;
+
+                *resolution = (
+                    u16::from_be_bytes([raw[0], raw[1]]),
+                    u16::from_be_bytes([raw[2], raw[3]]),
+                );
+            }
+
+            structure.resolutions = Some(resolutions);
+        }
+
+        if header & 0x08 > 0 {
+            structure.picture_group_count = *raw.get(offset)?;
+            offset += 1;
+
+            for picture in &mut structure.picture_groups[..structure.picture_group_count as usize] {
+                let byte = *raw.get(offset)?;
+                let references = ((byte >> 2) & 0x03) as usize;
+                offset += 1;
+
+                *picture = VP9PictureGroup {
+                    temporal_layer_id: byte >> 5,
+                    switching_up_point: byte & 0x10 > 0,
+                    reference_indices: references_from_raw(raw.get(offset..offset + references)?),
+                };
+                offset += references;
+            }
+        }
+
+        Some((structure, offset))
+    }
+}
+
+impl Default for VP9ScalabilityStructure {
+    fn default() -> Self {
+        Self {
+            spatial_layer_count: 0,
+            resolutions: None,
+            picture_group_count: 0,
+            picture_groups: [VP9PictureGroup::default(); VP9_MAX_PICTURE_GROUPS],
+        }
+    }
+}
+
+/// Describes the VP9 frame which is going to be packetized, in order to fill
+/// the optional fields of the payload descriptor.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VP9FrameMetadata {
+    /// Indicates if the frame is inter-picture predicted (P bit).
+    pub inter_picture_predicted: bool,
+
+    /// The layer indices of the frame (L byte), if any.
+    pub layer: Option<VP9LayerIndices>,
+
+    /// The running index of the temporal base layer frames (TL0PICIDX), only
+    /// sent in non-flexible mode with the layer indices.
+    pub tl0_pic_idx: u8,
+
+    /// The differences between the PictureID of the frame and the ones of
+    /// its references (P_DIFF), only sent in flexible mode with
+    /// inter-picture predicted frames. The references end at the first zero,
+    /// each one being at most 127.
+    pub reference_indices: [u8; VP9_MAX_REFERENCES],
+
+    /// Indicates if the frame is not used as reference by the upper spatial
+    /// layers (Z bit).
+    pub not_reference_for_upper_layers: bool,
+
+    /// The scalability structure to send with the frame, usually with the
+    /// key frames.
+    pub scalability_structure: Option<VP9ScalabilityStructure>,
+
+    /// Indicates if other spatial layer frames of the same picture will follow
+    /// the frame. In this case, the PictureID is not incremented.
+    pub more_layers: bool,
+}
 
 /// This payload generator is responsible to generate RTP packet's payloads
 /// from VP9 data in order to send them into a RTP stream.
+///
+/// The payload descriptor follows the [RTP Payload Format for VP9]:
+///
+/// ```text
+///         0 1 2 3 4 5 6 7
+///        +-+-+-+-+-+-+-+-+
+///        |I|P|L|F|B|E|V|Z| (REQUIRED)
+///        +-+-+-+-+-+-+-+-+
+///   I:   |M| PICTURE ID  | (REQUIRED)
+///        +-+-+-+-+-+-+-+-+
+///   M:   | EXTENDED PID  | (RECOMMENDED)
+///        +-+-+-+-+-+-+-+-+
+///   L:   | TID |U| SID |D| (CONDITIONALLY RECOMMENDED)
+///        +-+-+-+-+-+-+-+-+
+///        |   TL0PICIDX   | (CONDITIONALLY REQUIRED, non-flexible mode)
+///        +-+-+-+-+-+-+-+-+                             -\
+///   P,F: | P_DIFF      |N| (CONDITIONALLY REQUIRED)    - up to 3 times
+///        +-+-+-+-+-+-+-+-+                             -/
+///   V:   | SS            |
+///        | ..            |
+///        +-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The `metadata` field describes the next frame to packetize. The
+/// scalability structure is only sent in the first packet of the frame. If
+/// the metadata can't be written in the payload descriptor, for instance an
+/// inter-picture predicted frame without reference in flexible mode, no
+/// payload is generated.
+///
+/// [RTP Payload Format for VP9]: https://tools.ietf.org/html/draft-ietf-payload-vp9-09
 #[derive(Clone, Copy, Debug)]
 pub struct VP9PayloadGenerator {
     pub picture_id: u16,
     pub initialized: bool,
+
+    /// Indicates if the flexible mode (F bit) is used.
+    pub flexible_mode: bool,
+
+    /// The metadata of the next frame.
+    pub metadata: VP9FrameMetadata,
 }
 
 impl VP9PayloadGenerator {
@@ -17,6 +282,98 @@ impl VP9PayloadGenerator {
         self.picture_id = rand::thread_rng().gen();
         self.initialized = true;
     }
+
+    /// Checks if the metadata of the next frame can be written in a payload
+    /// descriptor.
+    fn is_metadata_valid(&self) -> bool {
+        let metadata = &self.metadata;
+
+        if self.flexible_mode && metadata.inter_picture_predicted {
+            let references = references(&metadata.reference_indices);
+            if references.is_empty()
+                || references
+                    .iter()
+                    .any(|reference| *reference > VP9_MAX_REFERENCE_INDEX)
+            {
+                return false;
+            }
+        }
+
+        match &metadata.scalability_structure {
+            Some(scalability_structure) => scalability_structure.is_valid(),
+            None => true,
+        }
+    }
+
+    /// Builds the payload descriptor of a packet.
+    fn descriptor(&self, start: bool, end: bool) -> Vec<u8> {
+        let metadata = &self.metadata;
+        let scalability_structure = metadata.scalability_structure.as_ref().filter(|_| start);
+
+        let mut descriptor = Vec::new();
+
+        let mut required = 0x80;
+        if metadata.inter_picture_predicted {
+            required |= 0x40;
+        }
+        if metadata.layer.is_some() {
+            required |= 0x20;
+        }
+        if self.flexible_mode {
+            required |= 0x10;
+        }
+        if start {
+            required |= 0x08;
+        }
+        if end {
+            required |= 0x04;
+        }
+        if scalability_structure.is_some() {
+            required |= 0x02;
+        }
+        if metadata.not_reference_for_upper_layers {
+            required |= 0x01;
+        }
+        descriptor.push(required);
+
+        descriptor.push((self.picture_id >> 8) as u8 | 0x80);
+        descriptor.push(self.picture_id as u8);
+
+        if let Some(layer) = &metadata.layer {
+            let mut byte =
+                (layer.temporal_layer_id & 0x07) << 5 | (layer.spatial_layer_id & 0x07) << 1;
+            if layer.switching_up_point {
+                byte |= 0x10;
+            }
+            if layer.inter_layer_dependency {
+                byte |= 0x01;
+            }
+            descriptor.push(byte);
+
+            if !self.flexible_mode {
+                descriptor.push(metadata.tl0_pic_idx);
+            }
+        }
+
+        if self.flexible_mode && metadata.inter_picture_predicted {
+            let references = references(&metadata.reference_indices);
+
+            // The N bit indicates that another P_DIFF follows
+            for (index, reference) in references.iter().enumerate() {
+                let mut byte = reference << 1;
+                if index < references.len() - 1 {
+                    byte |= 0x01;
+                }
+                descriptor.push(byte);
+            }
+        }
+
+        if let Some(scalability_structure) = scalability_structure {
+            scalability_structure.write(&mut descriptor);
+        }
+
+        descriptor
+    }
 }
 
 impl Default for VP9PayloadGenerator {
@@ -26,13 +383,23 @@ impl Default for VP9PayloadGenerator {
         Self {
             picture_id,
             initialized: true,
+            flexible_mode: true,
+            metadata: VP9FrameMetadata::default(),
         }
     }
 }
 
 impl PayloadGenerator for VP9PayloadGenerator {
     fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>> {
-        if payload.len() == 0 || mtu <= VP9_HEADER_SIZE {
+        if payload.is_empty() || !self.is_metadata_valid() {
+            return None;
+        }
+
+        // The first packet of the frame may carry the scalability structure
+        let first_header_size = self.descriptor(true, false).len();
+        let header_size = self.descriptor(false, false).len();
+
+        if mtu <= first_header_size || mtu <= header_size {
             return None;
         }
 
@@ -45,34 +412,21 @@ impl PayloadGenerator for VP9PayloadGenerator {
         let mut payloads = Vec::new();
 
         // Working variables
-        let max_size = mtu - VP9_HEADER_SIZE;
         let mut data_remaining = payload.len();
         let mut data_index = 0;
 
         while data_remaining > 0 {
+            let start = data_index == 0;
+            let max_size = if start {
+                mtu - first_header_size
+            } else {
+                mtu - header_size
+            };
             let current_size = max_size.min(data_remaining);
 
-            // Initiaziling the RTP payload
-            let mut generated = Vec::with_capacity(VP9_HEADER_SIZE + current_size);
-            for _ in 0..VP9_HEADER_SIZE + current_size {
-                generated.push(0x00);
-            }
-
             // Defining header for the current fragment according to https://www.ietf.org/id/draft-ietf-payload-vp9-09.txt
-            generated[0] = 0x90;
-
-            if data_index == 0 {
-                generated[0] |= 0x08;
-            }
-            if data_remaining == current_size {
-                generated[0] |= 0x04;
-            }
-
-            generated[1] = (self.picture_id >> 8) as u8 | 0x80;
-            generated[2] = self.picture_id as u8;
-
-            generated[VP9_HEADER_SIZE..]
-                .copy_from_slice(&payload[data_index..data_index + current_size]);
+            let mut generated = self.descriptor(start, data_remaining == current_size);
+            generated.extend_from_slice(&payload[data_index..data_index + current_size]);
 
             payloads.push(generated);
 
@@ -80,13 +434,16 @@ impl PayloadGenerator for VP9PayloadGenerator {
             data_index += current_size;
         }
 
-        // Incrementing picture ID and checking if it has overflowed
-        self.picture_id += 1;
-        if self.picture_id >= 0x8000 {
-            self.picture_id = 0;
+        // Incrementing picture ID and checking if it has overflowed, unless
+        // other layers of the same picture are following
+        if !self.metadata.more_layers {
+            self.picture_id += 1;
+            if self.picture_id >= 0x8000 {
+                self.picture_id = 0;
+            }
         }
 
-        if payloads.len() > 0 {
+        if !payloads.is_empty() {
             Some(payloads)
         } else {
             None
@@ -94,15 +451,408 @@ impl PayloadGenerator for VP9PayloadGenerator {
     }
 }
 
+/// Represents a parsed VP9 payload descriptor.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VP9PayloadDescriptor {
+    /// Indicates if the frame is inter-picture predicted (P bit).
+    pub inter_picture_predicted: bool,
+
+    /// Indicates if the flexible mode is used (F bit).
+    pub flexible_mode: bool,
+
+    /// Indicates if the payload starts a frame (B bit).
+    pub start_of_frame: bool,
+
+    /// Indicates if the payload ends a frame (E bit).
+    pub end_of_frame: bool,
+
+    /// Indicates if the frame is not used as reference by the upper spatial
+    /// layers (Z bit).
+    pub not_reference_for_upper_layers: bool,
+
+    /// The PictureID of the frame, if provided.
+    pub picture_id: Option<u16>,
+
+    /// The layer indices of the frame, if provided.
+    pub layer: Option<VP9LayerIndices>,
+
+    /// The running index of the temporal base layer frames (TL0PICIDX), if
+    /// provided.
+    pub tl0_pic_idx: Option<u8>,
+
+    /// The differences between the PictureID of the frame and the ones of
+    /// its references (P_DIFF). The references end at the first zero.
+    pub reference_indices: [u8; VP9_MAX_REFERENCES],
+
+    /// The scalability structure, if provided.
+    pub scalability_structure: Option<VP9ScalabilityStructure>,
+
+    /// The size of the descriptor in bytes.
+    pub size: usize,
+}
+
+impl VP9PayloadDescriptor {
+    /// Parses the payload descriptor at the beginning of a RTP packet's payload.
+    ///
+    /// If the descriptor is truncated, `None` is returned.
+    pub fn from_raw(payload: &[u8]) -> Option<Self> {
+        let required = *payload.first()?;
+        let mut offset = 1;
+
+        let mut descriptor = Self {
+            inter_picture_predicted: required & 0x40 > 0,
+            flexible_mode: required & 0x10 > 0,
+            start_of_frame: required & 0x08 > 0,
+            end_of_frame: required & 0x04 > 0,
+            not_reference_for_upper_layers: required & 0x01 > 0,
+            ..Default::default()
+        };
+
+        if required & 0x80 > 0 {
+            let byte = *payload.get(offset)?;
+            offset += 1;
+
+            // The M bit indicates a 15 bits PictureID
+            if byte & 0x80 > 0 {
+                let low = *payload.get(offset)?;
+                offset += 1;
+
+                descriptor.picture_id = Some(((byte & 0x7f) as u16) << 8 | low as u16);
+            } else {
+                descriptor.picture_id = Some(byte as u16);
+            }
+        }
+
+        if required & 0x20 > 0 {
+            let byte = *payload.get(offset)?;
+            offset += 1;
+
+            descriptor.layer = Some(VP9LayerIndices {
+                temporal_layer_id: byte >> 5,
+                switching_up_point: byte & 0x10 > 0,
+                spatial_layer_id: (byte >> 1) & 0x07,
+                inter_layer_dependency: byte & 0x01 > 0,
+            });
+
+            if !descriptor.flexible_mode {
+                descriptor.tl0_pic_idx = Some(*payload.get(offset)?);
+                offset += 1;
+            }
+        }
+
+        if descriptor.flexible_mode && descriptor.inter_picture_predicted {
+            for reference in &mut descriptor.reference_indices {
+                let byte = *payload.get(offset)?;
+                offset += 1;
+
+                *reference = byte >> 1;
+                if byte & 0x01 == 0 {
+                    break;
+                }
+            }
+        }
+
+        if required & 0x02 > 0 {
+            let (scalability_structure, size) =
+                VP9ScalabilityStructure::from_raw(&payload[offset..])?;
+
+            descriptor.scalability_structure = Some(scalability_structure);
+            offset += size;
+        }
+
+        descriptor.size = offset;
+
+        Some(descriptor)
+    }
+}
+
+/// This depacketizer is responsible to rebuild VP9 frames from RTP packet's
+/// payloads.
+///
+/// A frame starts with a packet having its B bit set and ends with a packet
+/// having its E bit set. With spatial scalability, each layer frame is
+/// emitted separately. If a packet of the frame is missing, the frame is
+/// dropped.
+#[derive(Clone, Debug, Default)]
+pub struct VP9Depacketizer {
+    frame: Vec<u8>,
+    frame_descriptor: Option<VP9PayloadDescriptor>,
+    descriptor: Option<VP9PayloadDescriptor>,
+    last_sequence_number: Option<u16>,
+}
+
+impl VP9Depacketizer {
+    /// Retrieves the payload descriptor of the first packet of the last
+    /// emitted frame.
+    pub fn descriptor(&self) -> Option<&VP9PayloadDescriptor> {
+        self.descriptor.as_ref()
+    }
+
+    /// Retrieves the spatial layer index of the last emitted frame.
+    pub fn spatial_layer_id(&self) -> Option<u8> {
+        self.descriptor()?.layer.map(|layer| layer.spatial_layer_id)
+    }
+
+    /// Retrieves the temporal layer index of the last emitted frame.
+    pub fn temporal_layer_id(&self) -> Option<u8> {
+        self.descriptor()?
+            .layer
+            .map(|layer| layer.temporal_layer_id)
+    }
+
+    /// Indicates if the last emitted frame is a key frame, which is a frame
+    /// of the lowest spatial layer without inter-picture prediction.
+    pub fn is_keyframe(&self) -> bool {
+        self.descriptor()
+            .map(|descriptor| {
+                !descriptor.inter_picture_predicted
+                    && descriptor
+                        .layer
+                        .map(|layer| layer.spatial_layer_id == 0)
+                        .unwrap_or(true)
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl Depacketizer for VP9Depacketizer {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        // The current frame can not be completed if a packet is missing
+        if let Some(last_sequence_number) = self.last_sequence_number {
+            if packet.sequence_number != last_sequence_number.wrapping_add(1) {
+                self.frame_descriptor = None;
+            }
+        }
+        self.last_sequence_number = Some(packet.sequence_number);
+
+        let descriptor = match VP9PayloadDescriptor::from_raw(&packet.payload) {
+            Some(descriptor) => descriptor,
+            None => {
+                self.frame_descriptor = None;
+                return None;
+            }
+        };
+
+        let end_of_frame = descriptor.end_of_frame;
+        let payload = &packet.payload[descriptor.size..];
+
+        // The first packet of the frame resets the frame under construction
+        if descriptor.start_of_frame {
+            self.frame.clear();
+            self.frame_descriptor = Some(descriptor);
+        } else if self.frame_descriptor.is_none() {
+            return None;
+        }
+
+        self.frame.extend_from_slice(payload);
+
+        if !end_of_frame {
+            return None;
+        }
+
+        self.descriptor = self.frame_descriptor.take();
+
+        Some(std::mem::take(&mut self.frame))
+    }
+
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        VP9PayloadDescriptor::from_raw(payload).is_some_and(|descriptor| descriptor.start_of_frame)
+    }
+
+    fn reset(&mut self) {
+        self.frame.clear();
+        self.frame_descriptor = None;
+    }
+}
+
+/// This filter is responsible to thin a VP9 stream with spatial and temporal
+/// scalability before forwarding it, by dropping the packets of the layers
+/// above the selected ones.
+///
+/// The marker bit is set on the last packet of the highest forwarded spatial
+/// layer, and the sequence numbers of the forwarded packets are rewritten
+/// to avoid gaps. The recent packets are remembered, so a retransmitted or
+/// reordered packet is rewritten according to the packets dropped before
+/// it, and not the ones dropped since.
+#[derive(Clone, Debug)]
+pub struct VP9LayerFilter {
+    /// The highest spatial layer to forward.
+    pub max_spatial_layer: u8,
+
+    /// The highest temporal layer to forward.
+    pub max_temporal_layer: u8,
+
+    highest_sequence_number: Option<u64>,
+    history: VecDeque<FilteredPacket>,
+}
+
+/// Describes a packet which has been seen by the layer filter.
+#[derive(Clone, Copy, Debug)]
+struct FilteredPacket {
+    /// The extended sequence number of the packet.
+    sequence_number: u64,
+
+    /// The number of packets dropped before this one when it was first seen.
+    dropped: u16,
+
+    /// Indicates if the packet has been forwarded.
+    forwarded: bool,
+}
+
+impl VP9LayerFilter {
+    /// Instanciates a new filter forwarding the layers up to the provided ones.
+    pub fn new(max_spatial_layer: u8, max_temporal_layer: u8) -> Self {
+        Self {
+            max_spatial_layer,
+            max_temporal_layer,
+            highest_sequence_number: None,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Filters a packet of the stream. If the packet belongs to a layer which
+    /// is not forwarded, `None` is returned. Otherwise the packet to forward
+    /// is returned.
+    ///
+    /// The packets without layer indices are always forwarded, unless they
+    /// are older than the beginning of the stream.
+    pub fn filter(&mut self, packet: &Packet) -> Option<Packet> {
+        let descriptor = VP9PayloadDescriptor::from_raw(&packet.payload);
+        let layer = descriptor.as_ref().and_then(|descriptor| descriptor.layer);
+
+        let sequence_number = match self.highest_sequence_number {
+            Some(highest) => extend_sequence_number(highest, packet.sequence_number)?,
+            None => u64::from(packet.sequence_number),
+        };
+
+        let dropped = match self
+            .history
+            .binary_search_by_key(&sequence_number, |seen| seen.sequence_number)
+        {
+            // A retransmitted packet is handled as when it was first seen
+            Ok(index) => {
+                let seen = self.history[index];
+                if !seen.forwarded {
+                    return None;
+                }
+
+                seen.dropped
+            }
+            Err(index) => {
+                let forwarded = match layer {
+                    Some(layer) => {
+                        layer.spatial_layer_id <= self.max_spatial_layer
+                            && layer.temporal_layer_id <= self.max_temporal_layer
+                    }
+                    None => true,
+                };
+                let newest = index == self.history.len();
+
+                // The drop count is the one in effect just after the previous
+                // packet. When a reordered packet is dropped, the following
+                // packets have already been forwarded, so it isn't counted by
+                // them and leaves a gap.
+                let dropped = match index.checked_sub(1).map(|index| self.history[index]) {
+                    Some(previous) => previous.dropped.wrapping_add(!previous.forwarded as u16),
+                    None => self.history.front().map_or(0, |next| next.dropped),
+                };
+
+                self.history.insert(
+                    index,
+                    FilteredPacket {
+                        sequence_number,
+                        dropped,
+                        forwarded,
+                    },
+                );
+                if self.history.len() > VP9_FILTER_HISTORY_SIZE {
+                    self.history.pop_front();
+                }
+
+                if newest {
+                    self.highest_sequence_number = Some(sequence_number);
+                }
+
+                if !forwarded {
+                    return None;
+                }
+
+                dropped
+            }
+        };
+
+        let mut forwarded = packet.clone();
+        forwarded.sequence_number = packet.sequence_number.wrapping_sub(dropped);
+
+        if let (Some(descriptor), Some(layer)) = (&descriptor, layer) {
+            if descriptor.end_of_frame && layer.spatial_layer_id == self.max_spatial_layer {
+                forwarded.marker = true;
+            }
+        }
+
+        // The raw representation is not matching the packet anymore
+        forwarded.raw = None;
+
+        Some(forwarded)
+    }
+}
+
+/// This keyframe detector is responsible to detect the VP9 keyframes from
+/// their payload descriptor.
+///
+/// A keyframe starts with a packet having its B bit set and its P bit
+/// cleared, in the lowest spatial layer.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VP9KeyframeDetector;
+
+impl KeyframeDetector for VP9KeyframeDetector {
+    fn is_keyframe(&self, payload: &[u8]) -> bool {
+        VP9PayloadDescriptor::from_raw(payload).is_some_and(|descriptor| {
+            descriptor.start_of_frame
+                && !descriptor.inter_picture_predicted
+                && match descriptor.layer {
+                    Some(layer) => layer.spatial_layer_id == 0,
+                    None => true,
+                }
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn packet(sequence_number: u16, marker: bool, payload: &[u8]) -> Packet {
+        Packet {
+            marker,
+            sequence_number,
+            payload: Vec::from(payload),
+            ..Default::default()
+        }
+    }
+
+    fn scalability_structure(
+        resolutions: &[(u16, u16)],
+        pictures: &[VP9PictureGroup],
+    ) -> VP9ScalabilityStructure {
+        let mut structure = VP9ScalabilityStructure {
+            spatial_layer_count: resolutions.len() as u8,
+            resolutions: Some([(0, 0); VP9_MAX_SPATIAL_LAYERS]),
+            picture_group_count: pictures.len() as u8,
+            ..Default::default()
+        };
+        structure.resolutions.as_mut().unwrap()[..resolutions.len()].copy_from_slice(resolutions);
+        structure.picture_groups[..pictures.len()].copy_from_slice(pictures);
+
+        structure
+    }
+
     #[test]
     fn it_produces_rtp_payload_for_one_packet() {
         let mut generator = VP9PayloadGenerator {
             picture_id: 0,
             initialized: true,
+            ..Default::default()
         };
         let expected = vec![vec![0x9c, 0x80, 0x00, 0x01, 0x02]];
 
@@ -119,6 +869,7 @@ mod tests {
         let mut generator = VP9PayloadGenerator {
             picture_id: 0,
             initialized: true,
+            ..Default::default()
         };
         let expected = vec![vec![0x98, 0x80, 0x00, 0x01], vec![0x94, 0x80, 0x00, 0x02]];
 
@@ -135,6 +886,7 @@ mod tests {
         let mut generator = VP9PayloadGenerator {
             picture_id: 0,
             initialized: true,
+            ..Default::default()
         };
         let expected = vec![
             vec![0x98, 0x80, 0x00, 0x01],
@@ -155,6 +907,7 @@ mod tests {
         let mut generator = VP9PayloadGenerator {
             picture_id: 0,
             initialized: true,
+            ..Default::default()
         };
         let expected = vec![
             vec![0x98, 0x80, 0x00, 0x01, 0x02],
@@ -185,4 +938,258 @@ mod tests {
 
         assert!(generator.generate(1, &[0x01; 3]).is_none());
     }
+
+    #[test]
+    fn it_produces_rtp_payload_with_layer_indices_in_non_flexible_mode() {
+        let mut generator = VP9PayloadGenerator {
+            picture_id: 0x1234,
+            flexible_mode: false,
+            metadata: VP9FrameMetadata {
+                inter_picture_predicted: true,
+                layer: Some(VP9LayerIndices {
+                    temporal_layer_id: 2,
+                    switching_up_point: true,
+                    spatial_layer_id: 1,
+                    inter_layer_dependency: true,
+                }),
+                tl0_pic_idx: 0x42,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected = vec![vec![0xec, 0x92, 0x34, 0x53, 0x42, 0x01]];
+
+        let payloads = generator.generate(10, &[0x01]);
+        assert_eq!(Some(expected), payloads);
+    }
+
+    #[test]
+    fn it_produces_rtp_payload_with_reference_indices_in_flexible_mode() {
+        let mut generator = VP9PayloadGenerator {
+            picture_id: 0,
+            metadata: VP9FrameMetadata {
+                inter_picture_predicted: true,
+                reference_indices: [1, 2, 0],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected = vec![
+            vec![0xd8, 0x80, 0x00, 0x03, 0x04, 0x01],
+            vec![0xd4, 0x80, 0x00, 0x03, 0x04, 0x02],
+        ];
+
+        let payloads = generator.generate(6, &[0x01, 0x02]);
+        assert_eq!(Some(expected), payloads);
+    }
+
+    #[test]
+    fn it_returns_none_when_metadata_is_invalid() {
+        let mut generator = VP9PayloadGenerator {
+            metadata: VP9FrameMetadata {
+                inter_picture_predicted: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(generator.generate(100, &[0x01]).is_none());
+
+        generator.metadata.reference_indices = [128, 0, 0];
+        assert!(generator.generate(100, &[0x01]).is_none());
+
+        generator.metadata.reference_indices = [0, 1, 0];
+        assert!(generator.generate(100, &[0x01]).is_none());
+
+        generator.metadata.reference_indices = [127, 0, 0];
+        assert!(generator.generate(100, &[0x01]).is_some());
+
+        generator.metadata.scalability_structure = Some(VP9ScalabilityStructure::default());
+        assert!(generator.generate(100, &[0x01]).is_none());
+
+        generator.metadata.scalability_structure = Some(VP9ScalabilityStructure {
+            spatial_layer_count: 9,
+            ..Default::default()
+        });
+        assert!(generator.generate(100, &[0x01]).is_none());
+    }
+
+    #[test]
+    fn it_produces_a_scalability_structure_in_the_first_packet() {
+        let mut generator = VP9PayloadGenerator {
+            picture_id: 0,
+            metadata: VP9FrameMetadata {
+                layer: Some(VP9LayerIndices::default()),
+                scalability_structure: Some(scalability_structure(
+                    &[(320, 180), (640, 360)],
+                    &[VP9PictureGroup {
+                        temporal_layer_id: 0,
+                        switching_up_point: false,
+                        reference_indices: [1, 0, 0],
+                    }],
+                )),
+                more_layers: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected = vec![
+            vec![
+                0xba, 0x80, 0x00, 0x00, // descriptor, with L and V bits
+                0x38, 0x01, 0x40, 0x00, 0xb4, 0x02, 0x80, 0x01,
+                0x68, // N_S=1, Y, G, resolutions
+                0x01, 0x04, 0x01, // N_G=1, R=1, P_DIFF=1
+                0x01,
+            ],
+            vec![0xb4, 0x80, 0x00, 0x00, 0x02],
+        ];
+
+        let payloads = generator.generate(17, &[0x01, 0x02]);
+        assert_eq!(Some(expected), payloads);
+
+        // The PictureID is kept for the next layer of the picture
+        assert_eq!(0, generator.picture_id);
+    }
+
+    #[test]
+    fn it_parses_what_has_been_generated() {
+        let metadata = VP9FrameMetadata {
+            layer: Some(VP9LayerIndices {
+                temporal_layer_id: 1,
+                switching_up_point: false,
+                spatial_layer_id: 1,
+                inter_layer_dependency: true,
+            }),
+            inter_picture_predicted: true,
+            reference_indices: [1, 3, 0],
+            scalability_structure: Some(scalability_structure(
+                &[(320, 180), (640, 360)],
+                &[VP9PictureGroup {
+                    temporal_layer_id: 1,
+                    switching_up_point: true,
+                    reference_indices: [1, 2, 0],
+                }],
+            )),
+            ..Default::default()
+        };
+        let mut generator = VP9PayloadGenerator {
+            picture_id: 0x1234,
+            metadata,
+            ..Default::default()
+        };
+
+        let payloads = generator.generate(100, &[0x01]).unwrap();
+        let descriptor = VP9PayloadDescriptor::from_raw(&payloads[0]).unwrap();
+
+        assert_eq!(Some(0x1234), descriptor.picture_id);
+        assert!(descriptor.flexible_mode);
+        assert!(descriptor.start_of_frame && descriptor.end_of_frame);
+        assert_eq!(metadata.layer, descriptor.layer);
+        assert_eq!(metadata.reference_indices, descriptor.reference_indices);
+        assert_eq!(
+            metadata.scalability_structure,
+            descriptor.scalability_structure
+        );
+        assert_eq!(payloads[0].len() - 1, descriptor.size);
+    }
+
+    #[test]
+    fn it_depacketizes_frames() {
+        let mut generator = VP9PayloadGenerator {
+            picture_id: 0,
+            ..Default::default()
+        };
+        let mut depacketizer = VP9Depacketizer::default();
+        let frame = [0x82, 0x49, 0x83, 0x42, 0x00, 0x13, 0xf0];
+
+        let payloads = generator.generate(6, &frame).unwrap();
+        assert_eq!(3, payloads.len());
+
+        let mut output = None;
+        for (index, payload) in payloads.iter().enumerate() {
+            output = depacketizer.depacketize(&packet(index as u16, index == 2, payload));
+        }
+
+        assert_eq!(Some(Vec::from(&frame[..])), output);
+        assert!(depacketizer.is_keyframe());
+        assert_eq!(None, depacketizer.spatial_layer_id());
+    }
+
+    #[test]
+    fn it_drops_frames_with_missing_packets() {
+        let mut depacketizer = VP9Depacketizer::default();
+
+        assert!(depacketizer
+            .depacketize(&packet(1, false, &[0xd8, 0x80, 0x00, 0x02, 0x01]))
+            .is_none());
+        assert!(depacketizer
+            .depacketize(&packet(3, true, &[0xd4, 0x80, 0x00, 0x02, 0x02]))
+            .is_none());
+    }
+
+    #[test]
+    fn it_filters_spatial_and_temporal_layers() {
+        let mut filter = VP9LayerFilter::new(0, 0);
+
+        // L byte: TID in the 3 upper bits and SID in bits 1 to 3
+        let packets = [
+            packet(10, false, &[0xac, 0x80, 0x00, 0x00, 0x01]), // S0 T0
+            packet(11, true, &[0xac, 0x80, 0x00, 0x02, 0x02]),  // S1 T0
+            packet(12, false, &[0xec, 0x80, 0x01, 0x20, 0x03]), // S0 T1
+            packet(13, true, &[0xec, 0x80, 0x01, 0x22, 0x04]),  // S1 T1
+            packet(14, false, &[0xec, 0x80, 0x02, 0x00, 0x05]), // S0 T0
+        ];
+
+        let forwarded: Vec<Packet> = packets
+            .iter()
+            .filter_map(|packet| filter.filter(packet))
+            .collect();
+
+        assert_eq!(2, forwarded.len());
+        assert_eq!(10, forwarded[0].sequence_number);
+        assert!(forwarded[0].marker);
+        assert_eq!(11, forwarded[1].sequence_number);
+        assert_eq!(0x05, forwarded[1].payload[4]);
+    }
+
+    #[test]
+    fn it_filters_reordered_and_retransmitted_packets() {
+        let mut filter = VP9LayerFilter::new(0, 0);
+        let mut forward = |sequence_number: u16, spatial_layer_id: u8| {
+            let payload = [0xac, 0x80, 0x00, spatial_layer_id << 1, 0x01];
+
+            filter
+                .filter(&packet(sequence_number, false, &payload))
+                .map(|packet| packet.sequence_number)
+        };
+
+        assert_eq!(Some(10), forward(10, 0));
+        assert_eq!(None, forward(11, 1));
+        assert_eq!(Some(12), forward(13, 0));
+        assert_eq!(None, forward(14, 1));
+
+        // The reordered packet is rewritten with the packets dropped before it
+        assert_eq!(Some(11), forward(12, 0));
+        assert_eq!(Some(13), forward(15, 0));
+
+        // The retransmitted packets are rewritten as when first seen
+        assert_eq!(Some(10), forward(10, 0));
+        assert_eq!(Some(12), forward(13, 0));
+        assert_eq!(None, forward(11, 1));
+        assert_eq!(Some(14), forward(16, 0));
+    }
+
+    #[test]
+    fn it_detects_keyframes() {
+        let detector = VP9KeyframeDetector;
+
+        assert!(detector.is_keyframe(&[0x88, 0x01, 0xaa]));
+        assert!(detector.is_keyframe(&[0xa8, 0x01, 0x00, 0x00, 0xaa]));
+        // An interframe
+        assert!(!detector.is_keyframe(&[0xc8, 0x01, 0xaa]));
+        // A packet which does not start the frame
+        assert!(!detector.is_keyframe(&[0x84, 0x01, 0xaa]));
+        // A frame of an upper spatial layer
+        assert!(!detector.is_keyframe(&[0xa8, 0x01, 0x02, 0x00, 0xaa]));
+        assert!(!detector.is_keyframe(&[0x88]));
+    }
 }
@@ -0,0 +1,94 @@
+use crate::packet::Packet;
+
+/// This trait defines the mandatory methods that a depacketizer
+/// structure should implement in order to rebuild codec data from
+/// the payloads of received RTP packets.
+pub trait Depacketizer {
+    /// Try to extracts codec data from a RTP packet.
+    ///
+    /// The packets must be provided in their sequence order. If the
+    /// packet does not complete any codec data yet, or if it's not a
+    /// valid one, this method should return `None`.
+    ///
+    /// This method has a mutable reference to `self` in case the
+    /// depacketizer needs to keep fragments between packets.
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>>;
+
+    /// Try to computes the duration of a rebuilt codec data, in RTP clock
+    /// ticks, from the data itself.
+    ///
+    /// By default, the duration can not be computed and `None` is returned.
+    fn duration(&self, _data: &[u8]) -> Option<u32> {
+        None
+    }
+
+    /// Checks if a RTP packet's payload is starting a new codec data, and
+    /// can therefore be depacketized without the previous packets.
+    ///
+    /// By default, every payload is considered as a start.
+    fn is_partition_head(&self, _payload: &[u8]) -> bool {
+        true
+    }
+
+    /// Drops the fragments kept from the previous packets, if any, for
+    /// instance when they are not completing a codec data.
+    ///
+    /// By default, there is nothing to drop.
+    fn reset(&mut self) {}
+}
+
+impl<D: Depacketizer + ?Sized> Depacketizer for Box<D> {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        (**self).depacketize(packet)
+    }
+
+    fn duration(&self, data: &[u8]) -> Option<u32> {
+        (**self).duration(data)
+    }
+
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        (**self).is_partition_head(payload)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// This depacketizer is responsible to extract codec data carried as is by
+/// the RTP packet's payloads, like the audio samples of the static payload
+/// types of the [RFC 3551].
+///
+/// Each payload is emitted as a whole. When the codec has a constant number
+/// of bytes per RTP clock tick, the duration is computed from the data size.
+///
+/// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-4.5
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PassthroughDepacketizer {
+    /// The number of bytes per RTP clock tick, if it's constant.
+    pub bytes_per_tick: Option<u32>,
+}
+
+impl PassthroughDepacketizer {
+    /// Instanciates a new depacketizer for a codec having the given number
+    /// of bytes per RTP clock tick, if it's constant.
+    pub fn new(bytes_per_tick: Option<u32>) -> Self {
+        Self { bytes_per_tick }
+    }
+}
+
+impl Depacketizer for PassthroughDepacketizer {
+    fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+        if packet.payload.is_empty() {
+            return None;
+        }
+
+        Some(packet.payload.clone())
+    }
+
+    fn duration(&self, data: &[u8]) -> Option<u32> {
+        let bytes_per_tick = self.bytes_per_tick.filter(|&bytes| bytes > 0)?;
+
+        Some(data.len() as u32 / bytes_per_tick)
+    }
+}
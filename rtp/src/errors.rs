@@ -15,3 +15,48 @@ pub enum RtpPacketError {
     #[fail(display = "Provided marshalled RTP packet is not valid")]
     InvalidRtpPacket,
 }
+
+/// This enumeration is exposing the errors that can occurs while parsing
+/// H.264 bitstream structures such as parameter sets.
+#[derive(Debug, Fail)]
+pub enum H264Error {
+    /// Emitted when the parsed NAL unit is not of the expected type.
+    #[fail(display = "Unexpected NAL unit type: {}", unit_type)]
+    UnexpectedNalUnitType { unit_type: u8 },
+
+    /// Emitted when the bitstream ends before the structure has been fully parsed.
+    #[fail(display = "H.264 bitstream ended unexpectedly")]
+    TruncatedBitstream,
+
+    /// Emitted when a syntax element has a value outside its allowed range.
+    #[fail(display = "Invalid value for H.264 syntax element: {}", element)]
+    InvalidSyntaxElement { element: &'static str },
+}
+
+/// This enumeration is exposing the errors that can occurs while parsing
+/// Opus packets, as defined in the section 3.4 of the [RFC 6716].
+///
+/// [RFC 6716]: https://tools.ietf.org/html/rfc6716#section-3.4
+#[derive(Debug, Fail)]
+pub enum OpusError {
+    /// Emitted when the Opus packet does not even contain a TOC byte.
+    #[fail(display = "Opus packet is empty")]
+    EmptyPacket,
+
+    /// Emitted when the frame count of the packet is not valid.
+    #[fail(display = "Invalid Opus frame count: {}", count)]
+    InvalidFrameCount { count: usize },
+
+    /// Emitted when the frame lengths are not consistent with the packet length.
+    #[fail(display = "Invalid Opus frame length")]
+    InvalidFrameLength,
+
+    /// Emitted when the duration of the packet exceeds 120 ms.
+    #[fail(display = "Invalid Opus packet duration: {} samples", duration)]
+    InvalidDuration { duration: u32 },
+
+    /// Emitted when a multistream packet does not contain the expected
+    /// number of streams.
+    #[fail(display = "Invalid Opus stream count: {}", count)]
+    InvalidStreamCount { count: usize },
+}
@@ -0,0 +1,250 @@
+use crate::packet::Packet;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// The default maximum number of times a packet can be retransmitted
+const DEFAULT_MAX_RETRANSMISSIONS: u32 = 10;
+
+#[derive(Clone, Debug)]
+struct HistoryEntry {
+    packet: Packet,
+    sent_at: Instant,
+    retransmissions: u32,
+}
+
+/// This structure is responsible to keep a bounded history of the
+/// recently sent RTP packets, in order to answer to the negative
+/// acknowledgements (NACK) of the receivers.
+///
+/// The packets are keyed by their synchronization source (SSRC) and their
+/// sequence number. When the history is full, the oldest packets are
+/// evicted. A packet is not retransmitted more than a maximum number of
+/// times, nor before a round-trip time (RTT) has elapsed since its last
+/// retransmission, since the receiver could not have received it yet.
+///
+/// The packets returned by this history can be resent as is, or wrapped
+/// into retransmission packets beforehand.
+#[derive(Clone, Debug)]
+pub struct RtpHistory {
+    capacity: usize,
+    max_retransmissions: u32,
+    rtt: Duration,
+    order: VecDeque<(u32, u16)>,
+    packets: HashMap<(u32, u16), HistoryEntry>,
+}
+
+impl RtpHistory {
+    /// Instanciates a new history keeping at most `capacity` packets.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            max_retransmissions: DEFAULT_MAX_RETRANSMISSIONS,
+            rtt: Duration::from_secs(0),
+            order: VecDeque::with_capacity(capacity),
+            packets: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Defines the maximum number of times a packet can be retransmitted.
+    pub fn with_max_retransmissions(mut self, max_retransmissions: u32) -> Self {
+        self.max_retransmissions = max_retransmissions;
+        self
+    }
+
+    /// Retrieves the round-trip time used as minimum resend interval.
+    pub fn rtt(&self) -> Duration {
+        self.rtt
+    }
+
+    /// Updates the round-trip time used as minimum resend interval,
+    /// usually from the RTCP reports.
+    pub fn set_rtt(&mut self, rtt: Duration) {
+        self.rtt = rtt;
+    }
+
+    /// Pushes a packet sent at `now` into the history. A packet with the same
+    /// SSRC and sequence number is replaced.
+    pub fn push(&mut self, packet: Packet, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let key = (packet.ssrc, packet.sequence_number);
+        let entry = HistoryEntry {
+            packet,
+            sent_at: now,
+            retransmissions: 0,
+        };
+
+        if self.packets.insert(key, entry).is_some() {
+            self.order.retain(|k| *k != key);
+        }
+        self.order.push_back(key);
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.packets.remove(&oldest);
+            }
+        }
+    }
+
+    /// Pushes packets sent at `now` into the history, like the ones returned
+    /// by a `Packetizer`.
+    pub fn extend<I: IntoIterator<Item = Packet>>(&mut self, packets: I, now: Instant) {
+        for packet in packets {
+            self.push(packet, now);
+        }
+    }
+
+    /// Retrieves a packet from the history.
+    pub fn get(&self, ssrc: u32, sequence_number: u16) -> Option<&Packet> {
+        self.packets
+            .get(&(ssrc, sequence_number))
+            .map(|entry| &entry.packet)
+    }
+
+    /// Retrieves the number of times a packet has been retransmitted.
+    pub fn retransmissions(&self, ssrc: u32, sequence_number: u16) -> Option<u32> {
+        self.packets
+            .get(&(ssrc, sequence_number))
+            .map(|entry| entry.retransmissions)
+    }
+
+    /// Retrieves the packets to resend at `now` in response to a list of
+    /// negatively acknowledged sequence numbers of a SSRC.
+    ///
+    /// The packets which are unknown, which have already been retransmitted
+    /// too many times, or which have been retransmitted less than a
+    /// round-trip time ago are skipped. The first retransmission of a packet
+    /// is not delayed, as the receiver only sends a NACK once it is missing.
+    /// The resend count of the returned packets is incremented.
+    pub fn resend<I: IntoIterator<Item = u16>>(
+        &mut self,
+        ssrc: u32,
+        sequence_numbers: I,
+        now: Instant,
+    ) -> Vec<Packet> {
+        let mut packets = vec![];
+
+        for sequence_number in sequence_numbers {
+            let entry = match self.packets.get_mut(&(ssrc, sequence_number)) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            if entry.retransmissions >= self.max_retransmissions
+                || (entry.retransmissions > 0
+                    && now.saturating_duration_since(entry.sent_at) < self.rtt)
+            {
+                continue;
+            }
+
+            entry.retransmissions += 1;
+            entry.sent_at = now;
+            packets.push(entry.packet.clone());
+        }
+
+        packets
+    }
+
+    /// Retrieves the number of packets in the history.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Checks if the history contains no packet.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Removes all the packets from the history.
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.packets.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(ssrc: u32, sequence_number: u16) -> Packet {
+        Packet {
+            payload_type: 96,
+            sequence_number,
+            ssrc,
+            payload: vec![sequence_number as u8],
+            ..Packet::default()
+        }
+    }
+
+    #[test]
+    fn it_evicts_the_oldest_packets() {
+        let now = Instant::now();
+        let mut history = RtpHistory::new(3);
+
+        history.extend((65534..=65535).chain(0..=1).map(|sn| packet(1, sn)), now);
+        history.push(packet(2, 0), now);
+
+        assert_eq!(3, history.len());
+        assert!(history.get(1, 65534).is_none());
+        assert!(history.get(1, 65535).is_none());
+        assert_eq!(vec![0], history.get(1, 0).unwrap().payload);
+        assert_eq!(vec![1], history.get(1, 1).unwrap().payload);
+        assert!(history.get(2, 0).is_some());
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn it_resends_the_negatively_acknowledged_packets() {
+        let now = Instant::now();
+        let mut history = RtpHistory::new(16).with_max_retransmissions(2);
+        history.set_rtt(Duration::from_millis(100));
+        history.extend((10..15).map(|sn| packet(1, sn)), now);
+
+        let now = now + Duration::from_millis(100);
+        let packets = history.resend(1, vec![11, 12, 20], now);
+        assert_eq!(
+            vec![11, 12],
+            packets
+                .iter()
+                .map(|p| p.sequence_number)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(1), history.retransmissions(1, 11));
+        assert_eq!(Some(0), history.retransmissions(1, 10));
+
+        // The resend interval starts again from the retransmission
+        assert!(history
+            .resend(1, vec![11], now + Duration::from_millis(50))
+            .is_empty());
+        assert!(history
+            .resend(2, vec![13], now + Duration::from_millis(100))
+            .is_empty());
+
+        let now = now + Duration::from_millis(100);
+        assert_eq!(1, history.resend(1, vec![11], now).len());
+
+        // The maximum number of retransmissions has been reached
+        let now = now + Duration::from_millis(100);
+        assert!(history.resend(1, vec![11], now).is_empty());
+        assert_eq!(1, history.resend(1, vec![12], now).len());
+    }
+
+    #[test]
+    fn it_resends_a_packet_negatively_acknowledged_within_a_round_trip_time() {
+        let now = Instant::now();
+        let mut history = RtpHistory::new(16);
+        history.set_rtt(Duration::from_millis(100));
+        history.push(packet(1, 10), now);
+
+        let now = now + Duration::from_millis(20);
+        assert_eq!(1, history.resend(1, vec![10], now).len());
+
+        // The retransmission could not have been received yet
+        let now = now + Duration::from_millis(20);
+        assert!(history.resend(1, vec![10], now).is_empty());
+    }
+}
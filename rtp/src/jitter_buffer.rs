@@ -0,0 +1,315 @@
+use crate::{packet::Packet, sequencer::extend_sequence_number};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// The default minimum playout delay
+const DEFAULT_MIN_DELAY: Duration = Duration::from_millis(20);
+
+/// The default maximum playout delay
+const DEFAULT_MAX_DELAY: Duration = Duration::from_millis(500);
+
+/// The default maximum number of packets kept by the buffer
+const DEFAULT_CAPACITY: usize = 256;
+
+/// The number of interarrival jitters covered by the playout delay
+const JITTER_FACTOR: f64 = 4.0;
+
+/// This structure is responsible to reorder the received RTP packets of a
+/// stream, and to release them on a playout schedule.
+///
+/// The packets are ordered by their sequence numbers, extended with their
+/// roll over count. The duplicated packets, and the packets received after
+/// the release of a following one, are dropped.
+///
+/// The playout time of a packet is computed from its timestamp, using the
+/// clock rate of the stream, with a playout delay adapting to the
+/// interarrival jitter estimated as stated in the section 6.4.1 of the
+/// [RFC 3550]. The delay covers four times the jitter, within the bounds of
+/// the buffer.
+///
+/// The current time is always given by the caller, so a simulated clock can
+/// be used.
+///
+/// [RFC 3550]: https://tools.ietf.org/html/rfc3550#section-6.4.1
+#[derive(Clone, Debug)]
+pub struct JitterBuffer {
+    clock_rate: u32,
+    min_delay: Duration,
+    max_delay: Duration,
+    capacity: usize,
+    jitter: f64,
+    last_arrival: Option<(Instant, u32)>,
+    anchor: Option<(u32, Instant)>,
+    highest_sequence_number: Option<u64>,
+    released_sequence_number: Option<u64>,
+    packets: BTreeMap<u64, Packet>,
+}
+
+impl JitterBuffer {
+    /// Instanciates a new jitter buffer for a stream with the given RTP
+    /// clock rate.
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate,
+            min_delay: DEFAULT_MIN_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            capacity: DEFAULT_CAPACITY,
+            jitter: 0.0,
+            last_arrival: None,
+            anchor: None,
+            highest_sequence_number: None,
+            released_sequence_number: None,
+            packets: BTreeMap::new(),
+        }
+    }
+
+    /// Defines the bounds of the playout delay.
+    pub fn with_delay(mut self, min_delay: Duration, max_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self.max_delay = max_delay.max(min_delay);
+        self
+    }
+
+    /// Defines the maximum number of packets kept by the buffer. When it's
+    /// full, the oldest packets are released without waiting for their
+    /// playout time.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Retrieves the RTP clock rate of the stream.
+    pub fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    /// Retrieves the estimated interarrival jitter.
+    pub fn jitter(&self) -> Duration {
+        Duration::from_secs_f64(self.jitter / f64::from(self.clock_rate))
+    }
+
+    /// Retrieves the current playout delay.
+    pub fn delay(&self) -> Duration {
+        let delay = self.jitter().mul_f64(JITTER_FACTOR);
+
+        delay.max(self.min_delay).min(self.max_delay)
+    }
+
+    /// Retrieves the number of packets in the buffer.
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Checks if the buffer contains no packet.
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Pushes a packet received at `now` into the buffer. `false` is
+    /// returned if the packet is dropped, because it's a duplicate or it's
+    /// too late.
+    pub fn push(&mut self, packet: Packet, now: Instant) -> bool {
+        let sequence_number = match self.highest_sequence_number {
+            Some(highest) => match extend_sequence_number(highest, packet.sequence_number) {
+                Some(sequence_number) => sequence_number,
+                None => return false,
+            },
+            None => u64::from(packet.sequence_number),
+        };
+
+        if self
+            .released_sequence_number
+            .is_some_and(|released| sequence_number <= released)
+            || self.packets.contains_key(&sequence_number)
+        {
+            return false;
+        }
+
+        self.update_jitter(packet.timestamp, now);
+
+        // The media time of the stream is anchored on the earliest arrival
+        let earlier = match self.media_time(packet.timestamp) {
+            Some(media_time) => now < media_time,
+            None => true,
+        };
+        if earlier {
+            self.anchor = Some((packet.timestamp, now));
+        }
+
+        self.highest_sequence_number = Some(
+            self.highest_sequence_number
+                .map_or(sequence_number, |highest| highest.max(sequence_number)),
+        );
+        self.packets.insert(sequence_number, packet);
+
+        true
+    }
+
+    /// Pops the next packet whose playout time is reached at `now`.
+    pub fn pop(&mut self, now: Instant) -> Option<Packet> {
+        let (&sequence_number, packet) = self.packets.iter().next()?;
+
+        let media_time = self.media_time(packet.timestamp);
+        let due = match media_time {
+            Some(media_time) => now >= media_time + self.delay(),
+            None => true,
+        };
+        if !due && self.packets.len() <= self.capacity {
+            return None;
+        }
+
+        let packet = self.packets.remove(&sequence_number)?;
+        if let Some(media_time) = media_time {
+            self.anchor = Some((packet.timestamp, media_time));
+        }
+        self.released_sequence_number = Some(sequence_number);
+
+        Some(packet)
+    }
+
+    /// Computes the media time of a timestamp, relatively to the anchor of
+    /// the stream.
+    fn media_time(&self, timestamp: u32) -> Option<Instant> {
+        let (anchor_timestamp, anchor) = self.anchor?;
+        let delta = timestamp.wrapping_sub(anchor_timestamp) as i32;
+        let offset =
+            Duration::from_secs_f64(f64::from(delta.unsigned_abs()) / f64::from(self.clock_rate));
+
+        if delta < 0 {
+            anchor.checked_sub(offset)
+        } else {
+            anchor.checked_add(offset)
+        }
+    }
+
+    /// Updates the interarrival jitter estimation with a packet.
+    fn update_jitter(&mut self, timestamp: u32, now: Instant) {
+        if let Some((arrival, last_timestamp)) = self.last_arrival {
+            let arrival_delta = if now >= arrival {
+                now.duration_since(arrival).as_secs_f64()
+            } else {
+                -arrival.duration_since(now).as_secs_f64()
+            };
+            let timestamp_delta = f64::from(timestamp.wrapping_sub(last_timestamp) as i32);
+
+            let difference = arrival_delta * f64::from(self.clock_rate) - timestamp_delta;
+            self.jitter += (difference.abs() - self.jitter) / 16.0;
+        }
+
+        self.last_arrival = Some((now, timestamp));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(sequence_number: u16, timestamp: u32) -> Packet {
+        Packet {
+            sequence_number,
+            timestamp,
+            ..Packet::default()
+        }
+    }
+
+    fn drain(buffer: &mut JitterBuffer, now: Instant) -> Vec<u16> {
+        let mut sequence_numbers = vec![];
+        while let Some(packet) = buffer.pop(now) {
+            sequence_numbers.push(packet.sequence_number);
+        }
+
+        sequence_numbers
+    }
+
+    #[test]
+    fn it_reorders_packets_and_drops_duplicates_and_late_ones() {
+        let now = Instant::now();
+        let mut buffer = JitterBuffer::new(8000);
+
+        assert!(buffer.push(packet(65535, 0), now));
+        assert!(buffer.push(packet(1, 320), now));
+        assert!(buffer.push(packet(0, 160), now));
+        assert!(!buffer.push(packet(1, 320), now));
+        assert_eq!(3, buffer.len());
+
+        assert_eq!(
+            vec![65535, 0, 1],
+            drain(&mut buffer, now + Duration::from_secs(1))
+        );
+        assert!(buffer.is_empty());
+
+        // The packets following the released ones are still accepted
+        assert!(!buffer.push(packet(65534, 4294967136), now));
+        assert!(!buffer.push(packet(0, 160), now));
+        assert!(buffer.push(packet(2, 480), now));
+    }
+
+    #[test]
+    fn it_releases_packets_on_playout_schedule() {
+        let now = Instant::now();
+        let mut buffer = JitterBuffer::new(8000)
+            .with_delay(Duration::from_millis(40), Duration::from_millis(200));
+
+        buffer.push(packet(0, 4294967136), now);
+        buffer.push(packet(1, 0), now + Duration::from_millis(20));
+
+        assert!(buffer.pop(now + Duration::from_millis(39)).is_none());
+        assert_eq!(
+            0,
+            buffer
+                .pop(now + Duration::from_millis(40))
+                .unwrap()
+                .sequence_number
+        );
+        assert!(buffer.pop(now + Duration::from_millis(59)).is_none());
+        assert_eq!(
+            1,
+            buffer
+                .pop(now + Duration::from_millis(60))
+                .unwrap()
+                .sequence_number
+        );
+    }
+
+    #[test]
+    fn it_adapts_the_playout_delay_to_the_jitter() {
+        let now = Instant::now();
+        let mut buffer = JitterBuffer::new(48000)
+            .with_delay(Duration::from_millis(20), Duration::from_millis(200));
+
+        for i in 0..50u16 {
+            let arrival = now + Duration::from_millis(u64::from(i) * 20);
+            buffer.push(packet(i, u32::from(i) * 960), arrival);
+        }
+        assert_eq!(Duration::from_secs(0), buffer.jitter());
+        assert_eq!(Duration::from_millis(20), buffer.delay());
+
+        for i in 50..100u16 {
+            let jitter = if i % 2 == 0 { 0 } else { 15 };
+            let arrival = now + Duration::from_millis(u64::from(i) * 20 + jitter);
+            buffer.push(packet(i, u32::from(i) * 960), arrival);
+        }
+        assert!(buffer.jitter() > Duration::from_millis(10));
+        assert!(buffer.delay() > Duration::from_millis(40));
+        assert!(buffer.delay() <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn it_releases_the_oldest_packets_when_full() {
+        let now = Instant::now();
+        let mut buffer = JitterBuffer::new(8000)
+            .with_delay(Duration::from_millis(100), Duration::from_millis(200))
+            .with_capacity(2);
+
+        for i in 0..4u16 {
+            let arrival = now + Duration::from_millis(u64::from(i) * 20);
+            buffer.push(packet(i, u32::from(i) * 160), arrival);
+        }
+
+        assert_eq!(
+            vec![0, 1],
+            drain(&mut buffer, now + Duration::from_millis(60))
+        );
+        assert_eq!(2, buffer.len());
+    }
+}
@@ -7,13 +7,24 @@ extern crate chrono;
 extern crate failure;
 
 pub mod codecs;
+mod depacketizer;
 pub mod errors;
+mod history;
+mod jitter_buffer;
+mod nack;
 pub mod packet;
 pub mod packetizer;
 mod payload_generator;
+pub mod registry;
+mod sample_builder;
 mod sequencer;
 
+pub use depacketizer::{Depacketizer, PassthroughDepacketizer};
+pub use history::RtpHistory;
+pub use jitter_buffer::JitterBuffer;
+pub use nack::NackGenerator;
 pub use payload_generator::PayloadGenerator;
+pub use sample_builder::{Sample, SampleBuilder};
 pub use sequencer::Sequencer;
 
 /// A conveniance module appropriate for glob imports (`use wrwr_rtp::prelude::*;`).
@@ -24,7 +35,8 @@ pub mod prelude {
     pub use crate::packet::{Packet, HEADER_SIZE, RTP_VERSION};
     #[doc(no_inline)]
     pub use crate::packetizer::{
-        ExtensionNumber, G711Packetizer, G722Packetizer, H264Packetizer, OpusPacketizer,
-        Packetizer, VP8Packetizer, VP9Packetizer,
+        AV1Packetizer, ExtensionNumber, G711Packetizer, G722Packetizer, H264Packetizer,
+        H265Packetizer, OpusMultistreamPacketizer, OpusPacketizer, Packetizer, VP8Packetizer,
+        VP9Packetizer,
     };
 }
@@ -0,0 +1,297 @@
+use crate::{packet::Packet, sequencer::extend_sequence_number};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use wrwr_rtcp::packet::GenericNack;
+
+/// The default maximum number of NACK sent for a lost packet
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+
+/// The default age, in sequence numbers, after which a lost packet is
+/// given up
+const DEFAULT_MAX_AGE: u64 = 1000;
+
+/// The default delay to wait for a reordered packet before to NACK it
+const DEFAULT_REORDERING_DELAY: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, Debug)]
+struct MissingPacket {
+    detected_at: Instant,
+    sent_at: Option<Instant>,
+    attempts: u32,
+}
+
+#[derive(Clone, Debug)]
+struct NackStream {
+    highest_sequence_number: u64,
+    missing: BTreeMap<u64, MissingPacket>,
+}
+
+/// This structure is responsible to detect the lost RTP packets of
+/// the received streams, and to generate the Generic NACK feedback
+/// packets asking for their retransmission, as defined in the [RFC 4585].
+///
+/// The sequence numbers are tracked per synchronization source (SSRC),
+/// extended with their roll over count in order to handle the wrap
+/// arounds. A missing packet is not negatively acknowledged before a
+/// reordering delay, in case it's only late. It's negatively acknowledged
+/// again after each round-trip time (RTT), until it's received, until a
+/// maximum number of attempts, or until it's too old.
+///
+/// [RFC 4585]: https://tools.ietf.org/html/rfc4585#section-6.2.1
+#[derive(Clone, Debug)]
+pub struct NackGenerator {
+    /// The synchronization source of the sender of the NACK packets
+    pub sender_ssrc: u32,
+
+    max_attempts: u32,
+    max_age: u64,
+    reordering_delay: Duration,
+    rtt: Duration,
+    streams: HashMap<u32, NackStream>,
+}
+
+impl NackGenerator {
+    /// Instanciates a new NACK generator sending its packets with the given
+    /// synchronization source.
+    pub fn new(sender_ssrc: u32) -> Self {
+        Self {
+            sender_ssrc,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            max_age: DEFAULT_MAX_AGE,
+            reordering_delay: DEFAULT_REORDERING_DELAY,
+            rtt: Duration::from_secs(0),
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Defines the maximum number of NACK sent for a lost packet.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Defines the age, in sequence numbers, after which a lost packet is
+    /// given up.
+    pub fn with_max_age(mut self, max_age: u16) -> Self {
+        self.max_age = u64::from(max_age);
+        self
+    }
+
+    /// Defines the delay to wait for a reordered packet before to NACK it.
+    pub fn with_reordering_delay(mut self, reordering_delay: Duration) -> Self {
+        self.reordering_delay = reordering_delay;
+        self
+    }
+
+    /// Retrieves the round-trip time waited between two NACK of a packet.
+    pub fn rtt(&self) -> Duration {
+        self.rtt
+    }
+
+    /// Updates the round-trip time waited between two NACK of a packet,
+    /// usually from the RTCP reports.
+    pub fn set_rtt(&mut self, rtt: Duration) {
+        self.rtt = rtt;
+    }
+
+    /// Tracks a packet received at `now`.
+    pub fn receive(&mut self, packet: &Packet, now: Instant) {
+        let max_age = self.max_age;
+        let stream = match self.streams.get_mut(&packet.ssrc) {
+            Some(stream) => stream,
+            None => {
+                self.streams.insert(
+                    packet.ssrc,
+                    NackStream {
+                        highest_sequence_number: u64::from(packet.sequence_number),
+                        missing: BTreeMap::new(),
+                    },
+                );
+                return;
+            }
+        };
+
+        let sequence_number =
+            match extend_sequence_number(stream.highest_sequence_number, packet.sequence_number) {
+                Some(sequence_number) => sequence_number,
+                None => return,
+            };
+        if sequence_number <= stream.highest_sequence_number {
+            // A reordered or retransmitted packet
+            stream.missing.remove(&sequence_number);
+            return;
+        }
+
+        let first =
+            (stream.highest_sequence_number + 1).max(sequence_number.saturating_sub(max_age));
+        for missing in first..sequence_number {
+            stream.missing.insert(
+                missing,
+                MissingPacket {
+                    detected_at: now,
+                    sent_at: None,
+                    attempts: 0,
+                },
+            );
+        }
+        stream.highest_sequence_number = sequence_number;
+
+        // Giving up the packets which are too old
+        let oldest = sequence_number.saturating_sub(max_age);
+        stream.missing = stream.missing.split_off(&oldest);
+    }
+
+    /// Retrieves the sequence numbers of the packets still missing from a
+    /// stream.
+    pub fn missing(&self, ssrc: u32) -> Vec<u16> {
+        self.streams
+            .get(&ssrc)
+            .map(|stream| stream.missing.keys().map(|&sn| sn as u16).collect())
+            .unwrap_or_default()
+    }
+
+    /// Generates the NACK packets to send at `now`, one per stream having
+    /// packets to negatively acknowledge.
+    pub fn generate(&mut self, now: Instant) -> Vec<GenericNack> {
+        let mut ssrcs: Vec<u32> = self.streams.keys().copied().collect();
+        ssrcs.sort_unstable();
+
+        let mut packets = vec![];
+        for ssrc in ssrcs {
+            let stream = match self.streams.get_mut(&ssrc) {
+                Some(stream) => stream,
+                None => continue,
+            };
+
+            let max_attempts = self.max_attempts;
+            stream
+                .missing
+                .retain(|_, missing| missing.attempts < max_attempts);
+
+            let mut sequence_numbers = vec![];
+            for (&sequence_number, missing) in stream.missing.iter_mut() {
+                let ready = match missing.sent_at {
+                    Some(sent_at) => now.saturating_duration_since(sent_at) >= self.rtt,
+                    None => {
+                        now.saturating_duration_since(missing.detected_at) >= self.reordering_delay
+                    }
+                };
+
+                if ready {
+                    missing.attempts += 1;
+                    missing.sent_at = Some(now);
+                    sequence_numbers.push(sequence_number as u16);
+                }
+            }
+
+            if !sequence_numbers.is_empty() {
+                packets.push(GenericNack::new(self.sender_ssrc, ssrc, &sequence_numbers));
+            }
+        }
+
+        packets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(ssrc: u32, sequence_number: u16) -> Packet {
+        Packet {
+            sequence_number,
+            ssrc,
+            ..Packet::default()
+        }
+    }
+
+    #[test]
+    fn it_negatively_acknowledges_lost_packets_across_wrap_around() {
+        let now = Instant::now();
+        let mut generator = NackGenerator::new(0x1234);
+
+        for sequence_number in [65533, 65534, 1, 3].iter() {
+            generator.receive(&packet(1, *sequence_number), now);
+        }
+        assert_eq!(vec![65535, 0, 2], generator.missing(1));
+
+        let now = now + Duration::from_millis(10);
+        let packets = generator.generate(now);
+        assert_eq!(1, packets.len());
+        assert_eq!(0x1234, packets[0].sender_ssrc);
+        assert_eq!(1, packets[0].media_ssrc);
+        assert_eq!(vec![65535, 0, 2], packets[0].sequence_numbers());
+    }
+
+    #[test]
+    fn it_does_not_negatively_acknowledge_reordered_packets() {
+        let now = Instant::now();
+        let mut generator = NackGenerator::new(0x1234);
+
+        generator.receive(&packet(1, 10), now);
+        generator.receive(&packet(1, 12), now);
+        generator.receive(&packet(2, 65535), now);
+        generator.receive(&packet(2, 1), now);
+
+        // The packets are only late
+        assert!(generator
+            .generate(now + Duration::from_millis(5))
+            .is_empty());
+        generator.receive(&packet(1, 11), now + Duration::from_millis(5));
+        generator.receive(&packet(2, 0), now + Duration::from_millis(5));
+        generator.receive(&packet(2, 65534), now + Duration::from_millis(5));
+
+        assert!(generator
+            .generate(now + Duration::from_millis(10))
+            .is_empty());
+        assert!(generator.missing(1).is_empty());
+        assert!(generator.missing(2).is_empty());
+    }
+
+    #[test]
+    fn it_retries_negative_acknowledgements() {
+        let now = Instant::now();
+        let mut generator = NackGenerator::new(0x1234)
+            .with_max_attempts(2)
+            .with_reordering_delay(Duration::from_secs(0));
+        generator.set_rtt(Duration::from_millis(100));
+
+        generator.receive(&packet(1, 0), now);
+        generator.receive(&packet(1, 2), now);
+
+        assert_eq!(1, generator.generate(now).len());
+        assert!(generator
+            .generate(now + Duration::from_millis(50))
+            .is_empty());
+
+        let now = now + Duration::from_millis(100);
+        assert_eq!(vec![1], generator.generate(now)[0].sequence_numbers());
+
+        // The maximum number of attempts has been reached
+        let now = now + Duration::from_millis(100);
+        assert!(generator.generate(now).is_empty());
+        assert!(generator.missing(1).is_empty());
+    }
+
+    #[test]
+    fn it_gives_up_too_old_packets() {
+        let now = Instant::now();
+        let mut generator = NackGenerator::new(0x1234)
+            .with_max_age(4)
+            .with_reordering_delay(Duration::from_secs(0));
+
+        generator.receive(&packet(1, 0), now);
+        generator.receive(&packet(1, 2), now);
+        assert_eq!(vec![1], generator.missing(1));
+
+        generator.receive(&packet(1, 6), now);
+        assert_eq!(vec![3, 4, 5], generator.missing(1));
+
+        generator.receive(&packet(1, 100), now);
+        assert_eq!(vec![96, 97, 98, 99], generator.missing(1));
+        assert_eq!(
+            vec![96, 97, 98, 99],
+            generator.generate(now)[0].sequence_numbers()
+        );
+    }
+}
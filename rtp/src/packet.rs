@@ -319,10 +319,10 @@ impl Packet {
         }
 
         // Instanciating output buffer
-        let mut buffer = Vec::with_capacity(self.packet_size());
+        let mut buffer = vec![0; self.packet_size()];
 
         // Setting the first byte of the buffer
-        buffer[0] = (self.version << VERSION_SHIFT) as u8 | self.csrc.len() as u8;
+        buffer[0] = (self.version << VERSION_SHIFT) | self.csrc.len() as u8;
         if self.padding {
             buffer[0] |= 1 << PADDING_SHIFT;
         }
@@ -360,7 +360,7 @@ impl Packet {
                 .iter()
                 .enumerate()
                 .for_each(|(offset, byte)| {
-                    buffer[12 + payload_offset + offset] = *byte;
+                    buffer[payload_offset + offset] = *byte;
                 });
 
             payload_offset += 4;
@@ -583,4 +583,21 @@ mod tests {
         let export = export.unwrap();
         assert_eq!(export, raw_packet);
     }
+
+    #[test]
+    fn it_marshalls_a_packet_without_its_raw_representation() {
+        let raw_packet: [u8; 29] = [
+            0x91, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x12, 0x34,
+            0x56, 0x78, 0x00, 0x01, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff, 0x98, 0x36, 0xbe, 0x88,
+            0x9e,
+        ];
+        let mut packet = Packet::from_raw(&raw_packet).unwrap();
+        packet.raw = None;
+
+        let export = packet.to_raw();
+        assert!(export.is_ok());
+
+        let export = export.unwrap();
+        assert_eq!(export, raw_packet);
+    }
 }
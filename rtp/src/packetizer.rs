@@ -5,10 +5,15 @@ use crate::{
 use chrono::Local;
 use rand::Rng;
 
+pub type AV1Packetizer = Packetizer<crate::codecs::av1::AV1PayloadGenerator>;
 pub type G711Packetizer = Packetizer<crate::codecs::g711::G711PayloadGenerator>;
 pub type G722Packetizer = Packetizer<crate::codecs::g722::G722PayloadGenerator>;
 pub type H264Packetizer = Packetizer<crate::codecs::h264::H264PayloadGenerator>;
+pub type H265Packetizer = Packetizer<crate::codecs::h265::H265PayloadGenerator>;
 pub type OpusPacketizer = Packetizer<crate::codecs::opus::OpusPayloadGenerator>;
+pub type OpusMultistreamPacketizer =
+    Packetizer<crate::codecs::opus::OpusMultistreamPayloadGenerator>;
+pub type RedPacketizer<G> = Packetizer<crate::codecs::red::RedPayloadGenerator<G>>;
 pub type VP8Packetizer = Packetizer<crate::codecs::vp8::VP8PayloadGenerator>;
 pub type VP9Packetizer = Packetizer<crate::codecs::vp9::VP9PayloadGenerator>;
 
@@ -38,6 +43,7 @@ pub struct Packetizer<G: PayloadGenerator + Default> {
     pub synchronization_source: u32,
 
     timestamp: u32,
+    talkspurt: bool,
     extensions: Vec<ExtensionNumber>,
     generator: G,
     sequencer: Sequencer,
@@ -54,6 +60,7 @@ where
             payload_type,
             synchronization_source: ssrc,
             timestamp: rand::thread_rng().gen(),
+            talkspurt: true,
             extensions: Vec::new(),
             generator: G::default(),
             sequencer: Sequencer::new(),
@@ -64,22 +71,26 @@ where
     ///
     /// The data must be in the codec supported by the generator you've
     /// specified when you've instanciated the packetizer.
+    ///
+    /// If the generator is able to compute the duration of each payload,
+    /// like the audio ones, the timestamp is advanced after each packet by
+    /// its duration. Otherwise, all the packets share the same timestamp,
+    /// which is advanced by `samples` afterwards.
+    ///
+    /// The marker bit is set on the last packet of the data, unless the
+    /// generator is carrying audio. In this case, it's only set on the first
+    /// packet of a talkspurt, which is the first packet generated by the
+    /// packetizer or after a call to [`skip`](Self::skip).
     pub fn packetize(&mut self, data: &[u8], samples: u32) -> Option<Vec<Packet>> {
-        if data.len() == 0 || self.mtu <= packet::HEADER_SIZE {
+        if data.is_empty() || self.mtu <= packet::HEADER_SIZE {
             return None;
         }
 
         // Trying to retrieve RTP packets' payloads
         let payloads = self
             .generator
-            .generate(self.mtu - packet::HEADER_SIZE, data);
-        if let None = payloads {
-            return None;
-        }
-
-        // Transforming RTP payloads into RTP packets
-        let payloads = payloads.unwrap();
-        if payloads.len() == 0 {
+            .generate(self.mtu - packet::HEADER_SIZE, data)?;
+        if payloads.is_empty() {
             return None;
         }
 
@@ -91,51 +102,64 @@ where
             }
         });
 
-        let packets: Vec<Packet> = payloads
-            .iter()
-            .enumerate()
-            .map(|(index, payload)| {
-                let mut extension = false;
-                let mut extension_profile = None;
-                let mut extension_payload = None;
-                let marker = payload.len() - 1 == index;
-
-                if marker {
-                    if let Some(abs_send_time) = &abs_send_time {
-                        let time = get_ntp_time();
-
-                        extension = true;
-                        extension_profile = Some(0xbede);
-                        extension_payload = Some(vec![
-                            ((*abs_send_time << 4) | 2) as u8,
-                            (time & 0xff0000 >> 16) as u8,
-                            (time & 0xff00 >> 8) as u8,
-                            (time & 0xff) as u8,
-                        ]);
-                    }
-                }
+        // Transforming RTP payloads into RTP packets
+        let mut packets = Vec::with_capacity(payloads.len());
+        let mut advanced = false;
+
+        for (index, payload) in payloads.iter().enumerate() {
+            let mut extension = false;
+            let mut extension_profile = None;
+            let mut extension_payload = None;
+            let last = payloads.len() - 1 == index;
+            let marker = if self.generator.is_audio() {
+                self.talkspurt && index == 0
+            } else {
+                last
+            };
+
+            if last {
+                if let Some(abs_send_time) = &abs_send_time {
+                    let time = get_ntp_time();
 
-                Packet {
-                    version: packet::RTP_VERSION,
-                    padding: false,
-                    extension,
-                    marker,
-                    payload_type: self.payload_type,
-                    sequence_number: self.sequencer.next_sequence_number(),
-                    timestamp: self.timestamp,
-                    ssrc: self.synchronization_source,
-                    csrc: Vec::new(),
-                    extension_profile,
-                    extension_payload,
-                    payload_offset: packet::HEADER_SIZE,
-                    payload: Vec::from(&payload[..]),
-                    raw: None,
+                    extension = true;
+                    extension_profile = Some(0xbede);
+                    extension_payload = Some(vec![
+                        ((*abs_send_time << 4) | 2) as u8,
+                        (time & 0xff0000 >> 16) as u8,
+                        (time & 0xff00 >> 8) as u8,
+                        (time & 0xff) as u8,
+                    ]);
                 }
-            })
-            .collect();
+            }
+
+            packets.push(Packet {
+                version: packet::RTP_VERSION,
+                padding: false,
+                extension,
+                marker,
+                payload_type: self.payload_type,
+                sequence_number: self.sequencer.next_sequence_number(),
+                timestamp: self.timestamp,
+                ssrc: self.synchronization_source,
+                csrc: Vec::new(),
+                extension_profile,
+                extension_payload,
+                payload_offset: packet::HEADER_SIZE,
+                payload: Vec::from(&payload[..]),
+                raw: None,
+            });
+
+            // Refreshing internal timestamp
+            if let Some(duration) = self.generator.duration(payload) {
+                self.timestamp = self.timestamp.wrapping_add(duration);
+                advanced = true;
+            }
+        }
 
-        // Refreshing internal timestamp
-        self.timestamp += samples;
+        if !advanced {
+            self.timestamp = self.timestamp.wrapping_add(samples);
+        }
+        self.talkspurt = false;
 
         Some(packets)
     }
@@ -144,6 +168,37 @@ where
     pub fn add_extension_number(&mut self, extension: ExtensionNumber) {
         self.extensions.push(extension);
     }
+
+    /// Retrieves the timestamp of the next packet to be generated.
+    pub fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    /// Advances the timestamp without generating any packet, like during a
+    /// silence which is not transmitted. The next packet starts a new
+    /// talkspurt.
+    pub fn skip(&mut self, samples: u32) {
+        self.timestamp = self.timestamp.wrapping_add(samples);
+        self.talkspurt = true;
+    }
+
+    /// Retrieves a mutable reference to the sequencer used by the
+    /// packetizer, in order to send other packets, like telephone events,
+    /// into the same RTP stream.
+    pub fn sequencer_mut(&mut self) -> &mut Sequencer {
+        &mut self.sequencer
+    }
+
+    /// Retrieves the payload generator used by the packetizer.
+    pub fn generator(&self) -> &G {
+        &self.generator
+    }
+
+    /// Retrieves a mutable reference to the payload generator used by the
+    /// packetizer, in order to update its state between two frames.
+    pub fn generator_mut(&mut self) -> &mut G {
+        &mut self.generator
+    }
 }
 
 /// Converts an Unix epoch, in nanoseconds, into a NTP time.
@@ -195,4 +250,54 @@ mod tests {
         let packets = packetizer.packetize(&[], 2000);
         assert!(packets.is_none());
     }
+
+    #[test]
+    fn it_advances_the_timestamp_per_audio_packet() {
+        let mut packetizer = G711Packetizer::new(1500, 0, 0x1234abcd);
+        packetizer.generator_mut().packetization_time.ptime = Some(20);
+        let data = [0xffu8; 400];
+
+        let packets = packetizer.packetize(&data, 400).unwrap();
+        let durations: Vec<_> = packets
+            .windows(2)
+            .map(|packets| packets[1].timestamp.wrapping_sub(packets[0].timestamp))
+            .collect();
+        assert_eq!(vec![160, 160], durations);
+
+        let next = packetizer.packetize(&data, 400).unwrap();
+        assert_eq!(400, next[0].timestamp.wrapping_sub(packets[0].timestamp));
+    }
+
+    #[test]
+    fn it_sets_the_marker_bit_on_the_last_packet_of_a_frame() {
+        let mut packetizer = VP8Packetizer::new(20, 96, 0x1234abcd);
+        let data = [0x01u8; 10];
+
+        for _ in 0..2 {
+            let packets = packetizer.packetize(&data, 3000).unwrap();
+            let markers: Vec<_> = packets.iter().map(|packet| packet.marker).collect();
+            assert_eq!(vec![false, true], markers);
+        }
+    }
+
+    #[test]
+    fn it_sets_the_marker_bit_on_the_first_packet_of_a_talkspurt() {
+        let mut packetizer = G711Packetizer::new(1500, 0, 0x1234abcd);
+        packetizer.generator_mut().packetization_time.ptime = Some(20);
+        let data = [0xffu8; 320];
+
+        let markers = |packets: Vec<Packet>| -> Vec<bool> {
+            packets.iter().map(|packet| packet.marker).collect()
+        };
+
+        let packets = packetizer.packetize(&data, 320).unwrap();
+        assert_eq!(vec![true, false], markers(packets));
+
+        let packets = packetizer.packetize(&data, 320).unwrap();
+        assert_eq!(vec![false, false], markers(packets));
+
+        packetizer.skip(800);
+        let packets = packetizer.packetize(&data, 320).unwrap();
+        assert_eq!(vec![true, false], markers(packets));
+    }
 }
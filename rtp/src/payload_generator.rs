@@ -10,4 +10,25 @@ pub trait PayloadGenerator {
     /// This method has a mutable reference to `self` in case the generator
     /// needs to mutate an internal state while generating the payloads.
     fn generate(&mut self, mtu: usize, payload: &[u8]) -> Option<Vec<Vec<u8>>>;
+
+    /// Try to computes the duration of an arbitrary payload, in RTP clock
+    /// ticks, from the payload itself.
+    ///
+    /// By default, the duration can not be computed and `None` is returned.
+    fn duration(&self, _payload: &[u8]) -> Option<u32> {
+        None
+    }
+
+    /// Indicates if the generated payloads are carrying audio. The marker
+    /// bit of the audio packets is set on the first packet of a talkspurt,
+    /// as defined in the [RFC 3551], while it's set on the last packet of
+    /// each frame for the other ones.
+    ///
+    /// By default, the payloads are not carrying audio and `false` is
+    /// returned.
+    ///
+    /// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-4.1
+    fn is_audio(&self) -> bool {
+        false
+    }
 }
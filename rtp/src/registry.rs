@@ -0,0 +1,465 @@
+use crate::{codecs::KeyframeDetector, Depacketizer, PassthroughDepacketizer};
+use std::collections::BTreeMap;
+
+/// The payload types which can be used in a RTP stream
+const MAX_PAYLOAD_TYPE: u8 = 0x7f;
+
+/// The static payload types assignments of the [RFC 3551], defined this way:
+/// `(payload type, encoding name, clock rate, channels)`.
+///
+/// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-6
+const STATIC_PAYLOAD_TYPES: [(u8, &str, u32, Option<u8>); 24] = [
+    (0, "PCMU", 8000, Some(1)),
+    (3, "GSM", 8000, Some(1)),
+    (4, "G723", 8000, Some(1)),
+    (5, "DVI4", 8000, Some(1)),
+    (6, "DVI4", 16000, Some(1)),
+    (7, "LPC", 8000, Some(1)),
+    (8, "PCMA", 8000, Some(1)),
+    (9, "G722", 8000, Some(1)),
+    (10, "L16", 44100, Some(2)),
+    (11, "L16", 44100, Some(1)),
+    (12, "QCELP", 8000, Some(1)),
+    (13, "CN", 8000, Some(1)),
+    (14, "MPA", 90000, None),
+    (15, "G728", 8000, Some(1)),
+    (16, "DVI4", 11025, Some(1)),
+    (17, "DVI4", 22050, Some(1)),
+    (18, "G729", 8000, Some(1)),
+    (25, "CelB", 90000, None),
+    (26, "JPEG", 90000, None),
+    (28, "nv", 90000, None),
+    (31, "H261", 90000, None),
+    (32, "MPV", 90000, None),
+    (33, "MP2T", 90000, None),
+    (34, "H263", 90000, None),
+];
+
+/// The audio codecs of the [RFC 3551] whose payloads are the codec data
+/// itself, defined this way: `(encoding name, bytes per sample)`. The number
+/// of bytes per sample is only given when it's constant.
+///
+/// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-4.5
+const PASSTHROUGH_AUDIO_CODECS: [(&str, Option<u32>); 12] = [
+    ("PCMU", Some(1)),
+    ("PCMA", Some(1)),
+    // The RTP clock rate of G.722 is half its sampling rate
+    ("G722", Some(1)),
+    ("L16", Some(2)),
+    ("GSM", None),
+    ("G723", None),
+    ("DVI4", None),
+    ("LPC", None),
+    ("QCELP", None),
+    ("CN", None),
+    ("G728", None),
+    ("G729", None),
+];
+
+/// Describes the codec carried by a RTP payload type, as given by the
+/// `rtpmap` and `fmtp` attributes of a SDP.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Codec {
+    /// The encoding name of the codec, which is case-insensitive.
+    pub name: String,
+
+    /// The RTP clock rate of the codec, in Hz.
+    pub clock_rate: u32,
+
+    /// The number of audio channels, if provided.
+    pub channels: Option<u8>,
+
+    /// The format specific parameters (`fmtp`), if provided.
+    pub parameters: Option<String>,
+}
+
+impl Codec {
+    /// Instanciates a new codec description without format specific
+    /// parameters.
+    pub fn new(name: &str, clock_rate: u32, channels: Option<u8>) -> Self {
+        Self {
+            name: String::from(name),
+            clock_rate,
+            channels,
+            parameters: None,
+        }
+    }
+
+    /// Parses the value of a `rtpmap` attribute, following the payload
+    /// type, like `opus/48000/2`.
+    ///
+    /// If the value is malformed, `None` is returned.
+    pub fn from_rtpmap(rtpmap: &str) -> Option<Self> {
+        let mut parts = rtpmap.trim().split('/');
+
+        let name = parts.next().filter(|name| !name.is_empty())?;
+        let clock_rate = parts.next()?.parse().ok()?;
+        let channels = match parts.next() {
+            Some(channels) => Some(channels.parse().ok()?),
+            None => None,
+        };
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self::new(name, clock_rate, channels))
+    }
+
+    /// Checks if the codec has the given encoding name, ignoring the case.
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    /// Instanciates the depacketizer of the codec, if it's supported.
+    ///
+    /// The audio codecs of the RFC 3551 get a depacketizer emitting the
+    /// payloads as is. There is no depacketizer for H.264 yet, so `None` is
+    /// returned for it.
+    pub fn depacketizer(&self) -> Option<Box<dyn Depacketizer>> {
+        #[cfg(feature = "av1")]
+        {
+            if self.is("AV1") {
+                return Some(Box::new(crate::codecs::av1::AV1Depacketizer::default()));
+            }
+        }
+
+        #[cfg(feature = "h265")]
+        {
+            if self.is("H265") {
+                return Some(Box::new(crate::codecs::h265::H265Depacketizer::default()));
+            }
+        }
+
+        #[cfg(feature = "opus")]
+        {
+            use crate::codecs::opus::{
+                OpusDepacketizer, OpusMultistreamConfig, OpusMultistreamDepacketizer,
+            };
+
+            if self.is("opus") {
+                return Some(Box::new(OpusDepacketizer::default()));
+            } else if self.is("multiopus") {
+                let config = OpusMultistreamConfig::from_fmtp(self.parameters.as_ref()?)?;
+
+                return Some(Box::new(OpusMultistreamDepacketizer::new(config)));
+            }
+        }
+
+        #[cfg(feature = "vp8")]
+        {
+            if self.is("VP8") {
+                return Some(Box::new(crate::codecs::vp8::VP8Depacketizer::default()));
+            }
+        }
+
+        #[cfg(feature = "vp9")]
+        {
+            if self.is("VP9") {
+                return Some(Box::new(crate::codecs::vp9::VP9Depacketizer::default()));
+            }
+        }
+
+        let (_, bytes_per_sample) = PASSTHROUGH_AUDIO_CODECS
+            .iter()
+            .find(|(name, _)| self.is(name))?;
+        let channels = u32::from(self.channels.unwrap_or(1));
+
+        Some(Box::new(PassthroughDepacketizer::new(
+            bytes_per_sample.map(|bytes| bytes * channels),
+        )))
+    }
+
+    /// Instanciates the keyframe detector of the codec, if it's a supported
+    /// video codec.
+    pub fn keyframe_detector(&self) -> Option<Box<dyn KeyframeDetector>> {
+        #[cfg(feature = "av1")]
+        {
+            if self.is("AV1") {
+                return Some(Box::new(crate::codecs::av1::AV1KeyframeDetector));
+            }
+        }
+
+        #[cfg(feature = "h264")]
+        {
+            if self.is("H264") {
+                return Some(Box::new(crate::codecs::h264::H264KeyframeDetector));
+            }
+        }
+
+        #[cfg(feature = "h265")]
+        {
+            if self.is("H265") {
+                return Some(Box::new(crate::codecs::h265::H265KeyframeDetector));
+            }
+        }
+
+        #[cfg(feature = "vp8")]
+        {
+            if self.is("VP8") {
+                return Some(Box::new(crate::codecs::vp8::VP8KeyframeDetector));
+            }
+        }
+
+        #[cfg(feature = "vp9")]
+        {
+            if self.is("VP9") {
+                return Some(Box::new(crate::codecs::vp9::VP9KeyframeDetector));
+            }
+        }
+
+        None
+    }
+}
+
+/// This structure is responsible to map the RTP payload types to the codecs
+/// they carry, so that a receiver knows how to handle an incoming packet.
+///
+/// The registry starts with the static assignments of the [RFC 3551], which
+/// can be overridden. The dynamic payload types are registered from the
+/// `rtpmap` and `fmtp` attributes of a SDP.
+///
+/// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-6
+#[derive(Clone, Debug)]
+pub struct PayloadTypeRegistry {
+    codecs: BTreeMap<u8, Codec>,
+}
+
+impl PayloadTypeRegistry {
+    /// Instanciates a new registry with the static payload types.
+    pub fn new() -> Self {
+        let codecs = STATIC_PAYLOAD_TYPES
+            .iter()
+            .map(|&(payload_type, name, clock_rate, channels)| {
+                (payload_type, Codec::new(name, clock_rate, channels))
+            })
+            .collect();
+
+        Self { codecs }
+    }
+
+    /// Instanciates a new registry without any payload type.
+    pub fn empty() -> Self {
+        Self {
+            codecs: BTreeMap::new(),
+        }
+    }
+
+    /// Retrieves the codec of a payload type.
+    pub fn get(&self, payload_type: u8) -> Option<&Codec> {
+        self.codecs.get(&payload_type)
+    }
+
+    /// Retrieves the first payload type carrying a codec with the given
+    /// encoding name and clock rate.
+    pub fn find(&self, name: &str, clock_rate: u32) -> Option<u8> {
+        self.codecs
+            .iter()
+            .find(|(_, codec)| codec.is(name) && codec.clock_rate == clock_rate)
+            .map(|(&payload_type, _)| payload_type)
+    }
+
+    /// Registers the codec of a payload type, replacing the previous one.
+    /// `false` is returned if the payload type is not valid.
+    pub fn register(&mut self, payload_type: u8, codec: Codec) -> bool {
+        if payload_type > MAX_PAYLOAD_TYPE {
+            return false;
+        }
+
+        self.codecs.insert(payload_type, codec);
+
+        true
+    }
+
+    /// Removes a payload type from the registry.
+    pub fn unregister(&mut self, payload_type: u8) -> Option<Codec> {
+        self.codecs.remove(&payload_type)
+    }
+
+    /// Registers a payload type from the value of its `rtpmap` attribute,
+    /// keeping the format specific parameters already registered.
+    pub fn register_rtpmap(&mut self, payload_type: u8, rtpmap: &str) -> Option<&Codec> {
+        let mut codec = Codec::from_rtpmap(rtpmap)?;
+        codec.parameters = self
+            .codecs
+            .get(&payload_type)
+            .and_then(|codec| codec.parameters.clone());
+
+        if !self.register(payload_type, codec) {
+            return None;
+        }
+
+        self.get(payload_type)
+    }
+
+    /// Registers the format specific parameters of a payload type from the
+    /// value of its `fmtp` attribute. `None` is returned if the payload type
+    /// is unknown.
+    pub fn register_fmtp(&mut self, payload_type: u8, fmtp: &str) -> Option<&Codec> {
+        let codec = self.codecs.get_mut(&payload_type)?;
+        codec.parameters = Some(String::from(fmtp.trim()));
+
+        Some(codec)
+    }
+
+    /// Registers a payload type from a SDP `rtpmap` or `fmtp` attribute line,
+    /// like `a=rtpmap:111 opus/48000/2`. `None` is returned if the line is
+    /// not such an attribute, or if it's malformed.
+    pub fn register_attribute(&mut self, line: &str) -> Option<&Codec> {
+        let line = line.trim();
+        let line = line.strip_prefix("a=").unwrap_or(line);
+
+        let (attribute, value) = line.split_at(line.find(':')?);
+        let value = value[1..].trim_start();
+        let (payload_type, value) = value.split_at(value.find(' ')?);
+        let payload_type = payload_type.parse().ok()?;
+
+        match attribute {
+            "rtpmap" => self.register_rtpmap(payload_type, value),
+            "fmtp" => self.register_fmtp(payload_type, value),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PayloadTypeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Packet;
+
+    #[test]
+    fn it_contains_the_static_payload_types() {
+        let registry = PayloadTypeRegistry::new();
+
+        assert_eq!(Some(&Codec::new("PCMU", 8000, Some(1))), registry.get(0));
+        assert_eq!(Some(&Codec::new("PCMA", 8000, Some(1))), registry.get(8));
+        assert_eq!(Some(&Codec::new("G722", 8000, Some(1))), registry.get(9));
+        assert_eq!(Some(&Codec::new("CN", 8000, Some(1))), registry.get(13));
+        assert_eq!(Some(&Codec::new("H263", 90000, None)), registry.get(34));
+        assert_eq!(None, registry.get(2));
+        assert_eq!(None, registry.get(96));
+
+        assert_eq!(Some(6), registry.find("dvi4", 16000));
+        assert_eq!(None, registry.find("opus", 48000));
+        assert_eq!(None, PayloadTypeRegistry::empty().get(0));
+    }
+
+    #[test]
+    fn it_parses_rtpmap_values() {
+        assert_eq!(
+            Some(Codec::new("opus", 48000, Some(2))),
+            Codec::from_rtpmap("opus/48000/2")
+        );
+        assert_eq!(
+            Some(Codec::new("VP8", 90000, None)),
+            Codec::from_rtpmap("VP8/90000")
+        );
+        assert_eq!(None, Codec::from_rtpmap("VP8"));
+        assert_eq!(None, Codec::from_rtpmap("/90000"));
+        assert_eq!(None, Codec::from_rtpmap("opus/48000/two"));
+        assert_eq!(None, Codec::from_rtpmap("opus/48000/2/1"));
+    }
+
+    #[test]
+    fn it_registers_dynamic_payload_types() {
+        let mut registry = PayloadTypeRegistry::new();
+
+        assert!(registry
+            .register_attribute("a=fmtp:111 minptime=10;useinbandfec=1")
+            .is_none());
+        assert!(registry
+            .register_attribute("a=rtpmap:111 opus/48000/2")
+            .is_some());
+        assert!(registry
+            .register_attribute("a=fmtp:111 minptime=10;useinbandfec=1")
+            .is_some());
+        assert!(registry
+            .register_attribute("a=rtpmap:96 VP8/90000")
+            .is_some());
+        assert!(registry.register_attribute("a=rtcp-fb:96 nack").is_none());
+        assert!(registry
+            .register_attribute("a=rtpmap:128 VP8/90000")
+            .is_none());
+
+        let codec = registry.get(111).unwrap();
+        assert!(codec.is("OPUS"));
+        assert_eq!(48000, codec.clock_rate);
+        assert_eq!(Some(2), codec.channels);
+        assert_eq!(
+            Some("minptime=10;useinbandfec=1"),
+            codec.parameters.as_deref()
+        );
+
+        // The parameters are kept when the codec is registered again
+        registry.register_rtpmap(111, "opus/48000/2");
+        assert!(registry.get(111).unwrap().parameters.is_some());
+
+        assert_eq!(Some(96), registry.find("vp8", 90000));
+        assert!(registry.unregister(96).is_some());
+        assert_eq!(None, registry.get(96));
+    }
+
+    #[cfg(all(feature = "opus", feature = "vp8"))]
+    #[test]
+    fn it_provides_the_depacketizers_and_keyframe_detectors() {
+        let mut registry = PayloadTypeRegistry::new();
+        registry.register_attribute("a=rtpmap:96 VP8/90000");
+        registry.register_attribute("a=rtpmap:111 opus/48000/2");
+        registry.register_attribute("a=rtpmap:112 multiopus/48000/6");
+
+        let vp8 = registry.get(96).unwrap();
+        assert!(vp8.depacketizer().is_some());
+        assert!(vp8
+            .keyframe_detector()
+            .unwrap()
+            .is_keyframe(&[0x10, 0x50, 0x02, 0x00]));
+
+        let opus = registry.get(111).unwrap();
+        assert!(opus.keyframe_detector().is_none());
+        assert_eq!(
+            Some(960),
+            opus.depacketizer().unwrap().duration(&[0x78, 0x01])
+        );
+
+        // The multistream parameters are required
+        assert!(registry.get(112).unwrap().depacketizer().is_none());
+        registry.register_attribute(
+            "a=fmtp:112 channel_mapping=0,4,1,2,3,5;num_streams=4;coupled_streams=2",
+        );
+        assert!(registry.get(112).unwrap().depacketizer().is_some());
+    }
+
+    #[test]
+    fn it_provides_pass_through_depacketizers_for_static_audio_codecs() {
+        let mut registry = PayloadTypeRegistry::new();
+        registry.register_attribute("a=rtpmap:97 L16/48000/2");
+        registry.register_attribute("a=rtpmap:98 H264/90000");
+
+        let mut pcmu = registry.get(0).unwrap().depacketizer().unwrap();
+        let packet = Packet {
+            payload: vec![0xff; 160],
+            ..Packet::default()
+        };
+        assert_eq!(Some(vec![0xff; 160]), pcmu.depacketize(&packet));
+        assert_eq!(Some(160), pcmu.duration(&packet.payload));
+
+        let g722 = registry.get(9).unwrap().depacketizer().unwrap();
+        assert_eq!(Some(160), g722.duration(&[0x00; 160]));
+
+        let l16 = registry.get(97).unwrap().depacketizer().unwrap();
+        assert_eq!(Some(40), l16.duration(&[0x00; 160]));
+
+        let gsm = registry.get(3).unwrap().depacketizer().unwrap();
+        assert_eq!(None, gsm.duration(&[0x00; 33]));
+
+        // There is no H.264 depacketizer yet
+        assert!(registry.get(98).unwrap().depacketizer().is_none());
+        assert!(registry.get(34).unwrap().depacketizer().is_none());
+    }
+}
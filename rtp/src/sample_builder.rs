@@ -0,0 +1,406 @@
+use crate::{depacketizer::Depacketizer, packet::Packet, sequencer::extend_sequence_number};
+use std::collections::BTreeMap;
+
+/// A media frame rebuilt from RTP packets.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Sample {
+    /// The codec data of the frame
+    pub data: Vec<u8>,
+
+    /// The RTP timestamp of the frame
+    pub timestamp: u32,
+
+    /// The duration of the frame in RTP clock ticks
+    pub duration: u32,
+
+    /// Indicates if some packets have been lost since the previous frame
+    pub packets_lost: bool,
+}
+
+/// This structure is responsible to rebuild whole media frames from the
+/// received RTP packets of a stream, with a depacketizer.
+///
+/// The packets are buffered and ordered by their sequence numbers, extended
+/// with their roll over count. A frame is made of the consecutive packets
+/// sharing a timestamp, and is complete once a packet with the marker bit,
+/// or a packet of the following frame, is received.
+///
+/// When a packet is missing, the builder waits for it until `max_late`
+/// packets have been received after it. Then, the incomplete frames are
+/// dropped, as well as the following frame if its first packet is not
+/// starting a partition for the depacketizer, and the next emitted frame is
+/// flagged with `packets_lost`. The depacketizer is reset when a frame is
+/// dropped or is not producing any data, so that its fragments are not
+/// mixed with the following frame.
+///
+/// The duration of a frame is computed by the depacketizer when it's able
+/// to, otherwise it's the timestamp difference with the following frame,
+/// which is awaited before emitting the frame. If the packet following the
+/// frame is missing, it's awaited like the other missing packets.
+#[derive(Clone, Debug)]
+pub struct SampleBuilder<D: Depacketizer> {
+    depacketizer: D,
+    max_late: u64,
+    packets: BTreeMap<u64, Packet>,
+    highest_sequence_number: Option<u64>,
+    next_sequence_number: Option<u64>,
+    last_timestamp: Option<u32>,
+    packets_lost: bool,
+    pending: Option<Sample>,
+}
+
+impl<D: Depacketizer> SampleBuilder<D> {
+    /// Instanciates a new sample builder waiting at most `max_late` packets
+    /// for a missing one.
+    pub fn new(depacketizer: D, max_late: u16) -> Self {
+        Self {
+            depacketizer,
+            max_late: u64::from(max_late),
+            packets: BTreeMap::new(),
+            highest_sequence_number: None,
+            next_sequence_number: None,
+            last_timestamp: None,
+            packets_lost: false,
+            pending: None,
+        }
+    }
+
+    /// Retrieves the depacketizer of the builder.
+    pub fn depacketizer(&self) -> &D {
+        &self.depacketizer
+    }
+
+    /// Retrieves a mutable reference to the depacketizer of the builder.
+    pub fn depacketizer_mut(&mut self) -> &mut D {
+        &mut self.depacketizer
+    }
+
+    /// Pushes a received packet into the builder. `false` is returned if the
+    /// packet is dropped, because it's a duplicate or it's too late.
+    pub fn push(&mut self, packet: Packet) -> bool {
+        let sequence_number = match self.highest_sequence_number {
+            Some(highest) => match extend_sequence_number(highest, packet.sequence_number) {
+                Some(sequence_number) => sequence_number,
+                None => return false,
+            },
+            None => u64::from(packet.sequence_number),
+        };
+
+        if self
+            .next_sequence_number
+            .is_some_and(|next| sequence_number < next)
+            || self.packets.contains_key(&sequence_number)
+        {
+            return false;
+        }
+
+        self.highest_sequence_number = Some(
+            self.highest_sequence_number
+                .map_or(sequence_number, |highest| highest.max(sequence_number)),
+        );
+        self.packets.insert(sequence_number, packet);
+
+        true
+    }
+
+    /// Pops the next complete frame, if any.
+    pub fn pop(&mut self) -> Option<Sample> {
+        loop {
+            if let Some(sample) = self.pending.take() {
+                // The following frame is known once the next packet has been
+                // received, or once it's given up
+                let next = self.next_sequence_number;
+                let packet = match next.and_then(|next| self.packets.get(&next)) {
+                    Some(packet) => packet,
+                    None => match self.packets.values().next() {
+                        Some(packet) if next.is_some_and(|next| self.is_too_late(next)) => packet,
+                        _ => {
+                            self.pending = Some(sample);
+                            return None;
+                        }
+                    },
+                };
+
+                return Some(Sample {
+                    duration: packet.timestamp.wrapping_sub(sample.timestamp),
+                    ..sample
+                });
+            }
+
+            let (&first, packet) = self.packets.iter().next()?;
+            let timestamp = packet.timestamp;
+
+            // Some packets are missing before the next frame
+            if let Some(next) = self.next_sequence_number {
+                if first != next {
+                    if !self.is_too_late(next) {
+                        return None;
+                    }
+
+                    self.packets_lost = true;
+                    self.next_sequence_number = Some(first);
+
+                    // The frame of the last released packet is incomplete, or
+                    // the first packets of the next frame are missing
+                    if self.last_timestamp == Some(timestamp)
+                        || !self.depacketizer.is_partition_head(&packet.payload)
+                    {
+                        self.drop_frame(first, timestamp);
+                    }
+
+                    continue;
+                }
+            }
+
+            let mut end = first;
+            let mut complete = false;
+            for (&sequence_number, packet) in self.packets.range(first..) {
+                if sequence_number != end {
+                    break;
+                } else if packet.timestamp != timestamp {
+                    complete = true;
+                    break;
+                }
+
+                end += 1;
+                if packet.marker {
+                    complete = true;
+                    break;
+                }
+            }
+
+            if !complete {
+                if !self.is_too_late(end) {
+                    return None;
+                }
+
+                self.packets_lost = true;
+                self.drop_frame(first, timestamp);
+
+                continue;
+            }
+
+            let mut data = vec![];
+            let mut depacketized = false;
+            for sequence_number in first..end {
+                if let Some(packet) = self.packets.remove(&sequence_number) {
+                    if let Some(payload) = self.depacketizer.depacketize(&packet) {
+                        data.extend_from_slice(&payload);
+                        depacketized = true;
+                    }
+                }
+            }
+            self.next_sequence_number = Some(end);
+            self.last_timestamp = Some(timestamp);
+
+            if !depacketized {
+                self.depacketizer.reset();
+                self.packets_lost = true;
+                continue;
+            }
+
+            let duration = self.depacketizer.duration(&data);
+            let sample = Sample {
+                data,
+                timestamp,
+                duration: duration.unwrap_or(0),
+                packets_lost: std::mem::take(&mut self.packets_lost),
+            };
+
+            if duration.is_some() {
+                return Some(sample);
+            }
+
+            self.pending = Some(sample);
+        }
+    }
+
+    /// Checks if enough packets have been received after a missing one to
+    /// give it up.
+    fn is_too_late(&self, sequence_number: u64) -> bool {
+        self.highest_sequence_number
+            .is_some_and(|highest| highest >= sequence_number + self.max_late)
+    }
+
+    /// Drops the consecutive packets of a frame, starting from the first
+    /// buffered one.
+    fn drop_frame(&mut self, first: u64, timestamp: u32) {
+        let mut next = first;
+        while self
+            .packets
+            .get(&next)
+            .is_some_and(|packet| packet.timestamp == timestamp)
+        {
+            self.packets.remove(&next);
+            next += 1;
+        }
+
+        self.next_sequence_number = Some(next);
+        self.last_timestamp = Some(timestamp);
+        self.depacketizer.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A depacketizer concatenating the payloads of a frame.
+    #[derive(Default)]
+    struct FrameDepacketizer {
+        frame: Vec<u8>,
+        /// The payloads which are not starting a frame
+        fragments: Vec<u8>,
+    }
+
+    impl Depacketizer for FrameDepacketizer {
+        fn depacketize(&mut self, packet: &Packet) -> Option<Vec<u8>> {
+            self.frame.extend_from_slice(&packet.payload);
+            if packet.marker {
+                Some(std::mem::take(&mut self.frame))
+            } else {
+                None
+            }
+        }
+
+        fn is_partition_head(&self, payload: &[u8]) -> bool {
+            !self.fragments.contains(&payload[0])
+        }
+
+        fn reset(&mut self) {
+            self.frame.clear();
+        }
+    }
+
+    fn packet(sequence_number: u16, timestamp: u32, marker: bool) -> Packet {
+        Packet {
+            marker,
+            sequence_number,
+            timestamp,
+            payload: vec![sequence_number as u8],
+            ..Packet::default()
+        }
+    }
+
+    fn sample(data: Vec<u8>, timestamp: u32, duration: u32, packets_lost: bool) -> Sample {
+        Sample {
+            data,
+            timestamp,
+            duration,
+            packets_lost,
+        }
+    }
+
+    #[test]
+    fn it_builds_frames_from_reordered_packets() {
+        let mut builder = SampleBuilder::new(FrameDepacketizer::default(), 10);
+
+        assert!(builder.push(packet(65534, 0, false)));
+        assert!(builder.push(packet(0, 0, true)));
+        assert!(builder.pop().is_none());
+
+        assert!(builder.push(packet(65535, 0, false)));
+        assert!(!builder.push(packet(65535, 0, false)));
+        // The duration of the frame is unknown until the next one
+        assert!(builder.pop().is_none());
+
+        builder.push(packet(1, 3000, true));
+        assert_eq!(
+            Some(sample(vec![254, 255, 0], 0, 3000, false)),
+            builder.pop()
+        );
+        assert!(builder.pop().is_none());
+
+        // The packets of the released frames are too late
+        assert!(!builder.push(packet(0, 0, true)));
+
+        builder.push(packet(2, 6000, true));
+        assert_eq!(Some(sample(vec![1], 3000, 3000, false)), builder.pop());
+    }
+
+    #[test]
+    fn it_drops_incomplete_frames() {
+        let mut builder = SampleBuilder::new(FrameDepacketizer::default(), 3);
+
+        builder.push(packet(10, 0, true));
+        builder.push(packet(11, 3000, false));
+        // The packet 12 of the second frame is lost
+        builder.push(packet(13, 3000, true));
+        builder.push(packet(14, 6000, true));
+
+        assert_eq!(Some(sample(vec![10], 0, 3000, false)), builder.pop());
+        assert!(builder.pop().is_none());
+
+        builder.push(packet(15, 9000, true));
+        assert_eq!(Some(sample(vec![14], 6000, 3000, true)), builder.pop());
+        assert!(builder.pop().is_none());
+
+        // The late packet is not starting a frame again
+        assert!(!builder.push(packet(12, 3000, false)));
+    }
+
+    #[test]
+    fn it_waits_for_missing_packets() {
+        let mut builder = SampleBuilder::new(FrameDepacketizer::default(), 3);
+
+        builder.push(packet(10, 0, true));
+        builder.push(packet(12, 6000, true));
+        builder.push(packet(13, 9000, true));
+
+        // The duration of the first frame depends on the missing packet
+        assert!(builder.pop().is_none());
+
+        builder.push(packet(11, 3000, true));
+        assert_eq!(Some(sample(vec![10], 0, 3000, false)), builder.pop());
+        assert_eq!(Some(sample(vec![11], 3000, 3000, false)), builder.pop());
+        assert_eq!(Some(sample(vec![12], 6000, 3000, false)), builder.pop());
+        assert!(builder.pop().is_none());
+    }
+
+    #[test]
+    fn it_drops_frames_whose_first_packets_are_lost() {
+        let depacketizer = FrameDepacketizer {
+            fragments: vec![12],
+            ..FrameDepacketizer::default()
+        };
+        let mut builder = SampleBuilder::new(depacketizer, 3);
+
+        builder.push(packet(10, 0, true));
+        // The packet 11 starting the second frame is lost
+        builder.push(packet(12, 3000, true));
+        builder.push(packet(13, 6000, true));
+        assert!(builder.pop().is_none());
+
+        builder.push(packet(14, 9000, true));
+        assert_eq!(Some(sample(vec![10], 0, 3000, false)), builder.pop());
+        assert_eq!(Some(sample(vec![13], 6000, 3000, true)), builder.pop());
+        assert!(builder.pop().is_none());
+    }
+
+    #[test]
+    fn it_resets_the_depacketizer_when_a_frame_produces_no_data() {
+        let mut builder = SampleBuilder::new(FrameDepacketizer::default(), 3);
+
+        // The first frame is completed by the next one, without a marker bit
+        builder.push(packet(10, 0, false));
+        builder.push(packet(11, 3000, true));
+        builder.push(packet(12, 6000, true));
+
+        assert_eq!(Some(sample(vec![11], 3000, 3000, true)), builder.pop());
+        assert!(builder.pop().is_none());
+    }
+
+    #[cfg(feature = "opus")]
+    #[test]
+    fn it_computes_the_duration_with_the_depacketizer() {
+        use crate::codecs::opus::OpusDepacketizer;
+
+        let mut builder = SampleBuilder::new(OpusDepacketizer::default(), 3);
+
+        let mut first = packet(0, 0, true);
+        first.payload = vec![0x78, 0x01];
+        builder.push(first);
+
+        assert_eq!(Some(sample(vec![0x78, 0x01], 0, 960, false)), builder.pop());
+    }
+}
@@ -40,6 +40,21 @@ impl Sequencer {
     }
 }
 
+/// Extends a sequence number with its roll over count, relatively to the
+/// highest extended sequence number received in the stream.
+///
+/// `None` is returned if the sequence number is older than the beginning of
+/// the stream.
+pub(crate) fn extend_sequence_number(highest: u64, sequence_number: u16) -> Option<u64> {
+    let delta = i64::from(sequence_number.wrapping_sub(highest as u16) as i16);
+
+    if delta < 0 && delta.unsigned_abs() > highest {
+        None
+    } else {
+        Some((highest as i64 + delta) as u64)
+    }
+}
+
 impl Default for Sequencer {
     fn default() -> Self {
         Self::new()